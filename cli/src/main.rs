@@ -1,7 +1,10 @@
 #![forbid(unsafe_code)]
 
-use anyhow::Result;
+use std::process;
 
-fn main() -> Result<()> {
-    generator::generate()
+fn main() {
+    // Errors are already printed by the generator in the requested message format.
+    if generator::generate().is_err() {
+        process::exit(1);
+    }
 }
@@ -0,0 +1,613 @@
+use crate::dump_config::{DumpConfig, DumpFormat};
+use crate::encode_config::EncodeConfig;
+use crate::script_config::ScriptConfig;
+use crate::util::DisplayNormalized;
+use crate::{encode, generate_internal, Config, InOutConfig, Lang, LangConfig};
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Typed API for running protox from code, e.g. a `build.rs` script.
+///
+/// ```no_run
+/// # fn main() -> anyhow::Result<()> {
+/// let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR")?);
+/// let report = generator::Builder::new("proto")
+///     .include("../proto_options/protos")
+///     .proto(generator::Lang::Rust, out_dir.join("rust-proto"))
+///     .template("templates/rust-example", out_dir.join("rust-example"))
+///     .script("scripts/flatbuffers", out_dir.join("fbs"))
+///     .clean_outputs(true)
+///     .generate()?;
+/// assert!(report.files().any(|file| file.ends_with("rust-example/mod.rs")));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Unlike [`crate::generate_with_config`], this never initializes logging, so it's safe to call
+/// multiple times and to use alongside your own logger.
+pub struct Builder {
+    config: Config,
+    emit_rerun_if_changed: bool,
+    clean_outputs: bool,
+}
+
+impl Builder {
+    /// `input` is the directory to search for proto files.
+    pub fn new(input: impl Into<PathBuf>) -> Self {
+        let mut config = Config::default();
+        config.input = input.into();
+        config.descriptor_set_path = config.default_descriptor_set_path();
+        Self {
+            config,
+            emit_rerun_if_changed: true,
+            clean_outputs: false,
+        }
+    }
+
+    /// Additional include folder passed to protoc as a `--proto_path`.
+    pub fn include(mut self, path: impl AsRef<Path>) -> Self {
+        self.config
+            .includes
+            .push(path.as_ref().display_normalized());
+        self
+    }
+
+    /// Argument passed directly to protoc, e.g. `--experimental_allow_proto3_optional`.
+    pub fn protoc_arg(mut self, arg: impl AsRef<str>) -> Self {
+        // Stored quoted to match args passed on the command line.
+        self.config
+            .extra_protoc_args
+            .push(format!("\"{}\"", arg.as_ref()));
+        self
+    }
+
+    /// Where to write the descriptor set generated by protoc. Defaults to a temporary directory.
+    pub fn descriptor_set_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.descriptor_set_path = path.into();
+        self
+    }
+
+    /// Generate protobuf code for `lang` to `output`.
+    pub fn proto(mut self, lang: Lang, output: impl Into<PathBuf>) -> Self {
+        self.config.protos.push(LangConfig {
+            lang,
+            output: output.into(),
+        });
+        self
+    }
+
+    /// Render the templates and config in `input` to `output`.
+    pub fn template(self, input: impl Into<PathBuf>, output: impl Into<PathBuf>) -> Self {
+        self.template_with_overlays(input, output, Vec::<PathBuf>::new())
+    }
+
+    /// Render the templates and config in `input` to `output`, applying each overlay file in order.
+    pub fn template_with_overlays<I, P>(
+        mut self,
+        input: impl Into<PathBuf>,
+        output: impl Into<PathBuf>,
+        overlays: I,
+    ) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.config
+            .templates
+            .push(in_out_config(input, output, overlays));
+        self
+    }
+
+    /// Render the Jinja templates and config in `input` to `output`.
+    pub fn jinja(self, input: impl Into<PathBuf>, output: impl Into<PathBuf>) -> Self {
+        self.jinja_with_overlays(input, output, Vec::<PathBuf>::new())
+    }
+
+    /// Render the Jinja templates and config in `input` to `output`, applying each overlay file in order.
+    pub fn jinja_with_overlays<I, P>(
+        mut self,
+        input: impl Into<PathBuf>,
+        output: impl Into<PathBuf>,
+        overlays: I,
+    ) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.config
+            .jinja
+            .push(in_out_config(input, output, overlays));
+        self
+    }
+
+    /// Render with the WebAssembly plugin and config in `input` to `output`.
+    pub fn wasm(self, input: impl Into<PathBuf>, output: impl Into<PathBuf>) -> Self {
+        self.wasm_with_overlays(input, output, Vec::<PathBuf>::new())
+    }
+
+    /// Render with the WebAssembly plugin and config in `input` to `output`, applying each overlay file in order.
+    pub fn wasm_with_overlays<I, P>(
+        mut self,
+        input: impl Into<PathBuf>,
+        output: impl Into<PathBuf>,
+        overlays: I,
+    ) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.config
+            .wasm
+            .push(in_out_config(input, output, overlays));
+        self
+    }
+
+    /// Render by running the process config and config in `input`, writing to `output`.
+    pub fn process(self, input: impl Into<PathBuf>, output: impl Into<PathBuf>) -> Self {
+        self.process_with_overlays(input, output, Vec::<PathBuf>::new())
+    }
+
+    /// Render by running the process config and config in `input`, writing to `output`, applying each overlay file in order.
+    pub fn process_with_overlays<I, P>(
+        mut self,
+        input: impl Into<PathBuf>,
+        output: impl Into<PathBuf>,
+        overlays: I,
+    ) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.config
+            .process
+            .push(in_out_config(input, output, overlays));
+        self
+    }
+
+    /// Render the scripts and config in `input` to `output`.
+    pub fn script(self, input: impl Into<PathBuf>, output: impl Into<PathBuf>) -> Self {
+        self.script_with_overlays(input, output, Vec::<PathBuf>::new())
+    }
+
+    /// Render the scripts and config in `input` to `output`, applying each overlay file in order.
+    pub fn script_with_overlays<I, P>(
+        mut self,
+        input: impl Into<PathBuf>,
+        output: impl Into<PathBuf>,
+        overlays: I,
+    ) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        let input = input.into();
+        let name = input
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        self.config.scripts.push(ScriptConfig {
+            name,
+            input,
+            output: output.into(),
+            overlays: overlays.into_iter().map(Into::into).collect(),
+            params: Default::default(),
+        });
+        self
+    }
+
+    /// Render the scripts and config in `input` to `output`, overriding the `params` of its
+    /// config with `params`. Scripts read them from `config.params`.
+    pub fn script_with_params<I, K, V>(
+        mut self,
+        input: impl Into<PathBuf>,
+        output: impl Into<PathBuf>,
+        params: I,
+    ) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<serde_yaml::Value>,
+    {
+        self = self.script(input, output);
+        if let Some(script) = self.config.scripts.last_mut() {
+            script.params.extend(
+                params
+                    .into_iter()
+                    .map(|(key, value)| (key.into(), value.into())),
+            );
+        }
+        self
+    }
+
+    /// Search `path` for rhai modules imported by every script, after each script's own
+    /// directory and configured module paths.
+    pub fn script_module_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.script_module_paths.push(path.into());
+        self
+    }
+
+    /// Encode the proto text-format file `target` as `message_type`. The result is written to
+    /// a file inside `output`.
+    pub fn encode(
+        mut self,
+        target: impl Into<PathBuf>,
+        message_type: impl Into<String>,
+        output: impl Into<PathBuf>,
+    ) -> Self {
+        self.config.encode.push(EncodeConfig {
+            target: target.into(),
+            message_type: message_type.into(),
+            output: output.into(),
+        });
+        self
+    }
+
+    /// Write the contexts passed to scripts and templates to `output` for debugging.
+    pub fn dump_context(mut self, format: DumpFormat, output: impl Into<PathBuf>) -> Self {
+        self.config.dump_context = Some(DumpConfig {
+            format,
+            output: output.into(),
+        });
+        self
+    }
+
+    /// Render with `jobs` threads instead of one per CPU. Output doesn't depend on it.
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.config.jobs = Some(jobs);
+        self
+    }
+
+    /// Print `cargo:rerun-if-changed` for every input when generating. Enabled by default.
+    pub fn emit_rerun_if_changed(mut self, emit: bool) -> Self {
+        self.emit_rerun_if_changed = emit;
+        self
+    }
+
+    /// Delete proto, script, template, jinja, wasm, process and context dump output directories
+    /// before generating.
+    /// Script and template outputs must be empty, which is otherwise an error. This also removes
+    /// stale files from previous runs. Disabled by default.
+    pub fn clean_outputs(mut self, clean: bool) -> Self {
+        self.clean_outputs = clean;
+        self
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn generate(self) -> Result<Report> {
+        if self.emit_rerun_if_changed {
+            for path in input_paths(&self.config) {
+                println!("cargo:rerun-if-changed={}", path.display_normalized());
+            }
+        }
+        if self.clean_outputs {
+            for output in cleanable_outputs(&self.config) {
+                clean_dir(output)?;
+            }
+        }
+        generate_internal(&self.config)?;
+        Report::from_config(&self.config)
+    }
+}
+
+/// Files written by each generator during [`Builder::generate`].
+#[derive(Debug)]
+pub struct Report {
+    pub outputs: Vec<GeneratedOutput>,
+}
+
+#[derive(Debug)]
+pub struct GeneratedOutput {
+    pub kind: GeneratorKind,
+    /// Output directory of the generator.
+    pub output: PathBuf,
+    /// Files written inside `output`, sorted.
+    pub files: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeneratorKind {
+    Proto(Lang),
+    Template,
+    Jinja,
+    Wasm,
+    Process,
+    Script(String),
+    Encode,
+    ContextDump,
+}
+
+impl Report {
+    fn from_config(config: &Config) -> Result<Self> {
+        let mut outputs = Vec::new();
+        // protoc doesn't say what it wrote, so its output directories are listed instead.
+        for proto in &config.protos {
+            outputs.push(GeneratedOutput::from_dir(
+                GeneratorKind::Proto(proto.lang.clone()),
+                &proto.output,
+            )?);
+        }
+        for template in &config.templates {
+            outputs.push(GeneratedOutput::written(
+                config,
+                GeneratorKind::Template,
+                &template.output,
+            ));
+        }
+        for jinja in &config.jinja {
+            outputs.push(GeneratedOutput::written(
+                config,
+                GeneratorKind::Jinja,
+                &jinja.output,
+            ));
+        }
+        for wasm in &config.wasm {
+            outputs.push(GeneratedOutput::written(
+                config,
+                GeneratorKind::Wasm,
+                &wasm.output,
+            ));
+        }
+        for process in &config.process {
+            outputs.push(GeneratedOutput::written(
+                config,
+                GeneratorKind::Process,
+                &process.output,
+            ));
+        }
+        for script in &config.scripts {
+            outputs.push(GeneratedOutput::written(
+                config,
+                GeneratorKind::Script(script.name.clone()),
+                &script.output,
+            ));
+        }
+        for encode_config in &config.encode {
+            outputs.push(GeneratedOutput {
+                kind: GeneratorKind::Encode,
+                output: encode_config.output.clone(),
+                files: vec![encode::output_file_path(encode_config)],
+            });
+        }
+        if let Some(dump_config) = &config.dump_context {
+            outputs.push(GeneratedOutput::written(
+                config,
+                GeneratorKind::ContextDump,
+                &dump_config.output,
+            ));
+        }
+        Ok(Self { outputs })
+    }
+
+    /// All generated files across all generators.
+    pub fn files(&self) -> impl Iterator<Item = &PathBuf> {
+        self.outputs.iter().flat_map(|output| output.files.iter())
+    }
+}
+
+impl GeneratedOutput {
+    /// Files the renderers recorded writing to `output`, ignoring anything else in it.
+    fn written(config: &Config, kind: GeneratorKind, output: &Path) -> Self {
+        Self {
+            kind,
+            output: output.to_path_buf(),
+            files: config.written_files(output),
+        }
+    }
+
+    fn from_dir(kind: GeneratorKind, output: &Path) -> Result<Self> {
+        let mut files = Vec::new();
+        if output.exists() {
+            for entry in WalkDir::new(output).sort_by_file_name() {
+                let entry = entry?;
+                if entry.file_type().is_file() {
+                    files.push(entry.into_path());
+                }
+            }
+        }
+        Ok(Self {
+            kind,
+            output: output.to_path_buf(),
+            files,
+        })
+    }
+}
+
+fn in_out_config<I, P>(
+    input: impl Into<PathBuf>,
+    output: impl Into<PathBuf>,
+    overlays: I,
+) -> InOutConfig
+where
+    I: IntoIterator<Item = P>,
+    P: Into<PathBuf>,
+{
+    InOutConfig {
+        input: input.into(),
+        output: output.into(),
+        overlays: overlays.into_iter().map(Into::into).collect(),
+    }
+}
+
+fn input_paths(config: &Config) -> Vec<PathBuf> {
+    let mut paths = vec![config.input.clone()];
+    paths.extend(config.includes.iter().map(PathBuf::from));
+    for template in &config.templates {
+        paths.push(template.input.clone());
+        paths.extend(template.overlays.iter().cloned());
+    }
+    for jinja in &config.jinja {
+        paths.push(jinja.input.clone());
+        paths.extend(jinja.overlays.iter().cloned());
+    }
+    for wasm in &config.wasm {
+        paths.push(wasm.input.clone());
+        paths.extend(wasm.overlays.iter().cloned());
+    }
+    for process in &config.process {
+        paths.push(process.input.clone());
+        paths.extend(process.overlays.iter().cloned());
+    }
+    for script in &config.scripts {
+        paths.push(script.input.clone());
+        paths.extend(script.overlays.iter().cloned());
+    }
+    paths.extend(config.script_module_paths.iter().cloned());
+    for encode_config in &config.encode {
+        paths.push(encode_config.target.clone());
+    }
+    paths
+}
+
+fn cleanable_outputs(config: &Config) -> Vec<&PathBuf> {
+    let mut outputs = Vec::new();
+    outputs.extend(config.protos.iter().map(|x| &x.output));
+    outputs.extend(config.templates.iter().map(|x| &x.output));
+    outputs.extend(config.jinja.iter().map(|x| &x.output));
+    outputs.extend(config.wasm.iter().map(|x| &x.output));
+    outputs.extend(config.process.iter().map(|x| &x.output));
+    outputs.extend(config.scripts.iter().map(|x| &x.output));
+    outputs.extend(config.dump_context.iter().map(|x| &x.output));
+    outputs
+}
+
+fn clean_dir(path: &Path) -> Result<()> {
+    if path.exists() {
+        fs::remove_dir_all(path).with_context(|| {
+            format!(
+                "Failed to clean output directory '{}'",
+                path.display_normalized()
+            )
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builder::{input_paths, Builder, GeneratedOutput, GeneratorKind, Report};
+    use crate::Lang;
+    use anyhow::Result;
+    use std::fs;
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    #[test]
+    fn descriptor_set_path_defaults_to_temp_dir() {
+        let builder = Builder::new("input");
+        assert!(builder.config().descriptor_set_path.is_absolute());
+    }
+
+    #[test]
+    fn collects_configs() {
+        let builder = Builder::new("input")
+            .include("include")
+            .protoc_arg("--arg")
+            .proto(Lang::Rust, "out/rust")
+            .template("templates/a", "out/a")
+            .jinja_with_overlays("templates/j", "out/j", ["j.yml"])
+            .wasm("plugins/w", "out/w")
+            .process("plugins/p", "out/p")
+            .script_with_overlays("scripts/b", "out/b", ["overlay.yml"])
+            .script_with_params("scripts/c", "out/c", [("target", "godot")])
+            .script_module_path("scripts/shared")
+            .encode("data.txtpb", "pkg.Msg", "out/encoded")
+            .jobs(2);
+        let config = builder.config();
+        assert_eq!(config.includes, vec!["include".to_owned()]);
+        assert_eq!(config.extra_protoc_args, vec!["\"--arg\"".to_owned()]);
+        assert_eq!(config.protos[0].lang, Lang::Rust);
+        assert_eq!(config.templates[0].output, PathBuf::from("out/a"));
+        assert_eq!(config.jinja[0].output, PathBuf::from("out/j"));
+        assert_eq!(config.jinja[0].overlays, vec![PathBuf::from("j.yml")]);
+        assert!(config.templates[0].overlays.is_empty());
+        assert_eq!(config.wasm[0].output, PathBuf::from("out/w"));
+        assert_eq!(config.process[0].output, PathBuf::from("out/p"));
+        assert_eq!(config.scripts[0].name, "b");
+        assert_eq!(
+            config.scripts[0].overlays,
+            vec![PathBuf::from("overlay.yml")]
+        );
+        assert_eq!(
+            config.scripts[1].params["target"],
+            serde_yaml::Value::from("godot")
+        );
+        assert!(config.scripts[0].params.is_empty());
+        assert_eq!(
+            config.script_module_paths,
+            vec![PathBuf::from("scripts/shared")]
+        );
+        assert_eq!(config.encode[0].message_type, "pkg.Msg");
+        assert_eq!(config.jobs, Some(2));
+    }
+
+    #[test]
+    fn input_paths_include_all_inputs() {
+        let builder = Builder::new("input")
+            .include("include")
+            .template_with_overlays("templates/a", "out/a", ["a.yml"])
+            .jinja("templates/j", "out/j")
+            .wasm_with_overlays("plugins/w", "out/w", ["w.yml"])
+            .process_with_overlays("plugins/p", "out/p", ["p.yml"])
+            .script_with_overlays("scripts/b", "out/b", ["overlay.yml"])
+            .script_module_path("scripts/shared")
+            .encode("data.txtpb", "pkg.Msg", "out/encoded");
+        assert_eq!(
+            input_paths(builder.config()),
+            [
+                "input",
+                "include",
+                "templates/a",
+                "a.yml",
+                "templates/j",
+                "plugins/w",
+                "w.yml",
+                "plugins/p",
+                "p.yml",
+                "scripts/b",
+                "overlay.yml",
+                "scripts/shared",
+                "data.txtpb"
+            ]
+            .iter()
+            .map(PathBuf::from)
+            .collect::<Vec<PathBuf>>()
+        );
+    }
+
+    #[test]
+    fn generated_output_lists_files_recursively() -> Result<()> {
+        let dir = tempdir()?;
+        fs::create_dir_all(dir.path().join("sub"))?;
+        fs::write(dir.path().join("b.txt"), "")?;
+        fs::write(dir.path().join("sub/a.txt"), "")?;
+        let output = GeneratedOutput::from_dir(GeneratorKind::Proto(Lang::Rust), dir.path())?;
+        assert_eq!(
+            output.files,
+            vec![dir.path().join("b.txt"), dir.path().join("sub/a.txt")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn report_lists_only_written_files() -> Result<()> {
+        let dir = tempdir()?;
+        let output = dir.path().join("out");
+        fs::create_dir_all(&output)?;
+        fs::write(output.join("stale.txt"), "")?;
+        let builder = Builder::new("input").template("templates/a", &output);
+        let config = builder.config();
+        config.record_written_files(&output, vec![output.join("a.txt")]);
+        let report = Report::from_config(config)?;
+        assert_eq!(report.outputs[0].kind, GeneratorKind::Template);
+        assert_eq!(
+            report.files().collect::<Vec<&PathBuf>>(),
+            vec![&output.join("a.txt")]
+        );
+        Ok(())
+    }
+}
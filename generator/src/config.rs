@@ -1,15 +1,23 @@
+use crate::diagnostic::MessageFormat;
+use crate::dump_config::DumpConfig;
 use crate::encode_config::EncodeConfig;
 use crate::idl::Idl;
 use crate::in_out_config::InOutConfig;
 use crate::lang::Lang;
 use crate::lang_config::LangConfig;
+use crate::manifest::{Manifest, MANIFEST_FILE_NAMES};
 use crate::protoc;
+use crate::renderer::process::PROCESS_CONFIG_FILE_NAMES;
+use crate::renderer::wasm::MODULE_FILE_NAME;
 use crate::script_config::ScriptConfig;
 use anyhow::{anyhow, Context, Result};
 use clap::{crate_version, App, Arg, ArgMatches, Values};
+use std::collections::BTreeMap;
 use std::env;
 use std::ffi::OsString;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
 use tempfile::{tempdir, TempDir};
 
 pub const APP_NAME: &str = "protox";
@@ -20,27 +28,44 @@ pub const SCRIPT: &str = "script";
 pub const SCRIPT_IN: &str = "script-in";
 pub const SCRIPT_OUT: &str = "script-out";
 pub const SCRIPT_OVERLAY: &str = "script-overlay";
+pub const SCRIPT_MODULE_PATH: &str = "script-module-path";
+pub const SCRIPT_PARAM: &str = "script-param";
 pub const TEMPLATE: &str = "template";
+pub const JINJA: &str = "jinja";
+pub const WASM: &str = "wasm";
+pub const PROCESS: &str = "process";
 pub const ENCODE: &str = "encode";
 pub const BYPASS: &str = "bypass";
 pub const TEMPLATE_ROOT: &str = "template-root";
 pub const SCRIPT_ROOT: &str = "script-root";
+pub const PLUGIN_ROOT: &str = "plugin-root";
 pub const OUTPUT_ROOT: &str = "output-root";
 pub const INCLUDES: &str = "includes";
 pub const INIT_SCRIPT: &str = "init-script";
 pub const INIT_TEMPLATE: &str = "init-template";
+pub const INIT_JINJA: &str = "init-jinja";
 pub const DESCRIPTOR_SET_OUT: &str = "descriptor-set-out";
 pub const PROTOC_ARGS: &str = "protoc-args";
+pub const WATCH: &str = "watch";
+pub const JOBS: &str = "jobs";
+pub const DUMP_CONTEXT: &str = "dump-context";
+pub const MANIFEST: &str = "manifest";
+pub const MESSAGE_FORMAT: &str = "message-format";
 pub const LONG_HELP_NEWLINE: &str = "\n\n";
 
-const MAIN_OPTS: &[&str; 7] = &[
+const MAIN_OPTS: &[&str; 12] = &[
     PROTO,
     TEMPLATE,
+    JINJA,
+    WASM,
+    PROCESS,
     SCRIPT,
     BYPASS,
     ENCODE,
+    DUMP_CONTEXT,
     INIT_SCRIPT,
     INIT_TEMPLATE,
+    INIT_JINJA,
 ];
 
 const DISPLAY_ORDER_DEFAULT: usize = 990;
@@ -70,12 +95,11 @@ where
 
             Arg::new(INPUT)
                 .display_order(1)
-                .help("File path to search for protobuf IDL files.")
+                .help("File path to search for protobuf IDL files. Required, unless specified in the manifest.")
                 .default_short()
                 .long(INPUT)
                 .takes_value(true)
-                .required(true)
-                .conflicts_with_all(&[INIT_SCRIPT, INIT_TEMPLATE]),
+                .conflicts_with_all(&[INIT_SCRIPT, INIT_TEMPLATE, INIT_JINJA]),
 
             Arg::new(SCRIPT)
                 .display_order(display_order())
@@ -84,14 +108,13 @@ where
                     &format!("Must also use either --{}, or --{} with an absolute path.", SCRIPT_ROOT, SCRIPT_IN),
                     &format!("Must also use either --{}, or --{} with an absolute path.", OUTPUT_ROOT, SCRIPT_OUT),
                     "Scripts use the language rhai (https://rhai.rs/).",
-                    &format!("See also: --{}.", SCRIPT_OVERLAY),
+                    &format!("See also: --{}, --{}, --{}.", SCRIPT_OVERLAY, SCRIPT_PARAM, SCRIPT_MODULE_PATH),
                 ]).as_str())
                 .default_short()
                 .long(SCRIPT)
                 .value_names(&["NAME"])
                 .multiple_occurrences(true)
-                .required_unless_present_any(all_except(MAIN_OPTS, SCRIPT))
-                .conflicts_with_all(&[INIT_SCRIPT, INIT_TEMPLATE]),
+                .conflicts_with_all(&[INIT_SCRIPT, INIT_TEMPLATE, INIT_JINJA]),
 
             Arg::new(SCRIPT_ROOT)
                 .display_order(display_order())
@@ -130,6 +153,29 @@ where
                 .value_names(&["NAME", "PATH"])
                 .multiple_occurrences(true),
 
+            Arg::new(SCRIPT_PARAM)
+                .display_order(display_order())
+                .long_help(join_help(&[
+                    &format!("Sets the param KEY to VALUE for the code generated by --{} NAME, overriding the same key in the \"params\" of its configuration. Scripts read params from `config.params`.", SCRIPT),
+                    "VALUE is parsed as yaml, so e.g. true and 5 are a bool and an int. Anything that isn't valid yaml is a string.",
+                    "NAME can also be a script declared in the manifest. It's an error if no script is called NAME.",
+                    &format!("e.g. --{} csharp --{} csharp target=unity", SCRIPT, SCRIPT_PARAM),
+                ]).as_str())
+                .long(SCRIPT_PARAM)
+                .value_names(&["NAME", "KEY=VALUE"])
+                .multiple_occurrences(true),
+
+            Arg::new(SCRIPT_MODULE_PATH)
+                .display_order(display_order())
+                .long_help(join_help(&[
+                    &format!("Adds PATH to the directories searched for rhai modules imported by every --{}, so modules can be shared between script sets.", SCRIPT),
+                    "Modules are searched for in the script directory first, then the \"scripted.module_paths\" of its configuration, then each PATH in the order specified, and last the bundled \"protox/\" modules.",
+                    "If PATH is a relative path, it is evaluated relative to the working directory.",
+                ]).as_str())
+                .long(SCRIPT_MODULE_PATH)
+                .value_name("PATH")
+                .multiple_occurrences(true),
+
             Arg::new(PROTO)
                 .display_order(display_order())
                 .long_help(join_help(&[
@@ -141,8 +187,7 @@ where
                 .long(PROTO)
                 .value_names(&["LANG", "OUTPUT"])
                 .multiple_occurrences(true)
-                .required_unless_present_any(all_except(MAIN_OPTS, PROTO))
-                .conflicts_with_all(&[INIT_SCRIPT, INIT_TEMPLATE]),
+                .conflicts_with_all(&[INIT_SCRIPT, INIT_TEMPLATE, INIT_JINJA]),
 
             Arg::new(TEMPLATE)
                 .display_order(display_order())
@@ -157,8 +202,47 @@ where
                 .long(TEMPLATE)
                 .value_names(&["INPUT", "OUTPUT"])
                 .multiple_occurrences(true)
-                .required_unless_present_any(all_except(MAIN_OPTS, TEMPLATE))
-                .conflicts_with_all(&[INIT_SCRIPT, INIT_TEMPLATE]),
+                .conflicts_with_all(&[INIT_SCRIPT, INIT_TEMPLATE, INIT_JINJA]),
+
+            Arg::new(JINJA)
+                .display_order(display_order())
+                .long_help(join_help(&[
+                    "Code will be generated for the Jinja templates and configuration found inside the INPUT folder, and written to directory located at OUTPUT.",
+                    "Templates use the Jinja2 template language as implemented by MiniJinja (https://docs.rs/minijinja/).",
+                    &format!("If INPUT is a relative path, it is evaluated relative to --{}.", TEMPLATE_ROOT),
+                    &format!("If OUTPUT is a relative path, it is evaluated relative to --{}.", OUTPUT_ROOT),
+                ]).as_str())
+                .default_short()
+                .long(JINJA)
+                .value_names(&["INPUT", "OUTPUT"])
+                .multiple_occurrences(true)
+                .conflicts_with_all(&[INIT_SCRIPT, INIT_TEMPLATE, INIT_JINJA]),
+
+            Arg::new(WASM)
+                .display_order(display_order())
+                .long_help(join_help(&[
+                    &format!("Code will be generated by the WebAssembly plugin ({}) and configuration found inside the INPUT folder, and written to directory located at OUTPUT.", MODULE_FILE_NAME),
+                    "Plugins run sandboxed inside protox, without filesystem or network access.",
+                    &format!("If INPUT is a relative path, it is evaluated relative to --{}.", PLUGIN_ROOT),
+                    &format!("If OUTPUT is a relative path, it is evaluated relative to --{}.", OUTPUT_ROOT),
+                ]).as_str())
+                .long(WASM)
+                .value_names(&["INPUT", "OUTPUT"])
+                .multiple_occurrences(true)
+                .conflicts_with_all(&[INIT_SCRIPT, INIT_TEMPLATE, INIT_JINJA]),
+
+            Arg::new(PROCESS)
+                .display_order(display_order())
+                .long_help(join_help(&[
+                    &format!("Code will be generated by running the command in the process config ({}) found inside the INPUT folder, using the configuration there, and written to directory located at OUTPUT.", PROCESS_CONFIG_FILE_NAMES.join(", ")),
+                    "The command receives every context as json on stdin, and answers with the files to write as json on stdout.",
+                    &format!("If INPUT is a relative path, it is evaluated relative to --{}.", PLUGIN_ROOT),
+                    &format!("If OUTPUT is a relative path, it is evaluated relative to --{}.", OUTPUT_ROOT),
+                ]).as_str())
+                .long(PROCESS)
+                .value_names(&["INPUT", "OUTPUT"])
+                .multiple_occurrences(true)
+                .conflicts_with_all(&[INIT_SCRIPT, INIT_TEMPLATE, INIT_JINJA]),
 
             Arg::new(ENCODE)
                 .display_order(display_order())
@@ -171,25 +255,40 @@ where
                 .long(ENCODE)
                 .value_names(&["TEXT_PROTO", "MESSAGE_TYPE", "OUTPUT"])
                 .multiple_occurrences(true)
-                .required_unless_present_any(all_except(MAIN_OPTS, ENCODE))
-                .conflicts_with_all(&[INIT_SCRIPT, INIT_TEMPLATE]),
+                .conflicts_with_all(&[INIT_SCRIPT, INIT_TEMPLATE, INIT_JINJA]),
+
+            Arg::new(DUMP_CONTEXT)
+                .display_order(display_order())
+                .long_help(join_help(&[
+                    "Writes the contexts passed to scripts and templates to the directory located at OUTPUT, in FORMAT (json or yaml). This includes overlays and options, which are useful for debugging why a script or template isn't rendering what you expect.",
+                    &format!("Each --{}, --{}, --{}, --{} and --{} set is written to its own directory inside OUTPUT, using that set's configuration and overlays. If there are none, the default configuration is used.", TEMPLATE, JINJA, WASM, PROCESS, SCRIPT),
+                    &format!("If OUTPUT is a relative path, it is evaluated relative to --{}.", OUTPUT_ROOT),
+                ]).as_str())
+                .long(DUMP_CONTEXT)
+                .value_names(&["FORMAT", "OUTPUT"])
+                .conflicts_with_all(&[INIT_SCRIPT, INIT_TEMPLATE, INIT_JINJA]),
 
             Arg::new(BYPASS)
                 .display_order(display_order())
                 .long_help("Bypass protox additional functionality and run protoc directly.")
                 .default_short()
                 .long(BYPASS)
-                .required_unless_present_any(all_except(MAIN_OPTS, BYPASS))
                 .conflicts_with(INIT_SCRIPT)
-                .conflicts_with_all(&[INIT_SCRIPT, INIT_TEMPLATE])
+                .conflicts_with_all(&[INIT_SCRIPT, INIT_TEMPLATE, INIT_JINJA])
                 .conflicts_with_all(&all_except(MAIN_OPTS, BYPASS)),
 
             Arg::new(TEMPLATE_ROOT)
                 .display_order(display_order())
-                .help(format!("All non-absolute --{} INPUT paths will be prefixed with this path. Required if any --{} INPUT paths are relative.", TEMPLATE, TEMPLATE).as_str())
+                .help(format!("All non-absolute --{} and --{} INPUT paths will be prefixed with this path. Required if any of their INPUT paths are relative.", TEMPLATE, JINJA).as_str())
                 .long(TEMPLATE_ROOT)
                 .takes_value(true),
 
+            Arg::new(PLUGIN_ROOT)
+                .display_order(display_order())
+                .help(format!("All non-absolute --{} and --{} INPUT paths will be prefixed with this path. Required if any of their INPUT paths are relative.", WASM, PROCESS).as_str())
+                .long(PLUGIN_ROOT)
+                .takes_value(true),
+
             Arg::new(OUTPUT_ROOT)
                 .display_order(display_order())
                 .help("All non-absolute output paths will be prefixed with this path. Required if any OUTPUT paths are relative.")
@@ -210,7 +309,7 @@ where
                 .long(INIT_SCRIPT)
                 .takes_value(true)
                 .value_name("TARGET")
-                .conflicts_with(INIT_TEMPLATE),
+                .conflicts_with_all(&[INIT_TEMPLATE, INIT_JINJA]),
 
             Arg::new(INIT_TEMPLATE)
                 .display_order(display_order())
@@ -218,7 +317,59 @@ where
                 .long(INIT_TEMPLATE)
                 .takes_value(true)
                 .value_name("TARGET")
-                .conflicts_with(INIT_SCRIPT),
+                .conflicts_with_all(&[INIT_SCRIPT, INIT_JINJA]),
+
+            Arg::new(INIT_JINJA)
+                .display_order(display_order())
+                .help(format!("Initialize the TARGET directory as a new Jinja template rendering target with the basic input files required for running protox with --{}.", JINJA).as_str())
+                .long(INIT_JINJA)
+                .takes_value(true)
+                .value_name("TARGET")
+                .conflicts_with_all(&[INIT_SCRIPT, INIT_TEMPLATE]),
+
+            Arg::new(MANIFEST)
+                .display_order(display_order())
+                .long_help(join_help(&[
+                    &format!("Path to a manifest file declaring the same options available on the command line, so they don't need to be repeated for each run. Supports yaml or toml. By default, protox looks for one of {} in the working directory.", MANIFEST_FILE_NAMES.join(", ")),
+                    "Relative paths in the manifest are evaluated relative to the manifest's directory.",
+                    "Options passed on the command line override single values in the manifest (e.g. the roots), and add to lists (e.g. generator targets).",
+                ]).as_str())
+                .long(MANIFEST)
+                .takes_value(true)
+                .value_name("PATH")
+                .conflicts_with_all(&[INIT_SCRIPT, INIT_TEMPLATE, INIT_JINJA]),
+
+            Arg::new(WATCH)
+                .display_order(display_order())
+                .long_help(join_help(&[
+                    &format!("After generating, keep running and watch --{}, --{}, all script and template input directories and overlay files for changes. Only the generators affected by a change are rerun.", INPUT, INCLUDES),
+                    "Errors are printed and watching continues, so they can be fixed without restarting.",
+                    "Script and template output directories are cleared before each regeneration, so they must be empty when starting.",
+                ]).as_str())
+                .long(WATCH)
+                .conflicts_with_all(&[BYPASS, INIT_SCRIPT, INIT_TEMPLATE, INIT_JINJA]),
+
+            Arg::new(JOBS)
+                .display_order(display_order())
+                .long_help(join_help(&[
+                    "Number of threads used to render files and run generators in parallel. Defaults to the number of CPUs.",
+                    "Output and log order are the same for any N, so --jobs 1 is only needed to debug a renderer one file at a time.",
+                ]).as_str())
+                .long(JOBS)
+                .takes_value(true)
+                .value_name("N"),
+
+            Arg::new(MESSAGE_FORMAT)
+                .display_order(DISPLAY_ORDER_DEFAULT)
+                .long_help(join_help(&[
+                    "How errors and warnings are printed. 'human' prints them to stderr with the file, line and column they point at. 'json' prints one json object per line to stdout, for editor and CI integration.",
+                    "Each object has the fields: severity, message, path, line, column and notes. Fields that aren't known are null.",
+                ]).as_str())
+                .long(MESSAGE_FORMAT)
+                .takes_value(true)
+                .value_name("FORMAT")
+                .possible_values(["human", "json"])
+                .default_value("human"),
 
             Arg::new(DESCRIPTOR_SET_OUT)
                 .display_order(DISPLAY_ORDER_DEFAULT)
@@ -255,14 +406,32 @@ pub struct Config {
     pub input: PathBuf,
     pub protos: Vec<LangConfig>,
     pub templates: Vec<InOutConfig>,
+    pub jinja: Vec<InOutConfig>,
+    pub wasm: Vec<InOutConfig>,
+    pub process: Vec<InOutConfig>,
     pub scripts: Vec<ScriptConfig>,
+    pub script_module_paths: Vec<PathBuf>,
     pub encode: Vec<EncodeConfig>,
+    pub dump_context: Option<DumpConfig>,
     pub bypass: bool,
     pub includes: Vec<String>,
     pub init_script_target: Option<PathBuf>,
     pub init_template_target: Option<PathBuf>,
+    pub init_jinja_target: Option<PathBuf>,
     pub descriptor_set_path: PathBuf,
     pub extra_protoc_args: Vec<String>,
+    pub watch: bool,
+    /// Threads used for rendering, or one per CPU if `None`.
+    pub jobs: Option<usize>,
+    pub message_format: MessageFormat,
+    /// Manifest the config was loaded from, if any.
+    pub manifest_path: Option<PathBuf>,
+
+    /// Args the config was parsed from, so it can be reloaded when the manifest changes.
+    args: Option<ArgMatches>,
+
+    /// Files written by the last render to each output directory.
+    written_files: Mutex<BTreeMap<PathBuf, Vec<PathBuf>>>,
 
     // Owned here to keep alive for full program execution.
     #[allow(dead_code)]
@@ -276,14 +445,26 @@ impl Default for Config {
             input: Default::default(),
             protos: vec![],
             templates: vec![],
+            jinja: vec![],
+            wasm: vec![],
+            process: vec![],
             scripts: vec![],
+            script_module_paths: vec![],
             encode: vec![],
+            dump_context: None,
             bypass: false,
             includes: vec![],
             init_script_target: None,
             init_template_target: None,
+            init_jinja_target: None,
             descriptor_set_path: Default::default(),
             extra_protoc_args: vec![],
+            watch: false,
+            jobs: None,
+            message_format: MessageFormat::default(),
+            manifest_path: None,
+            args: None,
+            written_files: Default::default(),
             intermediate_dir: tempdir().unwrap(),
         }
     }
@@ -299,40 +480,197 @@ impl Config {
 
     pub fn from_args(args: &ArgMatches) -> Result<Self> {
         let intermediate_dir = tempdir()?;
-        let input = parse_optional_path_from_arg(INPUT, &args)?.unwrap_or(PathBuf::new());
-        let output_root = parse_optional_path_from_arg(OUTPUT_ROOT, &args)?;
-        let template_root = parse_optional_path_from_arg(TEMPLATE_ROOT, &args)?;
-        let script_root = parse_optional_path_from_arg(SCRIPT_ROOT, &args)?;
-        let descriptor_set_path = parse_descriptor_path(intermediate_dir.path(), &args);
-        let config = Self {
+        let init_script_target = parse_optional_path_from_arg(INIT_SCRIPT, &args)?;
+        let init_template_target = parse_optional_path_from_arg(INIT_TEMPLATE, &args)?;
+        let init_jinja_target = parse_optional_path_from_arg(INIT_JINJA, &args)?;
+        let manifest = if init_script_target.is_some()
+            || init_template_target.is_some()
+            || init_jinja_target.is_some()
+        {
+            Manifest::default()
+        } else {
+            parse_manifest(&args)?
+        };
+        let input = parse_optional_path_from_arg(INPUT, &args)?
+            .or(manifest.input())
+            .unwrap_or(PathBuf::new());
+        let output_root =
+            parse_optional_path_from_arg(OUTPUT_ROOT, &args)?.or(manifest.output_root());
+        let template_root =
+            parse_optional_path_from_arg(TEMPLATE_ROOT, &args)?.or(manifest.template_root());
+        let script_root =
+            parse_optional_path_from_arg(SCRIPT_ROOT, &args)?.or(manifest.script_root());
+        let plugin_root =
+            parse_optional_path_from_arg(PLUGIN_ROOT, &args)?.or(manifest.plugin_root());
+        let descriptor_set_path = parse_descriptor_path(
+            intermediate_dir.path(),
+            &args,
+            manifest.descriptor_set_out(),
+        );
+        let mut config = Self {
             idl: Idl::from_args(&args)?,
             input,
-            protos: parse_protos(&args, output_root.as_ref())?,
-            templates: parse_in_out_configs(
-                TEMPLATE,
-                &args,
-                template_root.as_ref(),
-                output_root.as_ref(),
-            )?,
-            scripts: parse_script_configs(&args, script_root.as_ref(), output_root.as_ref())?,
-            encode: parse_encode_configs(args, output_root.as_ref())?,
+            protos: [
+                manifest.protos(output_root.as_ref())?,
+                parse_protos(&args, output_root.as_ref())?,
+            ]
+            .concat(),
+            templates: [
+                manifest.templates(template_root.as_ref(), output_root.as_ref())?,
+                parse_in_out_configs(
+                    TEMPLATE,
+                    &args,
+                    template_root.as_ref(),
+                    output_root.as_ref(),
+                )?,
+            ]
+            .concat(),
+            jinja: [
+                manifest.jinja(template_root.as_ref(), output_root.as_ref())?,
+                parse_in_out_configs(JINJA, &args, template_root.as_ref(), output_root.as_ref())?,
+            ]
+            .concat(),
+            wasm: [
+                manifest.wasm(plugin_root.as_ref(), output_root.as_ref())?,
+                parse_in_out_configs(WASM, &args, plugin_root.as_ref(), output_root.as_ref())?,
+            ]
+            .concat(),
+            process: [
+                manifest.process(plugin_root.as_ref(), output_root.as_ref())?,
+                parse_in_out_configs(PROCESS, &args, plugin_root.as_ref(), output_root.as_ref())?,
+            ]
+            .concat(),
+            scripts: [
+                manifest.scripts(script_root.as_ref(), output_root.as_ref())?,
+                parse_script_configs(&args, script_root.as_ref(), output_root.as_ref())?,
+            ]
+            .concat(),
+            script_module_paths: [
+                manifest.script_module_paths(),
+                parse_script_module_paths(&args)?,
+            ]
+            .concat(),
+            encode: [
+                manifest.encode(output_root.as_ref())?,
+                parse_encode_configs(args, output_root.as_ref())?,
+            ]
+            .concat(),
+            dump_context: match parse_dump_config(args, output_root.as_ref())? {
+                None => manifest.dump_context(output_root.as_ref())?,
+                Some(dump_config) => Some(dump_config),
+            },
             bypass: args.is_present(BYPASS),
-            includes: parse_includes(&args),
-            init_script_target: parse_optional_path_from_arg(INIT_SCRIPT, &args)?,
-            init_template_target: parse_optional_path_from_arg(INIT_TEMPLATE, &args)?,
+            includes: [manifest.includes(), parse_includes(&args)].concat(),
+            init_script_target,
+            init_template_target,
+            init_jinja_target,
             descriptor_set_path,
-            extra_protoc_args: parse_extra_protoc_args(&args),
+            extra_protoc_args: [manifest.protoc_args(), parse_extra_protoc_args(&args)].concat(),
+            watch: args.is_present(WATCH),
+            jobs: parse_jobs(&args)?.or(manifest.jobs()),
+            message_format: parse_message_format(&args)?,
+            manifest_path: manifest.file_path().map(Path::to_path_buf),
+            args: Some(args.clone()),
+            written_files: Default::default(),
             intermediate_dir,
         };
+        apply_script_params(&mut config.scripts, &args)?;
+        check_required(&config)?;
         check_proto_supported_languages(&config)?;
         Ok(config)
     }
 
+    /// Parses the same args again, picking up any changes to the manifest.
+    pub(crate) fn reload(&self) -> Result<Self> {
+        match &self.args {
+            None => Err(anyhow!(
+                "Only configs parsed from command line args can be reloaded."
+            )),
+            Some(args) => Config::from_args(args),
+        }
+    }
+
+    /// Replaces the files recorded for `output` with those a render just wrote.
+    pub(crate) fn record_written_files(&self, output: &Path, files: Vec<PathBuf>) {
+        self.written_files
+            .lock()
+            .unwrap()
+            .insert(output.to_path_buf(), files);
+    }
+
+    /// Files recorded for `output` and any output directory inside it, sorted.
+    pub(crate) fn written_files(&self, output: &Path) -> Vec<PathBuf> {
+        let mut files = self
+            .written_files
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(path, _)| path.starts_with(output))
+            .flat_map(|(_, files)| files.iter().cloned())
+            .collect::<Vec<PathBuf>>();
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    /// Descriptor set path inside the temp dir owned by this config.
+    pub(crate) fn default_descriptor_set_path(&self) -> PathBuf {
+        self.intermediate_dir
+            .path()
+            .join(DEFAULT_DESCRIPTOR_SET_FILENAME)
+    }
+
     pub fn requires_descriptor_set(&self) -> bool {
         self.protos.iter().find(|x| x.lang == Lang::Rust).is_some()
             || !self.templates.is_empty()
+            || !self.jinja.is_empty()
+            || !self.wasm.is_empty()
+            || !self.process.is_empty()
             || !self.scripts.is_empty()
+            || self.dump_context.is_some()
+    }
+}
+
+/// Required options are checked after merging with the manifest, since they may come from either.
+fn check_required(config: &Config) -> Result<()> {
+    if config.init_script_target.is_some()
+        || config.init_template_target.is_some()
+        || config.init_jinja_target.is_some()
+    {
+        return Ok(());
+    }
+    if config.input.as_os_str().is_empty() {
+        return Err(anyhow!(
+            "--{} is required, either on the command line or in the manifest.",
+            INPUT
+        ));
     }
+    let has_target = !config.protos.is_empty()
+        || !config.templates.is_empty()
+        || !config.jinja.is_empty()
+        || !config.wasm.is_empty()
+        || !config.process.is_empty()
+        || !config.scripts.is_empty()
+        || !config.encode.is_empty()
+        || config.dump_context.is_some();
+    // Clap only catches this on the command line, not when the targets come from the manifest.
+    if config.bypass && has_target {
+        return Err(anyhow!(
+            "--{} can't be used with generator targets, either on the command line or in the manifest.",
+            BYPASS
+        ));
+    }
+    if !has_target && !config.bypass {
+        return Err(anyhow!(
+            "At least one of {} is required, either on the command line or in the manifest.",
+            MAIN_OPTS
+                .iter()
+                .map(|opt| format!("--{}", opt))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ));
+    }
+    Ok(())
 }
 
 fn check_proto_supported_languages(config: &Config) -> Result<()> {
@@ -373,7 +711,22 @@ fn current_dir(arg_name: &str) -> Result<PathBuf> {
     })
 }
 
-fn parse_descriptor_path(intermediate_dir: &Path, args: &ArgMatches) -> PathBuf {
+fn parse_manifest(args: &ArgMatches) -> Result<Manifest> {
+    let path = parse_optional_path_from_arg(MANIFEST, args)?;
+    Manifest::load(path.as_deref(), &current_dir(MANIFEST)?)
+}
+
+fn parse_descriptor_path(
+    intermediate_dir: &Path,
+    args: &ArgMatches,
+    manifest_path: Option<PathBuf>,
+) -> PathBuf {
+    // The arg has a default value, so only use it over the manifest if it was explicitly passed.
+    if args.occurrences_of(DESCRIPTOR_SET_OUT) == 0 {
+        if let Some(path) = manifest_path {
+            return path;
+        }
+    }
     intermediate_dir.join(
         args.value_of(DESCRIPTOR_SET_OUT)
             .unwrap_or(DEFAULT_DESCRIPTOR_SET_FILENAME),
@@ -453,6 +806,43 @@ fn parse_script_configs(
     Ok(configs)
 }
 
+/// Applies each `--script-param NAME KEY=VALUE` to every script called NAME, whether it was
+/// declared in the manifest or on the command line, so params given later win.
+fn apply_script_params(scripts: &mut [ScriptConfig], args: &ArgMatches) -> Result<()> {
+    let values = match args.grouped_values_of(SCRIPT_PARAM) {
+        None => return Ok(()),
+        Some(values) => values,
+    };
+    for value in values {
+        let (name, param) = match (value.get(0), value.get(1)) {
+            (Some(name), Some(param)) => (*name, *param),
+            _ => return Err(anyhow!("--{} is missing NAME or KEY=VALUE", SCRIPT_PARAM)),
+        };
+        let mut matched = false;
+        for script in scripts.iter_mut().filter(|script| script.name == name) {
+            script.set_param(param)?;
+            matched = true;
+        }
+        if !matched {
+            return Err(anyhow!(
+                "--{} '{}' doesn't match any script, on the command line or in the manifest.",
+                SCRIPT_PARAM,
+                name
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn parse_script_module_paths(args: &ArgMatches) -> Result<Vec<PathBuf>> {
+    let paths = parse_arg_to_vec(SCRIPT_MODULE_PATH, args);
+    if paths.is_empty() {
+        return Ok(vec![]);
+    }
+    let current_dir = current_dir(SCRIPT_MODULE_PATH)?;
+    Ok(paths.iter().map(|path| current_dir.join(path)).collect())
+}
+
 fn get_grouped_values_by_name<'a>(
     args: &'a ArgMatches,
     arg_name: &str,
@@ -504,6 +894,23 @@ fn parse_encode_configs(
     Ok(configs)
 }
 
+fn parse_dump_config(
+    args: &ArgMatches,
+    output_root: Option<&PathBuf>,
+) -> Result<Option<DumpConfig>> {
+    let values = match args.values_of(DUMP_CONTEXT) {
+        None => return Ok(None),
+        Some(values) => values.collect::<Vec<&str>>(),
+    };
+    let format = values
+        .get(0)
+        .ok_or(anyhow!("--{} is missing FORMAT", DUMP_CONTEXT))?;
+    let output = values
+        .get(1)
+        .ok_or(anyhow!("--{} is missing OUTPUT", DUMP_CONTEXT))?;
+    Ok(Some(DumpConfig::from_config(format, output, output_root)?))
+}
+
 fn parse_includes(args: &ArgMatches) -> Vec<String> {
     parse_arg_to_vec(INCLUDES, args)
 }
@@ -519,6 +926,27 @@ fn parse_arg_to_vec(arg_name: &str, args: &ArgMatches) -> Vec<String> {
         .collect()
 }
 
+fn parse_jobs(args: &ArgMatches) -> Result<Option<usize>> {
+    match args.value_of(JOBS) {
+        None => Ok(None),
+        Some(jobs) => match jobs.parse::<usize>() {
+            Ok(jobs) if jobs > 0 => Ok(Some(jobs)),
+            _ => Err(anyhow!(
+                "--{} must be a number greater than 0, found '{}'.",
+                JOBS,
+                jobs
+            )),
+        },
+    }
+}
+
+fn parse_message_format(args: &ArgMatches) -> Result<MessageFormat> {
+    match args.value_of(MESSAGE_FORMAT) {
+        None => Ok(MessageFormat::default()),
+        Some(format) => MessageFormat::from_str(format),
+    }
+}
+
 fn lang_list(list: &[Lang]) -> String {
     list.iter()
         .map(|lang| lang.as_config())
@@ -540,11 +968,16 @@ impl ArgExt for Arg<'_> {
 #[cfg(test)]
 mod tests {
     use crate::config::{
-        parse_cli_args, APP_NAME, INCLUDES, INPUT, OUTPUT_ROOT, PROTO, PROTOC_ARGS,
+        parse_cli_args, APP_NAME, DUMP_CONTEXT, INCLUDES, INPUT, JINJA, JOBS, MESSAGE_FORMAT,
+        OUTPUT_ROOT, PLUGIN_ROOT, PROCESS, PROTO, PROTOC_ARGS, SCRIPT, SCRIPT_MODULE_PATH,
+        SCRIPT_PARAM, SCRIPT_ROOT, TEMPLATE_ROOT, WASM, WATCH,
     };
+    use crate::diagnostic::MessageFormat;
+    use crate::dump_config::DumpFormat;
     use crate::{Config, DisplayNormalized};
     use anyhow::Result;
     use std::env::current_dir;
+    use std::path::{Path, PathBuf};
 
     #[test]
     fn parse_input() -> Result<()> {
@@ -616,6 +1049,302 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn parse_script_module_paths() -> Result<()> {
+        let shared = current_dir()?.join("shared");
+        let config = config_with_required_args([
+            arg(SCRIPT_MODULE_PATH),
+            "shared".to_owned(),
+            arg(SCRIPT_MODULE_PATH),
+            "/abs/modules".to_owned(),
+        ])?;
+        assert_eq!(
+            config.script_module_paths,
+            vec![shared, "/abs/modules".into()]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parse_script_params() -> Result<()> {
+        let config = config_with_required_args([
+            arg(SCRIPT),
+            "a".to_owned(),
+            arg(SCRIPT),
+            "b".to_owned(),
+            arg(SCRIPT_ROOT),
+            current_dir()?.display_normalized(),
+            arg(OUTPUT_ROOT),
+            current_dir()?.display_normalized(),
+            arg(SCRIPT_PARAM),
+            "a".to_owned(),
+            "variant=lite".to_owned(),
+        ])?;
+        assert_eq!(
+            config.scripts[0].params["variant"],
+            serde_yaml::Value::from("lite")
+        );
+        assert!(config.scripts[1].params.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn script_param_without_script_errors() -> Result<()> {
+        let result = config_with_required_args([
+            arg(SCRIPT_PARAM),
+            "missing".to_owned(),
+            "variant=lite".to_owned(),
+        ]);
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parse_watch() -> Result<()> {
+        assert!(!config_with_required_args(Vec::<String>::new())?.watch);
+        assert!(config_with_required_args([arg(WATCH)])?.watch);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_message_format() -> Result<()> {
+        let config = config_with_required_args(Vec::<String>::new())?;
+        assert_eq!(config.message_format, MessageFormat::Human);
+        let config = config_with_required_args([arg(MESSAGE_FORMAT), "json".to_owned()])?;
+        assert_eq!(config.message_format, MessageFormat::Json);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_jobs() -> Result<()> {
+        let config = config_with_required_args(Vec::<String>::new())?;
+        assert_eq!(config.jobs, None);
+        let config = config_with_required_args([arg(JOBS), "3".to_owned()])?;
+        assert_eq!(config.jobs, Some(3));
+        assert!(config_with_required_args([arg(JOBS), "0".to_owned()]).is_err());
+        assert!(config_with_required_args([arg(JOBS), "many".to_owned()]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parse_dump_context() -> Result<()> {
+        let output = current_dir()?.join("dump");
+        let config = config_with_required_args([
+            arg(DUMP_CONTEXT),
+            "yaml".to_owned(),
+            output.display_normalized(),
+        ])?;
+        let dump_config = config.dump_context.unwrap();
+        assert_eq!(dump_config.format, DumpFormat::Yaml);
+        assert_eq!(dump_config.output, output);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_jinja() -> Result<()> {
+        let root = current_dir()?;
+        let config = config_with_required_args([
+            arg(JINJA),
+            "ts".to_owned(),
+            "out/ts".to_owned(),
+            arg(TEMPLATE_ROOT),
+            root.display_normalized(),
+            arg(OUTPUT_ROOT),
+            root.display_normalized(),
+        ])?;
+        assert_eq!(config.jinja[0].input, root.join("ts"));
+        assert_eq!(config.jinja[0].output, root.join("out/ts"));
+        assert!(config.templates.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn parse_wasm() -> Result<()> {
+        let root = current_dir()?;
+        let config = config_with_required_args([
+            arg(WASM),
+            "gen".to_owned(),
+            "out/gen".to_owned(),
+            arg(PLUGIN_ROOT),
+            root.join("plugins").display_normalized(),
+            arg(OUTPUT_ROOT),
+            root.display_normalized(),
+        ])?;
+        assert_eq!(config.wasm[0].input, root.join("plugins/gen"));
+        assert_eq!(config.wasm[0].output, root.join("out/gen"));
+        Ok(())
+    }
+
+    #[test]
+    fn parse_process() -> Result<()> {
+        let root = current_dir()?;
+        let config = config_with_required_args([
+            arg(PROCESS),
+            "py".to_owned(),
+            "out/py".to_owned(),
+            arg(PLUGIN_ROOT),
+            root.join("plugins").display_normalized(),
+            arg(OUTPUT_ROOT),
+            root.display_normalized(),
+        ])?;
+        assert_eq!(config.process[0].input, root.join("plugins/py"));
+        assert_eq!(config.process[0].output, root.join("out/py"));
+        assert!(config.wasm.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn written_files_include_nested_outputs() {
+        let config = Config::default();
+        config.record_written_files(Path::new("out/a"), vec![PathBuf::from("out/a/old")]);
+        config.record_written_files(Path::new("out/a"), vec![PathBuf::from("out/a/new")]);
+        config.record_written_files(Path::new("out/a/sub"), vec![PathBuf::from("out/a/sub/x")]);
+        config.record_written_files(Path::new("out/b"), vec![PathBuf::from("out/b/y")]);
+        assert_eq!(
+            config.written_files(Path::new("out/a")),
+            vec![PathBuf::from("out/a/new"), PathBuf::from("out/a/sub/x")]
+        );
+        assert!(config.written_files(Path::new("out/c")).is_empty());
+    }
+
+    mod manifest {
+        use crate::config::tests::arg;
+        use crate::config::{
+            parse_cli_args, APP_NAME, BYPASS, MANIFEST, OUTPUT_ROOT, PROTO, SCRIPT_PARAM,
+        };
+        use crate::{Config, DisplayNormalized};
+        use anyhow::Result;
+        use std::fs;
+        use tempfile::tempdir;
+
+        const MANIFEST_CONTENTS: &str = r#"
+input: protos
+output_root: out
+protos:
+  - lang: cpp
+    output: proto-cpp
+"#;
+
+        #[test]
+        fn provides_required_args() -> Result<()> {
+            let dir = tempdir()?;
+            let manifest_path = dir.path().join("protox.yml");
+            fs::write(&manifest_path, MANIFEST_CONTENTS)?;
+            let config = Config::from_args(&parse_cli_args([
+                APP_NAME,
+                &arg(MANIFEST),
+                &manifest_path.display_normalized(),
+            ])?)?;
+            assert_eq!(config.input, dir.path().join("protos"));
+            assert_eq!(config.protos.len(), 1);
+            assert_eq!(config.protos[0].output, dir.path().join("out/proto-cpp"));
+            Ok(())
+        }
+
+        #[test]
+        fn cli_overrides_and_adds() -> Result<()> {
+            let dir = tempdir()?;
+            let manifest_path = dir.path().join("protox.yml");
+            fs::write(&manifest_path, MANIFEST_CONTENTS)?;
+            let output_root = dir.path().join("cli_out");
+            let config = Config::from_args(&parse_cli_args([
+                APP_NAME,
+                &arg(MANIFEST),
+                &manifest_path.display_normalized(),
+                &arg(OUTPUT_ROOT),
+                &output_root.display_normalized(),
+                &arg(PROTO),
+                "java",
+                "proto-java",
+            ])?)?;
+            let outputs = config
+                .protos
+                .iter()
+                .map(|x| x.output.clone())
+                .collect::<Vec<_>>();
+            assert_eq!(
+                outputs,
+                vec![
+                    output_root.join("proto-cpp"),
+                    output_root.join("proto-java")
+                ]
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn bypass_with_manifest_targets_errors() -> Result<()> {
+            let dir = tempdir()?;
+            let manifest_path = dir.path().join("protox.yml");
+            fs::write(&manifest_path, MANIFEST_CONTENTS)?;
+            let result = Config::from_args(&parse_cli_args([
+                APP_NAME,
+                &arg(MANIFEST),
+                &manifest_path.display_normalized(),
+                &arg(BYPASS),
+            ])?);
+            assert!(result.is_err());
+            Ok(())
+        }
+
+        #[test]
+        fn bypass_with_manifest_input_is_allowed() -> Result<()> {
+            let dir = tempdir()?;
+            let manifest_path = dir.path().join("protox.yml");
+            fs::write(&manifest_path, "input: protos")?;
+            let config = Config::from_args(&parse_cli_args([
+                APP_NAME,
+                &arg(MANIFEST),
+                &manifest_path.display_normalized(),
+                &arg(BYPASS),
+            ])?)?;
+            assert!(config.bypass);
+            assert_eq!(config.input, dir.path().join("protos"));
+            Ok(())
+        }
+
+        #[test]
+        fn script_params_apply_to_manifest_scripts() -> Result<()> {
+            let dir = tempdir()?;
+            let manifest_path = dir.path().join("protox.yml");
+            fs::write(
+                &manifest_path,
+                r#"
+input: protos
+scripts:
+  - name: csharp
+    params: { target: unity, nullable_refs: true }
+"#,
+            )?;
+            let config = Config::from_args(&parse_cli_args([
+                APP_NAME,
+                &arg(MANIFEST),
+                &manifest_path.display_normalized(),
+                &arg(SCRIPT_PARAM),
+                "csharp",
+                "target=godot",
+            ])?)?;
+            let params = &config.scripts[0].params;
+            assert_eq!(params["target"], serde_yaml::Value::from("godot"));
+            assert_eq!(params["nullable_refs"], serde_yaml::Value::Bool(true));
+            Ok(())
+        }
+
+        #[test]
+        fn missing_input_errors() -> Result<()> {
+            let dir = tempdir()?;
+            let manifest_path = dir.path().join("protox.yml");
+            fs::write(&manifest_path, "protos: [{ lang: cpp, output: proto-cpp }]")?;
+            let result = Config::from_args(&parse_cli_args([
+                APP_NAME,
+                &arg(MANIFEST),
+                &manifest_path.display_normalized(),
+            ])?);
+            assert!(result.is_err());
+            Ok(())
+        }
+    }
+
     fn quote(value: &str) -> String {
         ["\"", value, "\""].concat()
     }
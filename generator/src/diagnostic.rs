@@ -0,0 +1,285 @@
+use crate::util::DisplayNormalized;
+use anyhow::anyhow;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// An error or warning pointing at a location in a proto, script or template file.
+///
+/// Returned as the source of errors from protoc, scripts and templates, so it can be found in
+/// an error chain with [`Diagnostic::collect`].
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    #[serde(serialize_with = "serialize_path")]
+    pub path: Option<PathBuf>,
+    /// 1-based.
+    pub line: Option<usize>,
+    /// 1-based.
+    pub column: Option<usize>,
+    /// Context added on top of the diagnostic, outermost first.
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+            path: None,
+            line: None,
+            column: None,
+            notes: vec![],
+        }
+    }
+
+    pub fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    pub fn with_position(mut self, line: Option<usize>, column: Option<usize>) -> Self {
+        self.line = line;
+        self.column = column;
+        self
+    }
+
+    /// Finds the diagnostics in the chain of `err`. If there are none, the error is converted
+    /// into a single diagnostic without a location. Any context added on top is kept as notes.
+    pub fn collect(err: &anyhow::Error) -> Vec<Diagnostic> {
+        let mut notes = Vec::new();
+        for cause in err.chain() {
+            let diagnostics = if let Some(diagnostics) = cause.downcast_ref::<Diagnostics>() {
+                diagnostics.0.clone()
+            } else if let Some(diagnostic) = cause.downcast_ref::<Diagnostic>() {
+                vec![diagnostic.clone()]
+            } else {
+                notes.push(cause.to_string());
+                continue;
+            };
+            return diagnostics
+                .into_iter()
+                .map(|mut diagnostic| {
+                    diagnostic.notes.splice(0..0, notes.iter().cloned());
+                    diagnostic
+                })
+                .collect();
+        }
+        let mut diagnostic = Diagnostic::error(notes.pop().unwrap_or_default());
+        diagnostic.notes = notes;
+        vec![diagnostic]
+    }
+
+    /// `path:line:column`, omitting the parts that aren't known.
+    pub fn location(&self) -> Option<String> {
+        let path = self.path.as_ref()?;
+        let mut location = path.display_normalized();
+        if let Some(line) = self.line {
+            location.push_str(&format!(":{}", line));
+            if let Some(column) = self.column {
+                location.push_str(&format!(":{}", column));
+            }
+        }
+        Some(location)
+    }
+
+    /// Compiler style, e.g.
+    /// ```txt
+    /// error: Variable not found: x
+    ///   --> scripts/flatbuffers/main.rhai:12:5
+    ///   = note: Error returned from script function 'render_file'
+    /// ```
+    pub fn render_human(&self) -> String {
+        let mut rendered = format!("{}: {}", self.severity, self.message);
+        if let Some(location) = self.location() {
+            rendered.push_str(&format!("\n  --> {}", location));
+        }
+        for note in &self.notes {
+            rendered.push_str(&format!("\n  = note: {}", note));
+        }
+        rendered
+    }
+
+    pub fn render_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| format!("{:?}", self.message))
+    }
+
+    pub fn render(&self, format: MessageFormat) -> String {
+        match format {
+            MessageFormat::Human => self.render_human(),
+            MessageFormat::Json => self.render_json(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.location() {
+            None => write!(f, "{}", self.message),
+            Some(location) => write!(f, "{}: {}", location, self.message),
+        }
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+/// Multiple diagnostics returned as one error, e.g. all errors reported by a protoc run.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostics(pub Vec<Diagnostic>);
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines = self.0.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl std::error::Error for Diagnostics {}
+
+/// How diagnostics are printed: human readable to stderr, or one json object per line to stdout.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MessageFormat {
+    Human,
+    Json,
+}
+
+impl Default for MessageFormat {
+    fn default() -> Self {
+        MessageFormat::Human
+    }
+}
+
+impl FromStr for MessageFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(MessageFormat::Human),
+            "json" => Ok(MessageFormat::Json),
+            _ => Err(anyhow!("Unsupported message format: {}", s)),
+        }
+    }
+}
+
+impl MessageFormat {
+    pub fn as_config(&self) -> String {
+        match self {
+            MessageFormat::Human => "human",
+            MessageFormat::Json => "json",
+        }
+        .to_owned()
+    }
+}
+
+/// Prints the diagnostics in `err` in `format`.
+pub fn emit(err: &anyhow::Error, format: MessageFormat) {
+    for diagnostic in Diagnostic::collect(err) {
+        match format {
+            MessageFormat::Human => eprintln!("{}", diagnostic.render_human()),
+            MessageFormat::Json => println!("{}", diagnostic.render_json()),
+        }
+    }
+}
+
+fn serialize_path<S: Serializer>(path: &Option<PathBuf>, serializer: S) -> Result<S::Ok, S::Error> {
+    path.as_deref()
+        .map(Path::display_normalized)
+        .serialize(serializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostic::{Diagnostic, Diagnostics, MessageFormat};
+    use anyhow::{anyhow, Context, Result};
+    use std::str::FromStr;
+
+    #[test]
+    fn message_format() -> Result<()> {
+        for format in [MessageFormat::Human, MessageFormat::Json].iter() {
+            assert_eq!(MessageFormat::from_str(&format.as_config())?, *format);
+        }
+        assert!(MessageFormat::from_str("xml").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn render_human() {
+        let mut diagnostic = Diagnostic::error("Unexpected token")
+            .with_path("dir/main.rhai")
+            .with_position(Some(3), Some(7));
+        diagnostic.notes.push("Failed to render".to_owned());
+        assert_eq!(
+            diagnostic.render_human(),
+            "error: Unexpected token\n  --> dir/main.rhai:3:7\n  = note: Failed to render"
+        );
+    }
+
+    #[test]
+    fn render_json() {
+        let diagnostic = Diagnostic::error("Unexpected token")
+            .with_path("file.proto")
+            .with_position(Some(3), None);
+        assert_eq!(
+            diagnostic.render_json(),
+            r#"{"severity":"error","message":"Unexpected token","path":"file.proto","line":3,"column":null,"notes":[]}"#
+        );
+    }
+
+    mod collect {
+        use super::*;
+
+        #[test]
+        fn keeps_context_as_notes() {
+            let err = Err::<(), _>(Diagnostic::error("inner").with_path("a.hbs"))
+                .context("middle")
+                .context("outer")
+                .unwrap_err();
+            let diagnostics = Diagnostic::collect(&err);
+            assert_eq!(diagnostics.len(), 1);
+            assert_eq!(diagnostics[0].message, "inner");
+            assert_eq!(diagnostics[0].notes, vec!["outer", "middle"]);
+        }
+
+        #[test]
+        fn multiple() {
+            let err = Err::<(), _>(Diagnostics(vec![
+                Diagnostic::error("first"),
+                Diagnostic::error("second"),
+            ]))
+            .context("outer")
+            .unwrap_err();
+            let diagnostics = Diagnostic::collect(&err);
+            assert_eq!(diagnostics.len(), 2);
+            assert_eq!(diagnostics[1].notes, vec!["outer"]);
+        }
+
+        #[test]
+        fn plain_error() {
+            let err = Err::<(), _>(anyhow!("root cause"))
+                .context("outer")
+                .unwrap_err();
+            let diagnostics = Diagnostic::collect(&err);
+            assert_eq!(diagnostics[0].message, "root cause");
+            assert_eq!(diagnostics[0].path, None);
+            assert_eq!(diagnostics[0].notes, vec!["outer"]);
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::renderer::jinja;
 use crate::renderer::scripted::{MAIN_SCRIPT_NAME, SCRIPT_EXT};
 use crate::renderer::template::{FILE_TEMPLATE_NAME, TEMPLATE_EXT};
 use crate::renderer::RendererConfig;
@@ -23,6 +24,14 @@ pub fn initialize_template_dir(dir: &Path) -> Result<()> {
     Ok(())
 }
 
+pub fn initialize_jinja_dir(dir: &Path) -> Result<()> {
+    util::create_dir_or_error(dir)?;
+    util::check_dir_is_empty(dir)?;
+    write_config(dir)?;
+    write_file_jinja_template(dir)?;
+    Ok(())
+}
+
 fn write_config(path: &Path) -> Result<()> {
     let config_file = util::create_file_or_error(&path.join(DEFAULT_CONFIG_FILE_NAME))?;
     let config = RendererConfig::default();
@@ -92,7 +101,7 @@ fn write_file_template(path: &Path) -> Result<()> {
         util::create_file_or_error(&path.join(FILE_TEMPLATE_NAME).with_extension(TEMPLATE_EXT))?;
     let contents = unindent(
         r#"
-        {{!
+        {{!--
         This is the root template file for most protox output.
 
         See the "builtin" and "examples" folders for usage examples:
@@ -103,7 +112,75 @@ fn write_file_template(path: &Path) -> Result<()> {
         https://github.com/nswarm/protox/tree/main/runner/src/renderer/context
 
         For more information on Handlebars: https://handlebarsjs.com/guide/
-        }}"#,
+
+        Helpers available in addition to the Handlebars built-ins (if, each, with, lookup, eq, ne,
+        gt, gte, lt, lte, and, or, not, len):
+
+          {{case name "UpperCamel"}}          Any case name from the config.
+          {{upper_camel name}}                Also upper, lower, lower_snake, upper_snake,
+                                              lower_kebab, upper_kebab and lower_camel.
+          {{join items ", "}}                 Joins an array.
+          {{replace name "." "::"}}           Replaces all occurrences.
+          {{trim name}}                       Removes surrounding whitespace.
+          {{pad name 20}}                     Pads to a width. Options: align="right", fill="-".
+          {{wrap_comment text 100}}           Word wraps as comment lines. Option: prefix="/// ".
+          {{first fields}}, {{last fields}}   First or last item, e.g. {{#with (first fields)}}.
+          {{lookup_overlay full_proto_name "key"}}
+                                              Overlay value for a target, or null.
+          {{set "name" value}}, {{get "name"}}
+                                              Variables for the rest of the output.
+          {{#if_equals lhs rhs}}              Block rendered if lhs == rhs.
+          {{#indent 4}}                       Indents the block, e.g. for recursive partials.
+
+        For example:
+
+        {{#each messages}}
+        {{wrap_comment (case name "lower_snake") 100}}
+        {{/each}}
+        --}}"#,
+    );
+    file.write_all(contents.as_bytes())?;
+    Ok(())
+}
+
+fn write_file_jinja_template(path: &Path) -> Result<()> {
+    let mut file = util::create_file_or_error(&path.join(jinja::FILE_TEMPLATE_NAME))?;
+    let contents = unindent(
+        r##"
+        {#
+        This is the root template file for most protox output.
+
+        See the "builtin" and "examples" folders for usage examples:
+        https://github.com/nswarm/protox/tree/main/builtin
+        https://github.com/nswarm/protox/tree/main/examples
+
+        See context data objects for information on what data is available. Each context also has
+        its "overlays" and builtin "options", the same as --dump-context shows:
+        https://github.com/nswarm/protox/tree/main/runner/src/renderer/context
+
+        For more information on the template language: https://docs.rs/minijinja/
+
+        Other templates are named by their path from this directory, e.g.
+        {% include "messages/struct.jinja" %} or {% import "macros.jinja" as macros %}.
+
+        Filters and functions available in addition to the MiniJinja built-ins (join, replace,
+        trim, indent, first, last, length, default, etc.):
+
+          {{ name | case("UpperCamel") }}     Any case name from the config.
+          {{ name | upper_camel }}            Also upper, lower, lower_snake, upper_snake,
+                                              lower_kebab, upper_kebab and lower_camel.
+          {{ text | wrap_comment(100) }}      Word wraps as comment lines. Prefix: "// ".
+          {{ text | comment("/**", 100) }}    Formats as a comment in a style, e.g. "//" or "#".
+          {{ lookup_overlay(full_proto_name, "key") }}
+                                              Overlay value for a target, or none.
+
+        For example:
+
+        {% for message in messages %}
+        {{ message.name | lower_snake | wrap_comment(100) }}
+        {% endfor %}
+        #}
+        "##,
     );
     file.write_all(contents.as_bytes())?;
     Ok(())
@@ -111,8 +188,9 @@ fn write_file_template(path: &Path) -> Result<()> {
 
 #[cfg(test)]
 mod tests {
-    use crate::dir_init::initialize_template_dir;
+    use crate::dir_init::{initialize_jinja_dir, initialize_template_dir};
     use crate::initialize_script_dir;
+    use crate::renderer::jinja;
     use crate::renderer::scripted::{MAIN_SCRIPT_NAME, SCRIPT_EXT};
     use crate::renderer::template::{FILE_TEMPLATE_NAME, TEMPLATE_EXT};
     use crate::renderer::{RendererConfig, CONFIG_FILE_NAMES};
@@ -163,4 +241,13 @@ mod tests {
         assert!(!result.is_empty());
         Ok(())
     }
+
+    #[test]
+    fn writes_file_jinja_file() -> Result<()> {
+        let tempdir = tempdir()?;
+        initialize_jinja_dir(tempdir.path())?;
+        let result = fs::read_to_string(tempdir.path().join(jinja::FILE_TEMPLATE_NAME))?;
+        assert!(!result.is_empty());
+        Ok(())
+    }
 }
@@ -0,0 +1,72 @@
+use crate::util;
+use anyhow::{anyhow, Result};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+#[derive(Clone)]
+pub struct DumpConfig {
+    pub format: DumpFormat,
+    pub output: PathBuf,
+}
+
+impl DumpConfig {
+    pub fn from_config(format: &str, output: &str, output_root: Option<&PathBuf>) -> Result<Self> {
+        Ok(DumpConfig {
+            format: DumpFormat::from_str(format)?,
+            output: util::path_as_absolute(output, output_root)?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DumpFormat {
+    Json,
+    Yaml,
+}
+
+impl FromStr for DumpFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match &s.to_lowercase()[..] {
+            "json" => DumpFormat::Json,
+            "yaml" | "yml" => DumpFormat::Yaml,
+            _ => return Err(anyhow!("Unsupported dump format: {}", s)),
+        })
+    }
+}
+
+impl DumpFormat {
+    pub fn as_config(&self) -> String {
+        match self {
+            DumpFormat::Json => "json",
+            DumpFormat::Yaml => "yaml",
+        }
+        .to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dump_config::DumpFormat;
+    use anyhow::Result;
+    use std::str::FromStr;
+
+    #[test]
+    fn from_str() -> Result<()> {
+        assert_eq!(DumpFormat::from_str("json")?, DumpFormat::Json);
+        assert_eq!(DumpFormat::from_str("JSON")?, DumpFormat::Json);
+        assert_eq!(DumpFormat::from_str("yaml")?, DumpFormat::Yaml);
+        assert_eq!(DumpFormat::from_str("yml")?, DumpFormat::Yaml);
+        assert!(DumpFormat::from_str("toml").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn round_trip() -> Result<()> {
+        for format in [DumpFormat::Json, DumpFormat::Yaml] {
+            assert_eq!(DumpFormat::from_str(&format.as_config())?, format);
+        }
+        Ok(())
+    }
+}
@@ -5,11 +5,14 @@ use std::fs::File;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 
+use crate::diagnostic::Diagnostics;
 use crate::encode_config::EncodeConfig;
 use crate::protoc;
 use crate::protoc::Protoc;
 use crate::Config;
 
+const STDIN_NAME: &str = "input";
+
 pub fn generate(config: &Config) -> Result<()> {
     let mut protoc = Protoc::new(config)?;
     protoc.add_input_files(
@@ -18,13 +21,27 @@ pub fn generate(config: &Config) -> Result<()> {
     for encode_config in &config.encode {
         let encode_arg = protoc::arg_with_value("encode", &encode_config.message_type);
         let target_contents = read_target(&encode_config.target)?;
-        let output = protoc.execute_with_args(Some(target_contents), &[&encode_arg])?;
+        let output = protoc
+            .execute_with_args(Some(target_contents), &[&encode_arg])
+            .map_err(|err| locate_in_target(err, &encode_config.target))?;
         encode_to_file(&output_file_path(encode_config), &output)?;
         log_encode(encode_config);
     }
     Ok(())
 }
 
+/// protoc names the encode target read from stdin "input" in its errors.
+fn locate_in_target(mut err: anyhow::Error, target: &Path) -> anyhow::Error {
+    if let Some(diagnostics) = err.downcast_mut::<Diagnostics>() {
+        for diagnostic in &mut diagnostics.0 {
+            if diagnostic.path.as_deref() == Some(Path::new(STDIN_NAME)) {
+                diagnostic.path = Some(target.to_path_buf());
+            }
+        }
+    }
+    err
+}
+
 fn read_target(target: &Path) -> Result<String> {
     let mut target_contents = String::new();
     File::open(target)
@@ -33,7 +50,7 @@ fn read_target(target: &Path) -> Result<String> {
     Ok(target_contents)
 }
 
-fn output_file_path(config: &EncodeConfig) -> PathBuf {
+pub(crate) fn output_file_path(config: &EncodeConfig) -> PathBuf {
     config
         .output
         .join(config.target.file_stem().unwrap())
@@ -12,6 +12,11 @@ pub trait InOutGenerator<R: Render> {
     fn app_config(&self) -> &Config;
     fn in_out_configs(&self) -> Vec<InOutConfig>;
 
+    /// Loads the renderer for `config`, the `index`th of `in_out_configs`.
+    fn load_renderer(&mut self, _index: usize, config: &InOutConfig) -> Result<()> {
+        self.renderer().load(&config.input, &config.overlays)
+    }
+
     fn generate(&mut self) -> Result<()> {
         if self.in_out_configs().is_empty() {
             return Ok(());
@@ -25,13 +30,15 @@ pub trait InOutGenerator<R: Render> {
         if self.in_out_configs().is_empty() {
             return Ok(());
         }
-        for config in &self.in_out_configs() {
+        for (index, config) in self.in_out_configs().iter().enumerate() {
             log_render_start(self.name(), &config);
-            self.renderer().load(&config.input, &config.overlays)?;
+            self.load_renderer(index, config)?;
             util::create_dir_or_error(&config.output)
                 .with_context(|| error_context(self.name()))?;
             util::check_dir_is_empty(&config.output).with_context(|| error_context(self.name()))?;
-            self.renderer().render(&descriptor_set, &config.output)?;
+            let files = self.renderer().render(&descriptor_set, &config.output)?;
+            self.app_config()
+                .record_written_files(&config.output, files);
         }
         Ok(())
     }
@@ -112,6 +119,22 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn records_written_files() -> Result<()> {
+        let test_dir = tempdir()?;
+        let input_dir = test_dir.path().join("input");
+        let output_dir = test_dir.path().join("output");
+        let descriptor_set = FileDescriptorSet { file: vec![] };
+        let config = Config::default();
+        let mut generator = TestGenerator::with_in_out(&config, &input_dir, &output_dir, &["a"]);
+        generator.generate_from_descriptor_set(&descriptor_set)?;
+        assert_eq!(
+            config.written_files(&output_dir.join("a")),
+            vec![output_dir.join("a").join("testfile.test")]
+        );
+        Ok(())
+    }
+
     struct TestRenderer {}
     impl Render for TestRenderer {
         fn load(&mut self, _input_root: &Path, _overlays: &[PathBuf]) -> Result<()> {
@@ -124,9 +147,10 @@ mod tests {
             &self,
             _descriptor_set: &FileDescriptorSet,
             output_path: &Path,
-        ) -> anyhow::Result<()> {
-            fs::File::create(output_path.join("testfile.test"))?;
-            Ok(())
+        ) -> anyhow::Result<Vec<PathBuf>> {
+            let path = output_path.join("testfile.test");
+            fs::File::create(&path)?;
+            Ok(vec![path])
         }
     }
     struct TestGenerator<'a> {
@@ -4,6 +4,7 @@ use anyhow::Result;
 use std::path::PathBuf;
 use std::str::FromStr;
 
+#[derive(Clone)]
 pub struct LangConfig {
     pub lang: Lang,
     pub output: PathBuf,
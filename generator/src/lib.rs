@@ -1,7 +1,10 @@
 #![forbid(unsafe_code)]
 
+mod builder;
 mod config;
+mod diagnostic;
 mod dir_init;
+mod dump_config;
 mod encode;
 mod encode_config;
 mod idl;
@@ -9,30 +12,49 @@ mod in_out_config;
 mod in_out_generator;
 mod lang;
 mod lang_config;
+mod manifest;
+mod parallel;
 mod protoc;
 mod render;
 mod renderer;
 mod script_config;
 mod util;
+mod watch;
 
-use crate::dir_init::{initialize_script_dir, initialize_template_dir};
+use crate::dir_init::{initialize_jinja_dir, initialize_script_dir, initialize_template_dir};
 use crate::renderer::DEFAULT_CONFIG_FILE_NAME;
 use crate::util::DisplayNormalized;
 use anyhow::Result;
+pub use builder::{Builder, GeneratedOutput, GeneratorKind, Report};
 pub use config::Config;
+pub use diagnostic::{Diagnostic, Diagnostics, MessageFormat, Severity};
+pub use dump_config::{DumpConfig, DumpFormat};
+pub use encode_config::EncodeConfig;
 pub use idl::Idl;
 pub use in_out_config::InOutConfig;
 pub use lang::Lang;
 pub use lang_config::LangConfig;
+pub use script_config::ScriptConfig;
 
+/// Runs protox with the command line args. Errors are printed as diagnostics in the
+/// `--message-format` before being returned.
 pub fn generate() -> Result<()> {
-    env_logger::init();
-    let config = Config::from_cli()?;
-    generate_internal(&config)
+    parallel::init_logger()?;
+    let mut message_format = MessageFormat::default();
+    let result = Config::from_cli().and_then(|config| {
+        message_format = config.message_format;
+        generate_internal(&config)
+    });
+    if let Err(err) = &result {
+        diagnostic::emit(err, message_format);
+    }
+    result
 }
 
+/// Prefer [`Builder`] when calling from code, which leaves logging alone.
 pub fn generate_with_config(config: Config) -> Result<()> {
-    env_logger::init();
+    // Don't panic if the caller already set up a logger.
+    let _ = parallel::init_logger();
     generate_internal(&config)
 }
 
@@ -43,14 +65,61 @@ fn generate_internal(config: &Config) -> Result<()> {
     if let Some(init_target) = &config.init_template_target {
         return initialize_template_dir(&init_target);
     }
-    match config.idl {
-        Idl::Proto => {
-            protoc::generate(&config)?;
-            renderer::template::generate(&config)?;
-            renderer::scripted::generate(&config)?;
-            encode::generate(&config)?;
+    if let Some(init_target) = &config.init_jinja_target {
+        return initialize_jinja_dir(&init_target);
+    }
+    parallel::install(config.jobs, || {
+        if config.watch {
+            return watch::watch(config);
         }
-    };
+        match config.idl {
+            Idl::Proto => {
+                protoc::generate(config)?;
+                run_generators(config, &Generator::ALL)?;
+            }
+        };
+        Ok(())
+    })?
+}
+
+/// Everything that runs after protoc. These only read the descriptor set and write to their own
+/// outputs, so they can run in parallel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Generator {
+    Template,
+    Jinja,
+    Wasm,
+    Process,
+    Script,
+    Dump,
+    Encode,
+}
+
+impl Generator {
+    pub(crate) const ALL: [Generator; 7] = [
+        Generator::Template,
+        Generator::Jinja,
+        Generator::Wasm,
+        Generator::Process,
+        Generator::Script,
+        Generator::Dump,
+        Generator::Encode,
+    ];
+
+    fn generate(self, config: &Config) -> Result<()> {
+        match self {
+            Generator::Template => renderer::template::generate(config),
+            Generator::Jinja => renderer::jinja::generate(config),
+            Generator::Wasm => renderer::wasm::generate(config),
+            Generator::Process => renderer::process::generate(config),
+            Generator::Script => renderer::scripted::generate(config),
+            Generator::Dump => renderer::dump::generate(config),
+            Generator::Encode => encode::generate(config),
+        }
+    }
+}
 
+pub(crate) fn run_generators(config: &Config, generators: &[Generator]) -> Result<()> {
+    parallel::map_ordered(generators, |generator| generator.generate(config))?;
     Ok(())
 }
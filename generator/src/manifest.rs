@@ -0,0 +1,461 @@
+use crate::dump_config::DumpConfig;
+use crate::encode_config::EncodeConfig;
+use crate::lang_config::LangConfig;
+use crate::script_config::ScriptConfig;
+use crate::util::DisplayNormalized;
+use crate::{util, InOutConfig};
+use anyhow::{Context, Result};
+use log::info;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const MANIFEST_FILE_NAMES: &[&str] = &["protox.yml", "protox.yaml", "protox.toml"];
+
+/// A project manifest describing a protox invocation, so it doesn't need to be spelled out on
+/// the command line each time.
+///
+/// Relative paths are evaluated relative to the directory containing the manifest. Each root
+/// also applies to the matching paths in its section, same as the command line options.
+#[derive(Deserialize, Default, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Manifest {
+    /// Directory the manifest was loaded from.
+    #[serde(skip)]
+    root: PathBuf,
+
+    /// File the manifest was loaded from, if any.
+    #[serde(skip)]
+    file_path: Option<PathBuf>,
+
+    input: Option<String>,
+    includes: Vec<String>,
+    output_root: Option<String>,
+    template_root: Option<String>,
+    script_root: Option<String>,
+    plugin_root: Option<String>,
+    script_module_paths: Vec<String>,
+    descriptor_set_out: Option<String>,
+    protoc_args: Vec<String>,
+    jobs: Option<usize>,
+    protos: Vec<ProtoManifest>,
+    templates: Vec<InOutManifest>,
+    jinja: Vec<InOutManifest>,
+    wasm: Vec<InOutManifest>,
+    process: Vec<InOutManifest>,
+    scripts: Vec<ScriptManifest>,
+    encode: Vec<EncodeManifest>,
+    dump_context: Option<DumpManifest>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct ProtoManifest {
+    lang: String,
+    output: String,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct InOutManifest {
+    input: String,
+    output: String,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct ScriptManifest {
+    name: String,
+    input: Option<String>,
+    output: Option<String>,
+    #[serde(default)]
+    overlays: Vec<String>,
+    #[serde(default)]
+    params: HashMap<String, serde_yaml::Value>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct EncodeManifest {
+    target: String,
+    message_type: String,
+    output: String,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct DumpManifest {
+    format: String,
+    output: String,
+}
+
+impl Manifest {
+    /// Loads the manifest at `path`, or if `None`, looks for one of the default manifest file
+    /// names in `dir`. Returns an empty manifest if no default manifest exists.
+    pub fn load(path: Option<&Path>, dir: &Path) -> Result<Self> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match find_existing_manifest_path(dir) {
+                None => return Ok(Self::default()),
+                Some(path) => path,
+            },
+        };
+        info!("Loading manifest from: {}", path.display_normalized());
+        let contents = fs::read_to_string(&path).with_context(|| {
+            format!("Failed to read manifest at '{}'", path.display_normalized())
+        })?;
+        let mut manifest = Self::parse(&contents, &path)
+            .with_context(|| format!("Invalid manifest '{}'", path.display_normalized()))?;
+        manifest.root = util::path_parent_or_error(&path)?.to_path_buf();
+        manifest.file_path = Some(path);
+        Ok(manifest)
+    }
+
+    fn parse(contents: &str, path: &Path) -> Result<Self> {
+        if path.extension() == Some(OsStr::new("toml")) {
+            Ok(toml::from_str(contents)?)
+        } else {
+            Ok(serde_yaml::from_str(contents)?)
+        }
+    }
+
+    pub fn file_path(&self) -> Option<&Path> {
+        self.file_path.as_deref()
+    }
+    pub fn input(&self) -> Option<PathBuf> {
+        self.path(&self.input)
+    }
+    pub fn output_root(&self) -> Option<PathBuf> {
+        self.path(&self.output_root)
+    }
+    pub fn template_root(&self) -> Option<PathBuf> {
+        self.path(&self.template_root)
+    }
+    pub fn script_root(&self) -> Option<PathBuf> {
+        self.path(&self.script_root)
+    }
+    pub fn plugin_root(&self) -> Option<PathBuf> {
+        self.path(&self.plugin_root)
+    }
+    pub fn descriptor_set_out(&self) -> Option<PathBuf> {
+        self.path(&self.descriptor_set_out)
+    }
+    /// Quoted to match the command line, where protoc args must be quoted so they aren't parsed
+    /// as protox args.
+    pub fn protoc_args(&self) -> Vec<String> {
+        self.protoc_args
+            .iter()
+            .map(|x| format!("\"{}\"", x))
+            .collect()
+    }
+    pub fn jobs(&self) -> Option<usize> {
+        self.jobs
+    }
+
+    pub fn script_module_paths(&self) -> Vec<PathBuf> {
+        self.script_module_paths
+            .iter()
+            .map(|x| self.root.join(x))
+            .collect()
+    }
+
+    pub fn includes(&self) -> Vec<String> {
+        self.includes
+            .iter()
+            .map(|x| self.root.join(x).display_normalized())
+            .collect()
+    }
+
+    pub fn protos(&self, output_root: Option<&PathBuf>) -> Result<Vec<LangConfig>> {
+        let output_root = output_root.unwrap_or(&self.root);
+        self.protos
+            .iter()
+            .map(|x| LangConfig::from_config(&x.lang, &x.output, Some(output_root)))
+            .collect()
+    }
+
+    pub fn templates(
+        &self,
+        template_root: Option<&PathBuf>,
+        output_root: Option<&PathBuf>,
+    ) -> Result<Vec<InOutConfig>> {
+        self.in_out_configs(&self.templates, template_root, output_root)
+    }
+
+    /// Jinja template sets share the template root with handlebars templates.
+    pub fn jinja(
+        &self,
+        template_root: Option<&PathBuf>,
+        output_root: Option<&PathBuf>,
+    ) -> Result<Vec<InOutConfig>> {
+        self.in_out_configs(&self.jinja, template_root, output_root)
+    }
+
+    pub fn wasm(
+        &self,
+        plugin_root: Option<&PathBuf>,
+        output_root: Option<&PathBuf>,
+    ) -> Result<Vec<InOutConfig>> {
+        self.in_out_configs(&self.wasm, plugin_root, output_root)
+    }
+
+    /// External processes share the plugin root with WebAssembly plugins.
+    pub fn process(
+        &self,
+        plugin_root: Option<&PathBuf>,
+        output_root: Option<&PathBuf>,
+    ) -> Result<Vec<InOutConfig>> {
+        self.in_out_configs(&self.process, plugin_root, output_root)
+    }
+
+    fn in_out_configs(
+        &self,
+        manifests: &[InOutManifest],
+        input_root: Option<&PathBuf>,
+        output_root: Option<&PathBuf>,
+    ) -> Result<Vec<InOutConfig>> {
+        let input_root = input_root.unwrap_or(&self.root);
+        let output_root = output_root.unwrap_or(&self.root);
+        manifests
+            .iter()
+            .map(|x| {
+                InOutConfig::from_config(&x.input, &x.output, Some(input_root), Some(output_root))
+            })
+            .collect()
+    }
+
+    pub fn scripts(
+        &self,
+        script_root: Option<&PathBuf>,
+        output_root: Option<&PathBuf>,
+    ) -> Result<Vec<ScriptConfig>> {
+        let script_root = script_root.unwrap_or(&self.root);
+        let output_root = output_root.unwrap_or(&self.root);
+        let mut configs = Vec::new();
+        for script in &self.scripts {
+            let mut config = ScriptConfig::from_config(
+                &script.name,
+                script.input.as_deref(),
+                script.output.as_deref(),
+                Some(script_root),
+                Some(output_root),
+                &[],
+            )?;
+            // Unlike on the command line, overlays are relative to the manifest like other paths.
+            config.overlays = script.overlays.iter().map(|x| self.root.join(x)).collect();
+            config.params = script.params.clone();
+            configs.push(config);
+        }
+        Ok(configs)
+    }
+
+    pub fn encode(&self, output_root: Option<&PathBuf>) -> Result<Vec<EncodeConfig>> {
+        let output_root = output_root.unwrap_or(&self.root);
+        let mut configs = Vec::new();
+        for encode in &self.encode {
+            let mut config = EncodeConfig::from_config(
+                &encode.target,
+                &encode.message_type,
+                &encode.output,
+                Some(output_root),
+            )?;
+            config.target = self.root.join(&config.target);
+            configs.push(config);
+        }
+        Ok(configs)
+    }
+
+    pub fn dump_context(&self, output_root: Option<&PathBuf>) -> Result<Option<DumpConfig>> {
+        let output_root = output_root.unwrap_or(&self.root);
+        match &self.dump_context {
+            None => Ok(None),
+            Some(dump) => Ok(Some(DumpConfig::from_config(
+                &dump.format,
+                &dump.output,
+                Some(output_root),
+            )?)),
+        }
+    }
+
+    fn path(&self, value: &Option<String>) -> Option<PathBuf> {
+        value.as_ref().map(|x| self.root.join(x))
+    }
+}
+
+fn find_existing_manifest_path(dir: &Path) -> Option<PathBuf> {
+    MANIFEST_FILE_NAMES
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.exists())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lang::Lang;
+    use crate::manifest::Manifest;
+    use anyhow::Result;
+    use std::fs;
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    #[test]
+    fn missing_default_is_empty() -> Result<()> {
+        let dir = tempdir()?;
+        assert_eq!(Manifest::load(None, dir.path())?, Manifest::default());
+        Ok(())
+    }
+
+    #[test]
+    fn missing_explicit_errors() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("protox.yml");
+        assert!(Manifest::load(Some(&path), dir.path()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_field_errors() -> Result<()> {
+        let dir = tempdir()?;
+        fs::write(dir.path().join("protox.yml"), "not_a_field: true")?;
+        assert!(Manifest::load(None, dir.path()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn yaml() -> Result<()> {
+        let dir = tempdir()?;
+        fs::write(
+            dir.path().join("protox.yml"),
+            r#"
+input: protos
+output_root: out
+plugin_root: plugins
+script_module_paths: [shared]
+jobs: 2
+protos:
+  - lang: cpp
+    output: proto-cpp
+jinja:
+  - input: templates/ts
+    output: ts
+wasm:
+  - input: gen
+    output: gen
+process:
+  - input: py
+    output: py
+scripts:
+  - name: fbs
+    overlays: [overlay.yml]
+    params: { variant: lite }
+"#,
+        )?;
+        let manifest = Manifest::load(None, dir.path())?;
+        assert_eq!(
+            manifest.file_path(),
+            Some(dir.path().join("protox.yml").as_path())
+        );
+        assert_manifest(&manifest, dir.path().to_path_buf())
+    }
+
+    #[test]
+    fn toml() -> Result<()> {
+        let dir = tempdir()?;
+        fs::write(
+            dir.path().join("protox.toml"),
+            r#"
+input = "protos"
+output_root = "out"
+plugin_root = "plugins"
+script_module_paths = ["shared"]
+jobs = 2
+
+[[protos]]
+lang = "cpp"
+output = "proto-cpp"
+
+[[jinja]]
+input = "templates/ts"
+output = "ts"
+
+[[wasm]]
+input = "gen"
+output = "gen"
+
+[[process]]
+input = "py"
+output = "py"
+
+[[scripts]]
+name = "fbs"
+overlays = ["overlay.yml"]
+params = { variant = "lite" }
+"#,
+        )?;
+        let manifest = Manifest::load(None, dir.path())?;
+        assert_manifest(&manifest, dir.path().to_path_buf())
+    }
+
+    #[test]
+    fn script_overlays_ignore_script_root() -> Result<()> {
+        let dir = tempdir()?;
+        fs::write(
+            dir.path().join("protox.yml"),
+            "scripts:\n  - name: fbs\n    overlays: [overlays/fbs.yml]\n",
+        )?;
+        let manifest = Manifest::load(None, dir.path())?;
+        let script_root = dir.path().join("scripts");
+        let scripts = manifest.scripts(Some(&script_root), None)?;
+        assert_eq!(scripts[0].input, script_root.join("fbs"));
+        assert_eq!(
+            scripts[0].overlays,
+            vec![dir.path().join("overlays/fbs.yml")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn protoc_args_are_quoted() -> Result<()> {
+        let dir = tempdir()?;
+        fs::write(dir.path().join("protox.yml"), "protoc_args: [--arg=value]")?;
+        let manifest = Manifest::load(None, dir.path())?;
+        assert_eq!(manifest.protoc_args(), vec!["\"--arg=value\"".to_owned()]);
+        Ok(())
+    }
+
+    fn assert_manifest(manifest: &Manifest, root: PathBuf) -> Result<()> {
+        let output_root = manifest.output_root();
+        assert_eq!(manifest.input(), Some(root.join("protos")));
+        assert_eq!(output_root, Some(root.join("out")));
+        assert_eq!(manifest.jobs(), Some(2));
+
+        let protos = manifest.protos(output_root.as_ref())?;
+        assert_eq!(protos[0].lang, Lang::Cpp);
+        assert_eq!(protos[0].output, root.join("out").join("proto-cpp"));
+
+        let jinja = manifest.jinja(None, output_root.as_ref())?;
+        assert_eq!(jinja[0].input, root.join("templates/ts"));
+        assert_eq!(jinja[0].output, root.join("out").join("ts"));
+
+        let wasm = manifest.wasm(manifest.plugin_root().as_ref(), output_root.as_ref())?;
+        assert_eq!(wasm[0].input, root.join("plugins").join("gen"));
+        assert_eq!(wasm[0].output, root.join("out").join("gen"));
+
+        let process = manifest.process(manifest.plugin_root().as_ref(), output_root.as_ref())?;
+        assert_eq!(process[0].input, root.join("plugins").join("py"));
+        assert_eq!(process[0].output, root.join("out").join("py"));
+
+        let scripts = manifest.scripts(None, output_root.as_ref())?;
+        assert_eq!(scripts[0].input, root.join("fbs"));
+        assert_eq!(scripts[0].output, root.join("out").join("fbs"));
+        assert_eq!(scripts[0].overlays, vec![root.join("overlay.yml")]);
+        assert_eq!(
+            scripts[0].params["variant"],
+            serde_yaml::Value::from("lite")
+        );
+        assert_eq!(manifest.script_module_paths(), vec![root.join("shared")]);
+        Ok(())
+    }
+}
@@ -0,0 +1,220 @@
+//! Generators and files are rendered on a thread pool, but output and logs must not depend on
+//! how the work was scheduled. Work is mapped in parallel, then the results and the logs emitted
+//! while producing them are handed back in the original order.
+
+use anyhow::Result;
+use log::{Level, Log, Metadata, Record, SetLoggerError};
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+use std::cell::RefCell;
+
+thread_local! {
+    /// Logs held back by the innermost [`capture_logs`] running on this thread, if any.
+    static CAPTURED: RefCell<Option<Vec<CapturedLog>>> = RefCell::new(None);
+}
+
+/// Runs `f` on a pool of `jobs` threads, or one per CPU if `None`.
+pub fn install<T: Send>(jobs: Option<usize>, f: impl FnOnce() -> T + Send) -> Result<T> {
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(jobs.unwrap_or(0))
+        .build()?;
+    Ok(pool.install(f))
+}
+
+/// Maps `items` with `f` in parallel. Results and logs come back in the order of `items`, and the
+/// error of the first item that failed is returned, same as if they had run one after another.
+pub fn map_ordered<T, R, F>(items: &[T], f: F) -> Result<Vec<R>>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> Result<R> + Sync,
+{
+    let results = items
+        .par_iter()
+        .map(|item| capture_logs(|| f(item)))
+        .collect::<Vec<_>>();
+    let mut outputs = Vec::with_capacity(results.len());
+    for (result, logs) in results {
+        emit(logs);
+        outputs.push(result?);
+    }
+    Ok(outputs)
+}
+
+/// Sets up `env_logger` behind [`OrderedLogger`]. Errors if a logger was already set.
+pub fn init_logger() -> Result<(), SetLoggerError> {
+    let logger = env_logger::Builder::from_default_env().build();
+    let max_level = logger.filter();
+    log::set_boxed_logger(Box::new(OrderedLogger { inner: logger }))?;
+    log::set_max_level(max_level);
+    Ok(())
+}
+
+/// Holds back logs emitted inside [`capture_logs`] instead of passing them on, so they can be
+/// emitted in order later. Only used when protox sets up logging itself, otherwise logs from
+/// parallel work are emitted as they happen.
+struct OrderedLogger<L> {
+    inner: L,
+}
+
+impl<L: Log> Log for OrderedLogger<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.inner.enabled(record.metadata()) {
+            return;
+        }
+        let captured = CAPTURED.with(|captured| match captured.borrow_mut().as_mut() {
+            None => false,
+            Some(logs) => {
+                logs.push(CapturedLog::new(record));
+                true
+            }
+        });
+        if !captured {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+struct CapturedLog {
+    level: Level,
+    target: String,
+    message: String,
+    module_path: Option<String>,
+    file: Option<String>,
+    line: Option<u32>,
+}
+
+impl CapturedLog {
+    fn new(record: &Record) -> Self {
+        Self {
+            level: record.level(),
+            target: record.target().to_owned(),
+            message: record.args().to_string(),
+            module_path: record.module_path().map(str::to_owned),
+            file: record.file().map(str::to_owned),
+            line: record.line(),
+        }
+    }
+}
+
+/// Captures can nest, e.g. files rendered in parallel inside generators running in parallel.
+fn capture_logs<T>(f: impl FnOnce() -> T) -> (T, Vec<CapturedLog>) {
+    let outer = CAPTURED.with(|captured| captured.replace(Some(Vec::new())));
+    let result = f();
+    let logs = CAPTURED
+        .with(|captured| captured.replace(outer))
+        .unwrap_or_default();
+    (result, logs)
+}
+
+/// Emits logs through the global logger, which captures them again if this is nested.
+fn emit(logs: Vec<CapturedLog>) {
+    for log in logs {
+        log::logger().log(
+            &Record::builder()
+                .level(log.level)
+                .target(&log.target)
+                .args(format_args!("{}", log.message))
+                .module_path(log.module_path.as_deref())
+                .file(log.file.as_deref())
+                .line(log.line)
+                .build(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parallel::{capture_logs, install, map_ordered, OrderedLogger};
+    use anyhow::{anyhow, Result};
+    use log::{Level, Log, Metadata, Record};
+    use std::sync::Mutex;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn results_keep_order() -> Result<()> {
+        let items = (0..16).collect::<Vec<u64>>();
+        let results = install(Some(4), || {
+            map_ordered(&items, |i| {
+                // Later items finish first.
+                thread::sleep(Duration::from_millis(16 - i));
+                Ok(i * 2)
+            })
+        })??;
+        assert_eq!(results, items.iter().map(|i| i * 2).collect::<Vec<u64>>());
+        Ok(())
+    }
+
+    #[test]
+    fn first_error_in_order() -> Result<()> {
+        let items = (0..16).collect::<Vec<u64>>();
+        let result = install(Some(4), || {
+            map_ordered(&items, |i| {
+                thread::sleep(Duration::from_millis(16 - i));
+                match i {
+                    3 | 12 => Err(anyhow!("failed {}", i)),
+                    _ => Ok(*i),
+                }
+            })
+        })?;
+        assert_eq!(result.unwrap_err().to_string(), "failed 3");
+        Ok(())
+    }
+
+    #[test]
+    fn captured_logs_are_held_back() {
+        let logger = OrderedLogger {
+            inner: RecordingLogger::default(),
+        };
+        let ((), logs) = capture_logs(|| {
+            log(&logger, "inner");
+            let ((), nested) = capture_logs(|| log(&logger, "nested"));
+            assert_eq!(nested.len(), 1);
+        });
+        assert!(logger.inner.messages.lock().unwrap().is_empty());
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].message, "inner");
+        assert_eq!(logs[0].level, Level::Info);
+
+        log(&logger, "outer");
+        assert_eq!(*logger.inner.messages.lock().unwrap(), vec!["outer"]);
+    }
+
+    #[derive(Default)]
+    struct RecordingLogger {
+        messages: Mutex<Vec<String>>,
+    }
+
+    impl Log for RecordingLogger {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record) {
+            self.messages
+                .lock()
+                .unwrap()
+                .push(record.args().to_string());
+        }
+
+        fn flush(&self) {}
+    }
+
+    fn log(logger: &impl Log, message: &str) {
+        logger.log(
+            &Record::builder()
+                .level(Level::Info)
+                .args(format_args!("{}", message))
+                .build(),
+        );
+    }
+}
@@ -1,20 +1,27 @@
+use crate::diagnostic::{Diagnostic, Diagnostics, Severity};
 use crate::{util, Config};
 use anyhow::{anyhow, bail, Context, Result};
-use log::info;
+use log::{info, warn};
 use std::fs;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use util::DisplayNormalized;
 
 const PROTOC_ARG_PROTO_PATH: &str = "proto_path";
 const PROTOC_ARG_DESCRIPTOR_SET_OUT: &str = "descriptor_set_out";
 const PROTOC_ARG_INCLUDE_SOURCE_INFO: &str = "include_source_info";
+const PROTOC_ARG_ERROR_FORMAT: &str = "error_format";
+/// `path:line:column: message`, which is parsed into diagnostics.
+const ERROR_FORMAT_GCC: &str = "gcc";
+const WARNING_PREFIX: &str = "warning: ";
 
 /// Manages collecting args and the invocation of `protoc`, the protobuf compiler.
 pub struct Protoc {
     args: Vec<String>,
     input_files: Vec<String>,
+    /// Paths in protoc errors are relative to one of these.
+    proto_paths: Vec<PathBuf>,
 }
 
 impl Protoc {
@@ -32,10 +39,18 @@ impl Protoc {
             ));
             args.push(["--", PROTOC_ARG_INCLUDE_SOURCE_INFO].concat());
         }
-        args.append(&mut collect_extra_protoc_args(config));
+        let mut extra_args = collect_extra_protoc_args(config);
+        let error_format_arg = ["--", PROTOC_ARG_ERROR_FORMAT].concat();
+        if !extra_args.iter().any(|x| x.starts_with(&error_format_arg)) {
+            args.push(arg_with_value(PROTOC_ARG_ERROR_FORMAT, ERROR_FORMAT_GCC));
+        }
+        args.append(&mut extra_args);
+        let mut proto_paths = vec![config.input.clone()];
+        proto_paths.extend(config.includes.iter().map(PathBuf::from));
         Ok(Self {
             args,
             input_files: Vec::new(),
+            proto_paths,
         })
     }
 
@@ -63,6 +78,7 @@ impl Protoc {
             .args(temp_args)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
             .spawn()
             .with_context(|| {
                 format!(
@@ -81,10 +97,18 @@ impl Protoc {
         }
 
         let output = child.wait_with_output()?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let diagnostics = parse_diagnostics(&stderr, &self.proto_paths);
         if output.status.success() {
+            for diagnostic in diagnostics {
+                warn!("{}", diagnostic);
+            }
             Ok(output.stdout)
-        } else {
+        } else if diagnostics.is_empty() {
             Err(anyhow!("protoc exited with status {}", output.status))
+        } else {
+            Err(anyhow::Error::new(Diagnostics(diagnostics))
+                .context(format!("protoc exited with status {}", output.status)))
         }
     }
 
@@ -131,6 +155,54 @@ fn protoc_path() -> PathBuf {
     }
 }
 
+/// Parses protoc's stderr in the gcc error format. Lines without a location are still kept as
+/// diagnostics, since protoc reports some errors (e.g. bad args) that way.
+fn parse_diagnostics(stderr: &str, proto_paths: &[PathBuf]) -> Vec<Diagnostic> {
+    stderr
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| parse_diagnostic(line, proto_paths))
+        .collect()
+}
+
+fn parse_diagnostic(line: &str, proto_paths: &[PathBuf]) -> Diagnostic {
+    let (location, message) = match line.find(": ") {
+        None => return Diagnostic::error(line),
+        Some(i) => (&line[..i], &line[i + 2..]),
+    };
+    let diagnostic = match message.strip_prefix(WARNING_PREFIX) {
+        None => Diagnostic::error(message),
+        Some(message) => Diagnostic {
+            severity: Severity::Warning,
+            ..Diagnostic::error(message)
+        },
+    };
+    // Column, then line, then the path, which may itself contain ':' on windows.
+    let mut parts = location.rsplitn(3, ':');
+    let column = parts.next().and_then(|x| x.parse::<usize>().ok());
+    let line_no = parts.next().and_then(|x| x.parse::<usize>().ok());
+    let (path, line_no, column) = match (parts.next(), line_no, column) {
+        (Some(path), Some(_), Some(_)) => (path, line_no, column),
+        _ => (location, None, None),
+    };
+    // Otherwise it's a message prefixed with an arg or program name, e.g. "--cpp_out: ...".
+    if path.starts_with('-') || path.contains(' ') {
+        return Diagnostic::error(line);
+    }
+    diagnostic
+        .with_path(resolve_proto_path(path, proto_paths))
+        .with_position(line_no, column)
+}
+
+fn resolve_proto_path(path: &str, proto_paths: &[PathBuf]) -> PathBuf {
+    proto_paths
+        .iter()
+        .map(|root| root.join(path))
+        .find(|x| x.exists())
+        .unwrap_or_else(|| Path::new(path).to_path_buf())
+}
+
 pub fn arg_with_value(arg: &str, value: &str) -> String {
     ["--", arg, "=", value].concat()
 }
@@ -191,6 +263,61 @@ mod tests {
         Ok(())
     }
 
+    mod diagnostics {
+        use crate::diagnostic::Severity;
+        use crate::protoc::protoc::parse_diagnostics;
+        use anyhow::Result;
+        use std::fs;
+        use std::path::PathBuf;
+        use tempfile::tempdir;
+
+        #[test]
+        fn resolves_location_in_proto_paths() -> Result<()> {
+            let dir = tempdir()?;
+            fs::create_dir_all(dir.path().join("include/pkg"))?;
+            fs::write(dir.path().join("include/pkg/a.proto"), "")?;
+            let proto_paths = vec![dir.path().join("input"), dir.path().join("include")];
+            let diagnostics =
+                parse_diagnostics("pkg/a.proto:3:14: Expected \";\".\n", &proto_paths);
+            assert_eq!(diagnostics.len(), 1);
+            assert_eq!(diagnostics[0].severity, Severity::Error);
+            assert_eq!(diagnostics[0].message, "Expected \";\".");
+            assert_eq!(
+                diagnostics[0].path,
+                Some(dir.path().join("include/pkg/a.proto"))
+            );
+            assert_eq!(diagnostics[0].line, Some(3));
+            assert_eq!(diagnostics[0].column, Some(14));
+            Ok(())
+        }
+
+        #[test]
+        fn warning() {
+            let diagnostics =
+                parse_diagnostics("a.proto:1:1: warning: Import b.proto is unused.", &[]);
+            assert_eq!(diagnostics[0].severity, Severity::Warning);
+            assert_eq!(diagnostics[0].message, "Import b.proto is unused.");
+        }
+
+        #[test]
+        fn path_without_position() {
+            let diagnostics = parse_diagnostics("a.proto: File not found.", &[]);
+            assert_eq!(diagnostics[0].path, Some(PathBuf::from("a.proto")));
+            assert_eq!(diagnostics[0].line, None);
+            assert_eq!(diagnostics[0].message, "File not found.");
+        }
+
+        #[test]
+        fn without_location() {
+            let line = "--cpp_out: protoc-gen-cpp: Plugin failed.";
+            let diagnostics = parse_diagnostics(&format!("{}\nMissing input file.", line), &[]);
+            assert_eq!(diagnostics.len(), 2);
+            assert_eq!(diagnostics[0].path, None);
+            assert_eq!(diagnostics[0].message, line);
+            assert_eq!(diagnostics[1].message, "Missing input file.");
+        }
+    }
+
     fn quote_arg(arg: &str) -> String {
         ["\"", arg, "\""].concat()
     }
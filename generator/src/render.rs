@@ -7,6 +7,10 @@ pub trait Render {
     fn load(&mut self, input_root: &Path, overlays: &[PathBuf]) -> Result<()>;
     /// Reset is called between runs with different input/outputs.
     fn reset(&mut self);
-    /// Do the actual rendering to the `output_path` directory.
-    fn render(&self, descriptor_set: &FileDescriptorSet, output_path: &Path) -> Result<()>;
+    /// Do the actual rendering to the `output_path` directory. Returns the written files, sorted.
+    fn render(
+        &self,
+        descriptor_set: &FileDescriptorSet,
+        output_path: &Path,
+    ) -> Result<Vec<PathBuf>>;
 }
@@ -1,9 +1,11 @@
+use anyhow::anyhow;
 use heck::{
     ToKebabCase, ToLowerCamelCase, ToShoutyKebabCase, ToShoutySnakeCase, ToSnakeCase,
     ToUpperCamelCase,
 };
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 #[derive(Serialize, Deserialize, Copy, Clone, Debug)]
 pub enum Case {
@@ -94,6 +96,28 @@ impl Case {
     }
 }
 
+/// Parses any of the names accepted in config, e.g. `UpperCamel` or `lower_snake_case`.
+impl FromStr for Case {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        serde_yaml::from_value(serde_yaml::Value::String(name.to_owned()))
+            .map_err(|_| anyhow!("Unknown case: '{}'", name))
+    }
+}
+
+/// Names of the single-case template helpers and filters, e.g. `upper_camel`.
+pub const CASES: &[(&str, Case)] = &[
+    ("upper", Case::Upper),
+    ("lower", Case::Lower),
+    ("lower_snake", Case::LowerSnake),
+    ("upper_snake", Case::UpperSnake),
+    ("lower_kebab", Case::LowerKebab),
+    ("upper_kebab", Case::UpperKebab),
+    ("lower_camel", Case::LowerCamel),
+    ("upper_camel", Case::UpperCamel),
+];
+
 #[cfg(test)]
 mod tests {
     use crate::renderer::case::Case;
@@ -264,6 +288,23 @@ mod tests {
         .collect()
     }
 
+    mod from_str {
+        use crate::renderer::case::Case;
+
+        #[test]
+        fn config_names() -> anyhow::Result<()> {
+            assert_eq!("UpperCamel".parse::<Case>()?.rename("a_b"), "AB");
+            assert_eq!("lower_snake_case".parse::<Case>()?.rename("aB"), "a_b");
+            assert_eq!("SCREAMING_SNAKE".parse::<Case>()?.rename("aB"), "A_B");
+            Ok(())
+        }
+
+        #[test]
+        fn unknown_errors() {
+            assert!("Sponge".parse::<Case>().is_err());
+        }
+    }
+
     mod rename_file_name {
         use crate::renderer::case::Case;
         use std::path::PathBuf;
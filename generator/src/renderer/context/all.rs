@@ -0,0 +1,144 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use prost_types::FileDescriptorProto;
+use serde::{Deserialize, Serialize};
+
+use crate::renderer::context::FileContext;
+use crate::renderer::proto::PACKAGE_SEPARATOR;
+use crate::renderer::RendererConfig;
+
+pub type PackageNodes = BTreeMap<String, PackageNode>;
+
+/// Context for rendering a single output from the whole descriptor set, e.g. a type registry.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AllContext {
+    /// Every file that isn't ignored, in the order protoc returned them.
+    files: Vec<FileContext>,
+
+    /// Packages as a tree of package components, starting from the root components.
+    ///
+    /// ```txt
+    /// e.g. files with packages root.a and root.b:
+    ///     root: {
+    ///         package: "root",
+    ///         files: [],
+    ///         children: {
+    ///             a: { package: "root.a", files: ["root/a.proto"], children: {} },
+    ///             b: { package: "root.b", files: ["root/b.proto"], children: {} },
+    ///         }
+    ///     }
+    /// ```
+    /// Files without a package are in the node with an empty key.
+    package_tree: PackageNodes,
+}
+
+/// A component in the package tree.
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct PackageNode {
+    /// Full package up to and including this component, formatted the same as
+    /// `FileContext::package`.
+    package: String,
+
+    /// Source files declaring exactly this package.
+    files: Vec<String>,
+
+    children: PackageNodes,
+}
+
+impl AllContext {
+    pub fn new<'a>(
+        files: impl IntoIterator<Item = &'a FileDescriptorProto>,
+        config: &RendererConfig,
+    ) -> Result<Self> {
+        let mut context = Self {
+            files: vec![],
+            package_tree: Default::default(),
+        };
+        for file in files {
+            let file_context = FileContext::new(file, config)?;
+            let package = file.package.clone().unwrap_or_default();
+            context.insert_package(&package, &file_context, config);
+            context.files.push(file_context);
+        }
+        Ok(context)
+    }
+
+    pub fn files(&self) -> &Vec<FileContext> {
+        &self.files
+    }
+    pub fn package_tree(&self) -> &PackageNodes {
+        &self.package_tree
+    }
+
+    fn insert_package(&mut self, package: &str, file: &FileContext, config: &RendererConfig) {
+        let mut nodes = &mut self.package_tree;
+        let mut full_package = Vec::new();
+        let components = package.split(PACKAGE_SEPARATOR).collect::<Vec<&str>>();
+        let last = components.len() - 1;
+        for (i, component) in components.into_iter().enumerate() {
+            full_package.push(
+                config
+                    .keywords
+                    .escape(config.case_config.package.rename(component)),
+            );
+            let node = nodes
+                .entry(component.to_owned())
+                .or_insert_with(PackageNode::default);
+            node.package = full_package.join(&config.package_separator);
+            if i == last {
+                node.files.push(file.source_file().to_owned());
+            }
+            nodes = &mut node.children;
+        }
+    }
+}
+
+impl PackageNode {
+    pub fn package(&self) -> &str {
+        &self.package
+    }
+    pub fn files(&self) -> &Vec<String> {
+        &self.files
+    }
+    pub fn children(&self) -> &PackageNodes {
+        &self.children
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::renderer::context::AllContext;
+    use crate::renderer::tests::fake_file;
+    use crate::renderer::RendererConfig;
+    use anyhow::Result;
+
+    #[test]
+    fn package_tree() -> Result<()> {
+        let mut a = fake_file("root/a.proto", vec![], vec![]);
+        a.package = Some("root.a".to_owned());
+        let mut b = fake_file("root/b.proto", vec![], vec![]);
+        b.package = Some("root.b".to_owned());
+        let none = fake_file("none.proto", vec![], vec![]);
+        let context = AllContext::new(&[a, b, none], &RendererConfig::default())?;
+
+        assert_eq!(context.files().len(), 3);
+        let root = &context.package_tree()["root"];
+        assert_eq!(root.package(), "root");
+        assert!(root.files().is_empty());
+        assert_eq!(root.children()["a"].package(), "root.a");
+        assert_eq!(
+            root.children()["a"].files(),
+            &vec!["root/a.proto".to_owned()]
+        );
+        assert_eq!(
+            root.children()["b"].files(),
+            &vec!["root/b.proto".to_owned()]
+        );
+        assert_eq!(
+            context.package_tree()[""].files(),
+            &vec!["none.proto".to_owned()]
+        );
+        Ok(())
+    }
+}
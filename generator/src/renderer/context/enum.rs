@@ -6,14 +6,25 @@ use log::debug;
 use prost_types::{EnumDescriptorProto, EnumOptions, EnumValueDescriptorProto, EnumValueOptions};
 use serde::{Deserialize, Serialize, Serializer};
 
+use crate::renderer::case::Case;
+use crate::renderer::proto;
+use crate::renderer::renderer_config::UnspecifiedValue;
 use crate::renderer::RendererConfig;
 use crate::util;
 
+const UNSPECIFIED: &str = "UNSPECIFIED";
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct EnumContext {
-    // Name of this enum.
+    /// Name of this enum, with case config applied.
     name: String,
 
+    /// Name as declared in the proto, e.g. `Color`.
+    proto_name: String,
+
+    /// Proto name qualified by the package, e.g. `pkg.sub.Color`.
+    full_proto_name: String,
+
     // Values defined by this enum.
     values: Vec<EnumValueContext>,
 
@@ -51,9 +62,21 @@ pub struct EnumContext {
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct EnumValueContext {
+    /// Name with `enum_values` and case config applied.
     name: String,
+
+    /// Name as declared in the proto, e.g. `COLOR_RED`.
+    proto_name: String,
+
+    /// Proto name qualified by the enum, e.g. `pkg.sub.Color.COLOR_RED`. Note that protobuf
+    /// itself scopes values as siblings of their enum, i.e. `pkg.sub.COLOR_RED`.
+    full_proto_name: String,
+
     number: i32,
 
+    /// This is the zero value named `UNSPECIFIED` or `<ENUM>_UNSPECIFIED`.
+    is_unspecified: bool,
+
     /// Currently only supported in scripted renderer.
     #[serde(skip)]
     options: Option<EnumValueOptions>,
@@ -65,19 +88,23 @@ pub struct EnumValueContext {
 }
 
 impl EnumContext {
+    /// `scope` is the package, or the outer message's full name for nested enums.
     pub fn new(
         proto: &EnumDescriptorProto,
-        package: Option<&String>,
+        scope: Option<&String>,
         config: &RendererConfig,
     ) -> Result<Self> {
         log_new_enum(&proto.name);
+        let proto_name = util::str_or_error(&proto.name, || "Enum has no 'name'".to_owned())?;
         let context = Self {
-            name: name(&proto, config)?,
-            values: values(&proto, package, config)?,
+            name: name(proto_name, config),
+            proto_name: proto_name.to_owned(),
+            full_proto_name: proto::join_name(scope.map(String::as_str), proto_name),
+            values: values(&proto, scope, config)?,
             options: proto.options.clone(),
             overlays: config
                 .overlays
-                .by_target_opt_clone(&full_name(package, &proto.name)),
+                .by_target_opt_clone(&full_name(scope, &proto.name)),
         };
         Ok(context)
     }
@@ -85,6 +112,12 @@ impl EnumContext {
     pub fn name(&self) -> &str {
         &self.name
     }
+    pub fn proto_name(&self) -> &str {
+        &self.proto_name
+    }
+    pub fn full_proto_name(&self) -> &str {
+        &self.full_proto_name
+    }
     pub fn values(&self) -> &[EnumValueContext] {
         &self.values
     }
@@ -96,17 +129,26 @@ impl EnumContext {
 impl EnumValueContext {
     pub fn new(
         proto: &EnumValueDescriptorProto,
+        enum_name: &str,
+        enum_full_name: &str,
         message_full_name: Option<&String>,
         config: &RendererConfig,
     ) -> Result<Self> {
-        let (name, number) = match (proto.name.clone(), proto.number) {
+        let (proto_name, number) = match (proto.name.clone(), proto.number) {
             (Some(name), Some(number)) => (name, number),
             _ => return Err(error_invalid_value(&proto.name)),
         };
-        let case = &config.case_config.enum_value_name;
+        let is_unspecified = number == 0 && is_unspecified_name(&proto_name);
+        let name = match &config.enum_values.unspecified_name {
+            Some(unspecified_name) if is_unspecified => unspecified_name.clone(),
+            _ => value_name(&proto_name, enum_name, config),
+        };
         Ok(EnumValueContext {
-            name: case.rename(&name),
+            name,
+            full_proto_name: proto::join_name(Some(enum_full_name), &proto_name),
+            proto_name,
             number,
+            is_unspecified,
             options: proto.options.clone(),
             overlays: config
                 .overlays
@@ -117,9 +159,18 @@ impl EnumValueContext {
     pub fn name(&self) -> &str {
         &self.name
     }
+    pub fn proto_name(&self) -> &str {
+        &self.proto_name
+    }
+    pub fn full_proto_name(&self) -> &str {
+        &self.full_proto_name
+    }
     pub fn number(&self) -> i32 {
         self.number
     }
+    pub fn is_unspecified(&self) -> bool {
+        self.is_unspecified
+    }
     pub fn options(&self) -> &Option<EnumValueOptions> {
         &self.options
     }
@@ -141,32 +192,70 @@ fn log_new_enum(name: &Option<String>) {
     debug!("Creating message context: {}", util::str_or_unknown(name));
 }
 
-fn full_name(package: Option<&String>, name: &Option<String>) -> Option<String> {
-    Some(format!("{}.{}", package?, name.as_ref()?))
+fn full_name(scope: Option<&String>, name: &Option<String>) -> Option<String> {
+    Some(format!("{}.{}", scope?, name.as_ref()?))
 }
 
-fn name(proto: &EnumDescriptorProto, config: &RendererConfig) -> Result<String> {
-    let name = util::str_or_error(&proto.name, || "Enum has no 'name'".to_owned())?;
-    Ok(config.case_config.enum_name.rename(name))
+fn name(proto_name: &str, config: &RendererConfig) -> String {
+    config
+        .keywords
+        .escape(config.case_config.enum_name.rename(proto_name))
 }
 
 fn values(
     proto: &EnumDescriptorProto,
-    package: Option<&String>,
+    scope: Option<&String>,
     config: &RendererConfig,
 ) -> Result<Vec<EnumValueContext>> {
+    let enum_name = util::str_or_error(&proto.name, || "Enum has no 'name'".to_owned())?;
+    let enum_full_name = proto::join_name(scope.map(String::as_str), enum_name);
     let mut values = Vec::new();
     for proto_value in &proto.value {
-        let message_full_name = full_name(package, &proto.name);
-        values.push(EnumValueContext::new(
+        let message_full_name = full_name(scope, &proto.name);
+        let value = EnumValueContext::new(
             proto_value,
+            enum_name,
+            &enum_full_name,
             message_full_name.as_ref(),
             config,
-        )?);
+        )?;
+        if value.is_unspecified && config.enum_values.unspecified == UnspecifiedValue::Drop {
+            continue;
+        }
+        values.push(value);
     }
     Ok(values)
 }
 
+fn value_name(proto_name: &str, enum_name: &str, config: &RendererConfig) -> String {
+    let name = if config.enum_values.strip_prefix {
+        strip_enum_prefix(proto_name, enum_name)
+    } else {
+        proto_name
+    };
+    let case = &config.case_config.enum_value_name;
+    config.keywords.escape(case.rename(name))
+}
+
+/// Strips the proto style prefix, e.g. `COLOR_` from `COLOR_RED` in enum `Color`, unless the
+/// result would be empty or start with a digit.
+fn strip_enum_prefix<'a>(value_name: &'a str, enum_name: &str) -> &'a str {
+    let prefix = format!("{}_", Case::UpperSnake.rename(enum_name));
+    if !value_name.to_uppercase().starts_with(&prefix) {
+        return value_name;
+    }
+    let stripped = &value_name[prefix.len()..];
+    match stripped.chars().next() {
+        Some(c) if !c.is_ascii_digit() => stripped,
+        _ => value_name,
+    }
+}
+
+fn is_unspecified_name(name: &str) -> bool {
+    let name = name.to_uppercase();
+    name == UNSPECIFIED || name.ends_with(&format!("_{}", UNSPECIFIED))
+}
+
 fn error_invalid_value(name: &Option<String>) -> anyhow::Error {
     anyhow!(
         "Enum '{}' has a value missing name and/or number.",
@@ -219,6 +308,25 @@ mod tests {
         proto.name = Some(enum_name.clone());
         let context = EnumContext::new(&proto, None, &config)?;
         assert_eq!(context.name, "MSG_NAME");
+        assert_eq!(context.proto_name, "MsgName");
+        Ok(())
+    }
+
+    #[test]
+    fn full_proto_names() -> Result<()> {
+        let mut config = RendererConfig::default();
+        config.case_config.enum_value_name = Case::UpperCamel;
+        let mut proto = EnumDescriptorProto::default();
+        proto.name = Some("Color".to_owned());
+        proto.value.push(named_enum_value("COLOR_RED", 1));
+        let package = "pkg.sub".to_owned();
+        let context = EnumContext::new(&proto, Some(&package), &config)?;
+        assert_eq!(context.full_proto_name(), "pkg.sub.Color");
+        assert_eq!(context.values[0].name(), "ColorRed");
+        assert_eq!(
+            context.values[0].full_proto_name(),
+            "pkg.sub.Color.COLOR_RED"
+        );
         Ok(())
     }
 
@@ -261,6 +369,89 @@ mod tests {
         Ok(())
     }
 
+    mod enum_values {
+        use anyhow::Result;
+        use prost_types::EnumDescriptorProto;
+
+        use crate::renderer::case::Case;
+        use crate::renderer::context::r#enum::tests::named_enum_value;
+        use crate::renderer::context::EnumContext;
+        use crate::renderer::renderer_config::UnspecifiedValue;
+        use crate::renderer::RendererConfig;
+
+        #[test]
+        fn strip_prefix() -> Result<()> {
+            let mut config = config();
+            config.enum_values.strip_prefix = true;
+            let context = EnumContext::new(&proto(), None, &config)?;
+            let names = names(&context);
+            assert_eq!(names, vec!["Unspecified", "Red", "DarkBlue", "Color2"]);
+            assert_eq!(context.values()[1].proto_name(), "COLOR_RED");
+            Ok(())
+        }
+
+        #[test]
+        fn keeps_prefix_by_default() -> Result<()> {
+            let context = EnumContext::new(&proto(), None, &config())?;
+            assert_eq!(names(&context)[1], "ColorRed");
+            Ok(())
+        }
+
+        #[test]
+        fn drop_unspecified() -> Result<()> {
+            let mut config = config();
+            config.enum_values.unspecified = UnspecifiedValue::Drop;
+            let context = EnumContext::new(&proto(), None, &config)?;
+            assert_eq!(context.values().len(), 3);
+            assert!(!context.values()[0].is_unspecified());
+            Ok(())
+        }
+
+        #[test]
+        fn rename_unspecified() -> Result<()> {
+            let mut config = config();
+            config.enum_values.unspecified_name = Some("None".to_owned());
+            let context = EnumContext::new(&proto(), None, &config)?;
+            assert!(context.values()[0].is_unspecified());
+            assert_eq!(context.values()[0].name(), "None");
+            assert_eq!(context.values()[0].proto_name(), "COLOR_UNSPECIFIED");
+            Ok(())
+        }
+
+        #[test]
+        fn unspecified_must_be_zero() -> Result<()> {
+            let mut proto = proto();
+            proto.value[0].number = Some(5);
+            let context = EnumContext::new(&proto, None, &config())?;
+            assert!(!context.values()[0].is_unspecified());
+            Ok(())
+        }
+
+        fn config() -> RendererConfig {
+            let mut config = RendererConfig::default();
+            config.case_config.enum_value_name = Case::UpperCamel;
+            config
+        }
+
+        fn proto() -> EnumDescriptorProto {
+            EnumDescriptorProto {
+                name: Some("Color".to_owned()),
+                value: vec![
+                    named_enum_value("COLOR_UNSPECIFIED", 0),
+                    named_enum_value("COLOR_RED", 1),
+                    named_enum_value("COLOR_DARK_BLUE", 2),
+                    // Stripping would leave an invalid identifier.
+                    named_enum_value("COLOR_2", 3),
+                ],
+                ..Default::default()
+            }
+        }
+
+        fn names(context: &EnumContext) -> Vec<&str> {
+            context.values().iter().map(|value| value.name()).collect()
+        }
+    }
+
     #[test]
     fn overlay_enum() -> Result<()> {
         let proto = EnumDescriptorProto {
@@ -306,7 +497,13 @@ mod tests {
             ),
             ..Default::default()
         };
-        let context = EnumValueContext::new(&proto, Some(&message_name), &config)?;
+        let context = EnumValueContext::new(
+            &proto,
+            "EnumName",
+            &message_name,
+            Some(&message_name),
+            &config,
+        )?;
         assert_eq!(
             &context.overlays.get("some_key").expect("key did not exist"),
             &"some_value"
@@ -2,21 +2,32 @@ use std::collections::HashMap;
 
 use anyhow::Result;
 use log::debug;
-use prost_types::field_descriptor_proto::Label;
+use prost_types::field_descriptor_proto::{Label, Type};
 use prost_types::{FieldDescriptorProto, FieldOptions};
 use serde::{Deserialize, Serialize, Serializer};
 
 use crate::renderer::context::message;
 use crate::renderer::context::overlayed::Overlayed;
+use crate::renderer::context::proto_type;
 use crate::renderer::context::proto_type::ProtoType;
-use crate::renderer::RendererConfig;
+use crate::renderer::renderer_config::TypePatterns;
+use crate::renderer::{proto, well_known, RendererConfig};
 use crate::util;
 
+/// Used for `full_type` of maps when `type_patterns.map` isn't set.
+const DEFAULT_MAP_PATTERN: &str = "map<{key}, {value}>";
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct FieldContext {
-    // Name of the field.
+    /// Name of the field, with case config and `field_name_override` applied.
     field_name: String,
 
+    /// Name as declared in the proto, e.g. `user_id`.
+    proto_name: String,
+
+    /// Proto name qualified by the package and message, e.g. `pkg.sub.User.user_id`.
+    full_proto_name: String,
+
     /// Type as defined by type config or literal type name. Only valid if `is_map` is false.
     ///
     /// If `is_map` is true, use `*_key_type` and `*_value_type` fields instead.
@@ -46,6 +57,32 @@ pub struct FieldContext {
     /// This field is part of a oneof type.
     is_oneof: bool,
 
+    /// What kind of type this field has: `scalar`, `enum`, `message`, `map` or `well_known`,
+    /// where `well_known` is any `google.protobuf.*` message like `Timestamp`.
+    type_kind: TypeKind,
+
+    /// Proto name of the scalar type, e.g. `int32` or `sfixed64`, regardless of type config.
+    /// Only set if `type_kind` is `scalar`.
+    proto_scalar_type: Option<String>,
+
+    /// This field's type is a well-known wrapper type like `google.protobuf.StringValue`, mapped
+    /// to its scalar type by `well_known_types`, so it may not have a value.
+    is_nullable: bool,
+
+    /// This field is declared proto3 `optional`, or `is_nullable` is true.
+    is_optional: bool,
+
+    /// The relative type with `type_patterns` applied, e.g. `Vec<Option<Box<sub.Message>>>`.
+    ///
+    /// ```txt
+    ///      pattern     applied to
+    ///      message     singular and repeated fields with a message type
+    ///      optional    `is_optional` fields
+    ///      repeated    `is_array` fields
+    ///      map         `is_map` fields, with the relative key and value types
+    /// ```
+    full_type: String,
+
     /// When `is_map` is true, equivalent to `fully_qualified_type` for the key type of the map.
     fully_qualified_key_type: Option<String>,
 
@@ -84,6 +121,11 @@ pub struct FieldContext {
     #[serde(serialize_with = "serialize_field_options", skip_deserializing)]
     options: Option<FieldOptions>,
 
+    /// Fully qualified proto name of the message or enum type, or of the value type for maps,
+    /// used to look the type up in the `TypeRegistry`. None for scalar types.
+    #[serde(skip)]
+    proto_type_name: Option<String>,
+
     // Config overlays applied to this File.
     // Only available in scripted renderer.
     #[serde(skip)]
@@ -116,22 +158,42 @@ impl FieldContext {
         message_name: Option<&String>,
         config: &RendererConfig,
     ) -> Result<Self> {
-        let type_path = ProtoType::from_field(field)?.to_type_path(config)?;
+        let proto_type = ProtoType::from_field(field)?;
+        let type_path = proto_type.to_type_path(config)?;
+        let type_kind = TypeKind::from_field(field);
         let parent_prefix = config.field_relative_parent_prefix.as_ref();
-        let context = Self {
+        let is_nullable = field
+            .type_name
+            .as_deref()
+            .map(|type_name| well_known::is_nullable(type_name, config))
+            .unwrap_or(false);
+        let mut context = Self {
             field_name: field_name(field, &config)?,
+            proto_name: util::str_or_unknown(&field.name).to_owned(),
+            full_proto_name: full_proto_name(package, message_name, &field.name),
             fully_qualified_type: Some(type_path.to_string()),
             relative_type: Some(type_path.relative_to(package, parent_prefix)),
             is_array: is_array(field),
             is_map: false,
             is_oneof: is_oneof(field),
+            type_kind,
+            proto_scalar_type: proto_scalar_type(field).map(str::to_owned),
+            is_nullable,
+            is_optional: field.proto3_optional.unwrap_or(false) || is_nullable,
+            full_type: String::new(),
             fully_qualified_key_type: None,
             fully_qualified_value_type: None,
             relative_key_type: None,
             relative_value_type: None,
             options: field.options.clone(),
+            proto_type_name: field
+                .type_name
+                .as_deref()
+                .map(|name| proto::normalize_prefix(name).to_owned()),
             overlays: overlays(package, message_name, &field.name, config),
         };
+        let is_message = is_message_ref(type_kind, &proto_type, config)?;
+        context.full_type = context.apply_type_patterns(&config.type_patterns, is_message);
         Ok(context)
     }
 
@@ -145,26 +207,70 @@ impl FieldContext {
         let key_type_path = entry.key.to_type_path(config)?;
         let value_type_path = entry.value.to_type_path(config)?;
         let parent_prefix = config.field_relative_parent_prefix.as_ref();
-        let context = Self {
+        let mut context = Self {
             field_name: field_name(field, &config)?,
+            proto_name: util::str_or_unknown(&field.name).to_owned(),
+            full_proto_name: full_proto_name(package, message_name, &field.name),
             fully_qualified_type: None,
             relative_type: None,
             is_array: false,
             is_map: true,
             is_oneof: is_oneof(field),
+            type_kind: TypeKind::Map,
+            proto_scalar_type: None,
+            is_nullable: false,
+            is_optional: false,
+            full_type: String::new(),
             fully_qualified_key_type: Some(key_type_path.to_string()),
             fully_qualified_value_type: Some(value_type_path.to_string()),
             relative_key_type: Some(key_type_path.relative_to(package, parent_prefix)),
             relative_value_type: Some(value_type_path.relative_to(package, parent_prefix)),
             options: field.options.clone(),
+            proto_type_name: entry.value.type_name().map(str::to_owned),
             overlays: overlays(package, message_name, &field.name, config),
         };
+        let is_message = is_message_ref(entry.value_kind, &entry.value, config)?;
+        context.full_type = context.apply_type_patterns(&config.type_patterns, is_message);
         Ok(context)
     }
 
+    /// `is_message` is for the field's type, or the value type of maps.
+    fn apply_type_patterns(&self, patterns: &TypePatterns, is_message: bool) -> String {
+        if self.is_map {
+            let mut value = self.relative_value_type.clone().unwrap_or_default();
+            if is_message {
+                value = apply_pattern(&patterns.message, value);
+            }
+            let pattern = patterns.map.as_deref().unwrap_or(DEFAULT_MAP_PATTERN);
+            return pattern
+                .replace(
+                    "{key}",
+                    self.relative_key_type.as_deref().unwrap_or_default(),
+                )
+                .replace("{value}", &value);
+        }
+        let mut full_type = self.relative_type.clone().unwrap_or_default();
+        if is_message {
+            full_type = apply_pattern(&patterns.message, full_type);
+        }
+        if self.is_optional {
+            full_type = apply_pattern(&patterns.optional, full_type);
+        }
+        if self.is_array {
+            full_type = apply_pattern(&patterns.repeated, full_type);
+        }
+        full_type
+    }
+
     pub fn name(&self) -> &str {
         &self.field_name
     }
+    pub fn proto_name(&self) -> &str {
+        &self.proto_name
+    }
+    pub fn full_proto_name(&self) -> &str {
+        &self.full_proto_name
+    }
     pub fn fully_qualified_type(&self) -> Option<&String> {
         self.fully_qualified_type.as_ref()
     }
@@ -180,6 +286,21 @@ impl FieldContext {
     pub fn is_oneof(&self) -> bool {
         self.is_oneof
     }
+    pub fn type_kind(&self) -> TypeKind {
+        self.type_kind
+    }
+    pub fn proto_scalar_type(&self) -> Option<&String> {
+        self.proto_scalar_type.as_ref()
+    }
+    pub fn is_nullable(&self) -> bool {
+        self.is_nullable
+    }
+    pub fn is_optional(&self) -> bool {
+        self.is_optional
+    }
+    pub fn full_type(&self) -> &str {
+        &self.full_type
+    }
     pub fn fully_qualified_key_type(&self) -> Option<&String> {
         self.fully_qualified_key_type.as_ref()
     }
@@ -195,6 +316,46 @@ impl FieldContext {
     pub fn options(&self) -> Option<&FieldOptions> {
         self.options.as_ref()
     }
+    pub fn proto_type_name(&self) -> Option<&String> {
+        self.proto_type_name.as_ref()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TypeKind {
+    Scalar,
+    Enum,
+    Message,
+    Map,
+    WellKnown,
+}
+
+impl TypeKind {
+    /// Map fields are detected by the owning message, so they're never returned here.
+    pub(crate) fn from_field(field: &FieldDescriptorProto) -> Self {
+        if proto_scalar_type(field).is_some() {
+            return TypeKind::Scalar;
+        }
+        if field.r#type == Some(Type::Enum as i32) {
+            return TypeKind::Enum;
+        }
+        match &field.type_name {
+            None => TypeKind::Scalar,
+            Some(type_name) if is_well_known(type_name) => TypeKind::WellKnown,
+            Some(_) => TypeKind::Message,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TypeKind::Scalar => "scalar",
+            TypeKind::Enum => "enum",
+            TypeKind::Message => "message",
+            TypeKind::Map => "map",
+            TypeKind::WellKnown => "well_known",
+        }
+    }
 }
 
 impl Overlayed for FieldContext {
@@ -203,6 +364,15 @@ impl Overlayed for FieldContext {
     }
 }
 
+/// Well-known types are messages too, unless they're rendered as a native type.
+fn is_message_ref(kind: TypeKind, proto_type: &ProtoType, config: &RendererConfig) -> Result<bool> {
+    match kind {
+        TypeKind::Message => Ok(true),
+        TypeKind::WellKnown => Ok(!proto_type.is_native(config)?),
+        _ => Ok(false),
+    }
+}
+
 fn log_new_field(name: &Option<String>) {
     debug!("Creating field context: {}", util::str_or_unknown(name));
 }
@@ -220,6 +390,15 @@ fn full_name(
     ))
 }
 
+fn full_proto_name(
+    package: Option<&String>,
+    message_name: Option<&String>,
+    field_name: &Option<String>,
+) -> String {
+    let message_name = message_name.map(|name| proto::join_name(package.map(String::as_str), name));
+    proto::join_name(message_name.as_deref(), util::str_or_unknown(field_name))
+}
+
 fn overlays(
     package: Option<&String>,
     message_name: Option<&String>,
@@ -235,11 +414,11 @@ fn field_name(field: &FieldDescriptorProto, config: &RendererConfig) -> Result<S
     let field_name = util::str_or_error(&field.name, || "Field has no 'name'".to_owned())?;
     let case = config.case_config.field_name;
     let renamed = case.rename(field_name);
-    let result = config
-        .field_name_override
-        .get(&renamed)
-        .map(String::clone)
-        .unwrap_or(renamed);
+    // Explicit overrides take precedence over keyword escaping.
+    let result = match config.field_name_override.get(&renamed) {
+        Some(name) => name.clone(),
+        None => config.keywords.escape(renamed),
+    };
     Ok(result)
 }
 
@@ -254,6 +433,21 @@ fn is_oneof(field: &FieldDescriptorProto) -> bool {
     field.oneof_index.is_some()
 }
 
+fn apply_pattern(pattern: &Option<String>, inner: String) -> String {
+    match pattern {
+        None => inner,
+        Some(pattern) => pattern.replace("{}", &inner),
+    }
+}
+
+fn proto_scalar_type(field: &FieldDescriptorProto) -> Option<&'static str> {
+    field.r#type.and_then(proto_type::scalar_type_name)
+}
+
+fn is_well_known(type_name: &str) -> bool {
+    well_known::well_known_name(type_name).is_some()
+}
+
 fn serialize_field_options<S: Serializer>(
     _options: &Option<FieldOptions>,
     serializer: S,
@@ -311,6 +505,29 @@ mod tests {
         field.type_name = Some(primitive::FLOAT.to_owned());
         let context = FieldContext::new(&field, None, None, &message::MapData::new(), &config)?;
         assert_eq!(context.field_name.to_owned(), new_name);
+        assert_eq!(context.proto_name, "bad_name");
+        Ok(())
+    }
+
+    #[test]
+    fn proto_names() -> Result<()> {
+        let mut config = RendererConfig::default();
+        config.case_config.field_name = Case::LowerCamel;
+        let mut field = FieldDescriptorProto::default();
+        field.name = Some("user_id".to_owned());
+        field.type_name = Some(primitive::FLOAT.to_owned());
+        let package = "pkg.sub".to_owned();
+        let message_name = "User".to_owned();
+        let context = FieldContext::new(
+            &field,
+            Some(&package),
+            Some(&message_name),
+            &message::MapData::new(),
+            &config,
+        )?;
+        assert_eq!(context.name(), "userId");
+        assert_eq!(context.proto_name(), "user_id");
+        assert_eq!(context.full_proto_name(), "pkg.sub.User.user_id");
         Ok(())
     }
 
@@ -480,6 +697,7 @@ mod tests {
         use prost_types::FieldDescriptorProto;
 
         use crate::renderer::context::field::tests::field_with_required;
+        use crate::renderer::context::field::TypeKind;
         use crate::renderer::context::message::MapEntryData;
         use crate::renderer::context::proto_type::{primitive_type_name, ProtoType};
         use crate::renderer::context::{message, FieldContext};
@@ -497,12 +715,14 @@ mod tests {
                 MapEntryData {
                     key: ProtoType::Type(int_proto_type),
                     value: ProtoType::TypeName(".root.sub.inner.TypeName".to_owned()),
+                    value_kind: TypeKind::Message,
                 },
             );
 
             let expected_key = primitive_type_name(int_proto_type, &config)?;
             let context = FieldContext::new(&field, Some(&package), None, &map_data, &config)?;
             assert!(context.is_map);
+            assert_eq!(context.type_kind, TypeKind::Map);
             assert_eq!(
                 context.fully_qualified_key_type,
                 Some(expected_key.to_owned())
@@ -531,6 +751,7 @@ mod tests {
                 MapEntryData {
                     key: ProtoType::Type(int_proto_type),
                     value: ProtoType::Type(float_proto_type),
+                    value_kind: TypeKind::Scalar,
                 },
             );
 
@@ -619,6 +840,277 @@ mod tests {
         Ok(())
     }
 
+    mod type_kind {
+        use anyhow::Result;
+        use prost_types::field_descriptor_proto::Type;
+        use prost_types::FieldDescriptorProto;
+
+        use crate::renderer::context::field::tests::field_with_required;
+        use crate::renderer::context::field::TypeKind;
+        use crate::renderer::context::{message, FieldContext};
+        use crate::renderer::keywords::KeywordPreset;
+        use crate::renderer::RendererConfig;
+
+        #[test]
+        fn scalar() -> Result<()> {
+            let context = context(&field_with_required())?;
+            assert_eq!(context.type_kind(), TypeKind::Scalar);
+            assert_eq!(context.proto_scalar_type(), Some(&"float".to_owned()));
+            Ok(())
+        }
+
+        #[test]
+        fn enum_type() -> Result<()> {
+            let context = context(&complex_field(Type::Enum, ".pkg.Kind"))?;
+            assert_eq!(context.type_kind(), TypeKind::Enum);
+            assert_eq!(context.proto_scalar_type(), None);
+            Ok(())
+        }
+
+        #[test]
+        fn message() -> Result<()> {
+            let context = context(&complex_field(Type::Message, ".pkg.Message"))?;
+            assert_eq!(context.type_kind(), TypeKind::Message);
+            Ok(())
+        }
+
+        #[test]
+        fn well_known() -> Result<()> {
+            let field = complex_field(Type::Message, ".google.protobuf.Timestamp");
+            assert_eq!(context(&field)?.type_kind(), TypeKind::WellKnown);
+            Ok(())
+        }
+
+        #[test]
+        fn well_known_wrapper_is_nullable() -> Result<()> {
+            let mut config = RendererConfig::default();
+            config.well_known_types = Some(Default::default());
+            config
+                .type_config
+                .insert("string".to_owned(), "String".to_owned());
+            let field = complex_field(Type::Message, ".google.protobuf.StringValue");
+            let context = FieldContext::new(&field, None, None, &message::MapData::new(), &config)?;
+            assert_eq!(context.type_kind(), TypeKind::WellKnown);
+            assert_eq!(context.fully_qualified_type(), Some(&"String".to_owned()));
+            assert!(context.is_nullable());
+            Ok(())
+        }
+
+        #[test]
+        fn type_references_escape_keywords() -> Result<()> {
+            let mut config = RendererConfig::default();
+            config.keywords.presets.push(KeywordPreset::Rust);
+            let field = complex_field(Type::Message, ".mod.Type");
+            let context = FieldContext::new(&field, None, None, &message::MapData::new(), &config)?;
+            assert_eq!(
+                context.fully_qualified_type(),
+                Some(&"mod_.Type".to_owned())
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn serialized_snake_case() -> Result<()> {
+            assert_eq!(
+                serde_json::to_string(&TypeKind::WellKnown)?,
+                format!("\"{}\"", TypeKind::WellKnown.as_str())
+            );
+            Ok(())
+        }
+
+        fn complex_field(r#type: Type, type_name: &str) -> FieldDescriptorProto {
+            let mut field = field_with_required();
+            field.r#type = Some(r#type as i32);
+            field.type_name = Some(type_name.to_owned());
+            field
+        }
+
+        fn context(field: &FieldDescriptorProto) -> Result<FieldContext> {
+            let config = RendererConfig::default();
+            FieldContext::new(field, None, None, &message::MapData::new(), &config)
+        }
+    }
+
+    mod type_patterns {
+        use anyhow::Result;
+        use prost_types::field_descriptor_proto::{Label, Type};
+        use prost_types::FieldDescriptorProto;
+
+        use crate::renderer::context::field::tests::field_with_required;
+        use crate::renderer::context::field::TypeKind;
+        use crate::renderer::context::message::{MapData, MapEntryData};
+        use crate::renderer::context::proto_type::ProtoType;
+        use crate::renderer::context::FieldContext;
+        use crate::renderer::renderer_config::TypePatterns;
+        use crate::renderer::well_known::{WellKnownPreset, WellKnownTypesConfig};
+        use crate::renderer::RendererConfig;
+
+        #[test]
+        fn unset_patterns_use_relative_type() -> Result<()> {
+            let context = context(&message_field(), &RendererConfig::default())?;
+            assert_eq!(context.full_type(), "sub.Message");
+            Ok(())
+        }
+
+        #[test]
+        fn repeated_message() -> Result<()> {
+            let mut field = message_field();
+            field.label = Some(Label::Repeated as i32);
+            let context = context(&field, &config())?;
+            assert_eq!(context.full_type(), "Vec<Box<sub.Message>>");
+            Ok(())
+        }
+
+        #[test]
+        fn optional_scalar() -> Result<()> {
+            let mut field = field_with_required();
+            field.proto3_optional = Some(true);
+            let context = context(&field, &config())?;
+            assert!(context.is_optional());
+            assert_eq!(context.full_type(), "Option<float>");
+            Ok(())
+        }
+
+        #[test]
+        fn scalar_without_patterns() -> Result<()> {
+            let context = context(&field_with_required(), &config())?;
+            assert_eq!(context.full_type(), "float");
+            Ok(())
+        }
+
+        #[test]
+        fn map() -> Result<()> {
+            let mut field = field_with_required();
+            field.type_name = Some(".pkg.MapEntry".to_owned());
+            let mut map_data = MapData::new();
+            map_data.insert(
+                ".pkg.MapEntry".to_owned(),
+                MapEntryData {
+                    key: ProtoType::Type(Type::String as i32),
+                    value: ProtoType::TypeName(".pkg.sub.Message".to_owned()),
+                    value_kind: TypeKind::Message,
+                },
+            );
+            let package = "pkg".to_owned();
+            let config = config();
+            let context = FieldContext::new(&field, Some(&package), None, &map_data, &config)?;
+            assert_eq!(context.full_type(), "HashMap<string, Box<sub.Message>>");
+
+            let context = FieldContext::new(
+                &field,
+                Some(&package),
+                None,
+                &map_data,
+                &RendererConfig::default(),
+            )?;
+            assert_eq!(context.full_type(), "map<string, sub.Message>");
+            Ok(())
+        }
+
+        #[test]
+        fn map_of_enums() -> Result<()> {
+            let mut field = field_with_required();
+            field.type_name = Some(".pkg.MapEntry".to_owned());
+            let mut map_data = MapData::new();
+            map_data.insert(
+                ".pkg.MapEntry".to_owned(),
+                MapEntryData {
+                    key: ProtoType::Type(Type::String as i32),
+                    value: ProtoType::TypeName(".pkg.sub.Enum".to_owned()),
+                    value_kind: TypeKind::Enum,
+                },
+            );
+            let package = "pkg".to_owned();
+            let context = FieldContext::new(&field, Some(&package), None, &map_data, &config())?;
+            assert_eq!(context.full_type(), "HashMap<string, sub.Enum>");
+            Ok(())
+        }
+
+        #[test]
+        fn well_known_message() -> Result<()> {
+            let context = context(&timestamp_field(), &config())?;
+            assert_eq!(context.type_kind(), TypeKind::WellKnown);
+            assert_eq!(context.full_type(), "Box<google.protobuf.Timestamp>");
+            Ok(())
+        }
+
+        #[test]
+        fn well_known_native_type() -> Result<()> {
+            let config = RendererConfig {
+                well_known_types: Some(WellKnownTypesConfig {
+                    preset: Some(WellKnownPreset::Rust),
+                    types: Default::default(),
+                    nullable_wrappers: false,
+                }),
+                ..config()
+            };
+            let context = context(&timestamp_field(), &config)?;
+            assert_eq!(context.full_type(), "::prost_types::Timestamp");
+            Ok(())
+        }
+
+        #[test]
+        fn map_of_well_known_native_type() -> Result<()> {
+            let mut field = field_with_required();
+            field.type_name = Some(".pkg.MapEntry".to_owned());
+            let mut map_data = MapData::new();
+            map_data.insert(
+                ".pkg.MapEntry".to_owned(),
+                MapEntryData {
+                    key: ProtoType::Type(Type::String as i32),
+                    value: ProtoType::TypeName(".google.protobuf.Timestamp".to_owned()),
+                    value_kind: TypeKind::WellKnown,
+                },
+            );
+            let package = "pkg".to_owned();
+            let config = RendererConfig {
+                well_known_types: Some(WellKnownTypesConfig {
+                    preset: Some(WellKnownPreset::Rust),
+                    types: Default::default(),
+                    nullable_wrappers: false,
+                }),
+                ..config()
+            };
+            let context = FieldContext::new(&field, Some(&package), None, &map_data, &config)?;
+            assert_eq!(
+                context.full_type(),
+                "HashMap<string, ::prost_types::Timestamp>"
+            );
+            Ok(())
+        }
+
+        fn timestamp_field() -> FieldDescriptorProto {
+            let mut field = field_with_required();
+            field.r#type = Some(Type::Message as i32);
+            field.type_name = Some(".google.protobuf.Timestamp".to_owned());
+            field
+        }
+
+        fn config() -> RendererConfig {
+            RendererConfig {
+                type_patterns: TypePatterns {
+                    repeated: Some("Vec<{}>".to_owned()),
+                    map: Some("HashMap<{key}, {value}>".to_owned()),
+                    optional: Some("Option<{}>".to_owned()),
+                    message: Some("Box<{}>".to_owned()),
+                },
+                ..Default::default()
+            }
+        }
+
+        fn message_field() -> FieldDescriptorProto {
+            let mut field = field_with_required();
+            field.r#type = Some(Type::Message as i32);
+            field.type_name = Some(".pkg.sub.Message".to_owned());
+            field
+        }
+
+        fn context(field: &FieldDescriptorProto, config: &RendererConfig) -> Result<FieldContext> {
+            let package = "pkg".to_owned();
+            FieldContext::new(field, Some(&package), None, &MapData::new(), config)
+        }
+    }
+
     fn field_with_required() -> FieldDescriptorProto {
         let mut field = FieldDescriptorProto::default();
         field.name = Some("field_name".to_owned());
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use anyhow::{anyhow, Result};
 use log::debug;
@@ -116,6 +116,7 @@ fn package(file: &FileDescriptorProto, config: &RendererConfig) -> String {
             let mut type_path = TypePath::from_package(package);
             type_path.set_separator(&config.package_separator);
             type_path.set_package_case(Some(config.case_config.package));
+            type_path.set_keywords(Some(&config.keywords));
             type_path.to_string()
         }
     }
@@ -170,7 +171,7 @@ fn serialize_file_options<S: Serializer>(
         None => return serializer.serialize_none(),
         Some(options) => options,
     };
-    let mut map = HashMap::new();
+    let mut map = BTreeMap::new();
     insert_builtin_file_options(&mut map, options)
         .map_err(|err| S::Error::custom(file_options_error(err)))?;
     debug!("Serializing file options: {:?}", map);
@@ -182,7 +183,7 @@ fn file_options_error(err: impl Error) -> String {
 }
 
 fn insert_builtin_file_options(
-    map: &mut HashMap<String, serde_json::Value>,
+    map: &mut BTreeMap<String, serde_json::Value>,
     options: &FileOptions,
 ) -> Result<(), serde_json::Error> {
     insert_file_option!(deprecated, map, options);
@@ -208,7 +209,7 @@ fn insert_builtin_file_options(
 }
 
 fn try_insert_option<T: Serialize>(
-    map: &mut HashMap<String, serde_json::Value>,
+    map: &mut BTreeMap<String, serde_json::Value>,
     name: impl Into<String>,
     value: &Option<T>,
 ) -> Result<(), serde_json::Error> {
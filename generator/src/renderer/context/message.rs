@@ -5,19 +5,25 @@ use log::debug;
 use prost_types::{DescriptorProto, FieldDescriptorProto, MessageOptions};
 use serde::{Deserialize, Serialize, Serializer};
 
-use crate::renderer::case::Case;
+use crate::renderer::context::field::TypeKind;
 use crate::renderer::context::overlayed::Overlayed;
 use crate::renderer::context::proto_type::ProtoType;
 use crate::renderer::context::FieldContext;
-use crate::renderer::proto::PACKAGE_SEPARATOR;
+use crate::renderer::proto::{self, PACKAGE_SEPARATOR};
 use crate::renderer::RendererConfig;
 use crate::util;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct MessageContext {
-    /// Name of this message.
+    /// Name of this message, with case config applied.
     name: String,
 
+    /// Name as declared in the proto, e.g. `MyMessage`.
+    proto_name: String,
+
+    /// Proto name qualified by the package, e.g. `pkg.sub.MyMessage`.
+    full_proto_name: String,
+
     /// Fields available in this message.
     fields: Vec<FieldContext>,
 
@@ -58,15 +64,30 @@ impl MessageContext {
         message: &DescriptorProto,
         package: Option<&String>,
         config: &RendererConfig,
+    ) -> Result<Self> {
+        Self::nested(message, package, None, config)
+    }
+
+    /// A message declared inside `outer`, the outer message's name relative to the package,
+    /// e.g. `Outer` for `pkg.Outer.Inner`. The package is still used to resolve field types.
+    pub fn nested(
+        message: &DescriptorProto,
+        package: Option<&String>,
+        outer: Option<&str>,
+        config: &RendererConfig,
     ) -> Result<Self> {
         log_new_message(&message.name);
+        let proto_name = util::str_or_error(&message.name, || "Message has no 'name'".to_owned())?;
+        let scoped_name = proto::join_name(outer, proto_name);
         let context = Self {
-            name: name(message, config.case_config.message_name)?,
-            fields: fields(message, package, config)?,
+            name: name(proto_name, config),
+            proto_name: proto_name.to_owned(),
+            full_proto_name: proto::join_name(package.map(String::as_str), &scoped_name),
+            fields: fields(message, package, &scoped_name, config)?,
             options: message.options.clone(),
             overlays: config
                 .overlays
-                .by_target_opt_clone(&full_name(package, &message.name)),
+                .by_target_opt_clone(&full_name(package, &scoped_name)),
         };
         Ok(context)
     }
@@ -74,6 +95,12 @@ impl MessageContext {
     pub fn name(&self) -> &str {
         &self.name
     }
+    pub fn proto_name(&self) -> &str {
+        &self.proto_name
+    }
+    pub fn full_proto_name(&self) -> &str {
+        &self.full_proto_name
+    }
     pub fn fields(&self) -> &Vec<FieldContext> {
         &self.fields
     }
@@ -92,33 +119,36 @@ pub type MapData = HashMap<String, MapEntryData>;
 pub struct MapEntryData {
     pub key: ProtoType,
     pub value: ProtoType,
+    pub value_kind: TypeKind,
 }
 
 fn log_new_message(name: &Option<String>) {
     debug!("Creating message context: {}", util::str_or_unknown(name));
 }
 
-fn full_name(package: Option<&String>, name: &Option<String>) -> Option<String> {
-    Some(format!("{}.{}", package?, name.as_ref()?))
+fn full_name(package: Option<&String>, scoped_name: &str) -> Option<String> {
+    Some(format!("{}.{}", package?, scoped_name))
 }
 
-fn name(message: &DescriptorProto, case: Case) -> Result<String> {
-    let name = util::str_or_error(&message.name, || "Message has no 'name'".to_owned())?;
-    Ok(case.rename(name))
+fn name(proto_name: &str, config: &RendererConfig) -> String {
+    let case = config.case_config.message_name;
+    config.keywords.escape(case.rename(proto_name))
 }
 
 fn fields(
     message: &DescriptorProto,
     package: Option<&String>,
+    scoped_name: &str,
     config: &RendererConfig,
 ) -> Result<Vec<FieldContext>> {
-    let map_data = collect_map_data(message, package)?;
+    let map_data = collect_map_data(message, package, scoped_name)?;
+    let message_name = scoped_name.to_owned();
     let mut fields = Vec::new();
     for field in &message.field {
         fields.push(FieldContext::new(
             field,
             package,
-            message.name.as_ref(),
+            Some(&message_name),
             &map_data,
             config,
         )?);
@@ -126,38 +156,46 @@ fn fields(
     Ok(fields)
 }
 
-fn collect_map_data(message: &DescriptorProto, package: Option<&String>) -> Result<MapData> {
+fn collect_map_data(
+    message: &DescriptorProto,
+    package: Option<&String>,
+    scoped_name: &str,
+) -> Result<MapData> {
     let message_name = util::str_or_error(&message.name, || {
         "collect_map_data: No message name.".to_owned()
     })?;
     let mut map_data = MapData::new();
     for nested in message.nested_type.iter().filter(is_map) {
-        let (key, value) = find_map_key_value(nested, message_name)?;
+        let entry = find_map_key_value(nested, message_name)?;
         let fully_qualified_nested_type =
-            fully_qualify_map_type(&nested_name(&nested, message_name)?, message_name, package);
-        map_data.insert(fully_qualified_nested_type, MapEntryData { key, value });
+            fully_qualify_map_type(&nested_name(&nested, message_name)?, scoped_name, package);
+        map_data.insert(fully_qualified_nested_type, entry);
     }
     Ok(map_data)
 }
 
-fn find_map_key_value(
-    nested: &DescriptorProto,
-    outer_msg_name: &str,
-) -> Result<(ProtoType, ProtoType)> {
+fn find_map_key_value(nested: &DescriptorProto, outer_msg_name: &str) -> Result<MapEntryData> {
     static KEY_FIELD_NAME: &str = "key";
     static VALUE_FIELD_NAME: &str = "value";
-    let key = find_field_type(KEY_FIELD_NAME, &nested.field)
+    let key = find_field(KEY_FIELD_NAME, &nested.field)
         .with_context(|| error_context_failed_collect_map_data(outer_msg_name, &nested.name))?;
-    let value = find_field_type(VALUE_FIELD_NAME, &nested.field)
+    let value = find_field(VALUE_FIELD_NAME, &nested.field)
         .with_context(|| error_context_failed_collect_map_data(outer_msg_name, &nested.name))?;
-    Ok((key, value))
+    Ok(MapEntryData {
+        key: ProtoType::from_field(key)?,
+        value: ProtoType::from_field(value)?,
+        value_kind: TypeKind::from_field(value),
+    })
 }
 
-fn find_field_type(field_name: &str, fields: &[FieldDescriptorProto]) -> Result<ProtoType> {
+fn find_field<'a>(
+    field_name: &str,
+    fields: &'a [FieldDescriptorProto],
+) -> Result<&'a FieldDescriptorProto> {
     for field in fields {
         if let Some(name) = &field.name {
             if name == field_name {
-                return ProtoType::from_field(field);
+                return Ok(field);
             }
         }
     }
@@ -231,6 +269,7 @@ mod tests {
 
     use crate::renderer::case::Case;
     use crate::renderer::context::message::MessageContext;
+    use crate::renderer::keywords::{EscapeStrategy, KeywordPreset};
     use crate::renderer::overlay_config::OverlayConfig;
     use crate::renderer::RendererConfig;
 
@@ -254,6 +293,37 @@ mod tests {
         message.name = Some(msg_name.clone());
         let context = MessageContext::new(&message, None, &config)?;
         assert_eq!(context.name, "MSG_NAME");
+        assert_eq!(context.proto_name, "msgName");
+        Ok(())
+    }
+
+    #[test]
+    fn full_proto_name() -> Result<()> {
+        let mut config = RendererConfig::default();
+        config.case_config.message_name = Case::LowerSnake;
+        let message = DescriptorProto {
+            name: Some("MsgName".to_owned()),
+            ..Default::default()
+        };
+        let package = "pkg.sub".to_owned();
+        let context = MessageContext::new(&message, Some(&package), &config)?;
+        assert_eq!(context.full_proto_name(), "pkg.sub.MsgName");
+        let context = MessageContext::new(&message, None, &config)?;
+        assert_eq!(context.full_proto_name(), "MsgName");
+        Ok(())
+    }
+
+    #[test]
+    fn escapes_keywords() -> Result<()> {
+        let mut config = RendererConfig::default();
+        config.keywords.presets.push(KeywordPreset::Rust);
+        config.keywords.escape = EscapeStrategy::Raw;
+        let mut message = DescriptorProto::default();
+        message.name = Some("self".to_owned());
+        message.field.push(field("type"));
+        let context = MessageContext::new(&message, None, &config)?;
+        assert_eq!(context.name, "Self_");
+        assert_eq!(context.fields[0].name(), "r#type");
         Ok(())
     }
 
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 use anyhow::Result;
@@ -6,7 +6,8 @@ use serde::{Deserialize, Serialize, Serializer};
 
 use crate::{util, DisplayNormalized};
 
-pub type PackageTree = HashMap<String, PackageTreeNode>;
+/// Sorted by key, so templates iterate it the same way on every run.
+pub type PackageTree = BTreeMap<String, PackageTreeNode>;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct MetadataContext {
@@ -177,16 +178,15 @@ impl MetadataContext {
         Ok(())
     }
 
-    pub fn append_package_files(&mut self, package_files: HashMap<String, impl AsRef<Path>>) {
+    pub fn append_package_files(&mut self, package_files: BTreeMap<String, impl AsRef<Path>>) {
         self.package_file_tree = create_package_file_tree(&package_files);
         self.package_files_full = package_files
             .into_iter()
             .map(|(package, path)| PackageFile {
-                package: package.to_owned(),
+                package,
                 file_name: path.as_ref().display_normalized(),
             })
             .collect::<Vec<PackageFile>>();
-        self.package_files_full.sort()
     }
 
     fn is_direct_child(&self, path: &Path) -> bool {
@@ -224,7 +224,7 @@ fn serialize_directory<S: Serializer>(
 
 /// Converts a map of fully-qualified package -> file name to a tree of package components that
 /// include the associated file path.
-fn create_package_file_tree(package_files: &HashMap<String, impl AsRef<Path>>) -> PackageTree {
+fn create_package_file_tree(package_files: &BTreeMap<String, impl AsRef<Path>>) -> PackageTree {
     let mut tree = PackageTree::new();
     for (package, file_name) in package_files {
         let mut package_it = &mut tree;
@@ -351,7 +351,7 @@ mod tests {
     }
 
     mod create_package_file_tree {
-        use std::collections::HashMap;
+        use std::collections::BTreeMap;
         use std::path::PathBuf;
 
         use anyhow::{anyhow, Result};
@@ -385,8 +385,8 @@ mod tests {
             Ok(())
         }
 
-        fn create_package_file_map(values: &[(&str, &str)]) -> HashMap<String, PathBuf> {
-            let mut package_files = HashMap::new();
+        fn create_package_file_map(values: &[(&str, &str)]) -> BTreeMap<String, PathBuf> {
+            let mut package_files = BTreeMap::new();
             for (package, file) in values {
                 package_files.insert(package.to_string(), PathBuf::from(file));
             }
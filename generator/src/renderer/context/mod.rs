@@ -1,3 +1,4 @@
+pub use all::{AllContext, PackageNode};
 pub use field::FieldContext;
 pub use file::FileContext;
 pub use import::ImportContext;
@@ -5,7 +6,9 @@ pub use message::MessageContext;
 pub use metadata::{MetadataContext, PackageFile, PackageTree, PackageTreeNode};
 pub use r#enum::EnumContext;
 pub use r#enum::EnumValueContext;
+pub use registry::TypeRegistry;
 
+mod all;
 mod r#enum;
 mod field;
 mod file;
@@ -13,5 +16,6 @@ mod import;
 mod message;
 mod metadata;
 mod proto_type;
+mod registry;
 
 pub mod overlayed;
@@ -4,7 +4,7 @@ use prost_types::FieldDescriptorProto;
 
 use crate::renderer::proto::TypePath;
 use crate::renderer::RendererConfig;
-use crate::renderer::{primitive, proto};
+use crate::renderer::{primitive, proto, well_known};
 use crate::util;
 
 #[derive(Clone, Debug)]
@@ -37,15 +37,36 @@ impl ProtoType {
     pub fn to_type_path<'a>(&self, config: &'a RendererConfig) -> Result<TypePath<'a>> {
         let result = match self {
             ProtoType::Type(proto_type) => primitive_type_path(*proto_type, config)?,
-            ProtoType::TypeName(type_name) => {
-                complex_type_path(&type_name, config, ChangeCase::Yes)
-            }
+            ProtoType::TypeName(type_name) => match well_known_type_name(type_name, config)? {
+                // Native types are used as is, like primitives.
+                Some(native_type) => proto::TypePath::from_type(native_type),
+                None => complex_type_path(&type_name, config, ChangeCase::Yes),
+            },
             ProtoType::NativeTypeOverride(type_name) => {
                 complex_type_path(&type_name, config, ChangeCase::No)
             }
         };
         Ok(result)
     }
+
+    /// True if this is rendered as a native type rather than a reference to a generated one,
+    /// e.g. a well-known type mapped by `well_known_types` or a `native_type` override.
+    pub fn is_native(&self, config: &RendererConfig) -> Result<bool> {
+        match self {
+            ProtoType::TypeName(type_name) => {
+                Ok(well_known_type_name(type_name, config)?.is_some())
+            }
+            _ => Ok(true),
+        }
+    }
+
+    /// Fully qualified proto name of a message or enum type, without the leading separator.
+    pub fn type_name(&self) -> Option<&str> {
+        match self {
+            ProtoType::TypeName(type_name) => Some(proto::normalize_prefix(type_name)),
+            _ => None,
+        }
+    }
 }
 
 fn native_type_override(field: &FieldDescriptorProto) -> Option<&str> {
@@ -63,6 +84,20 @@ fn primitive_type_path(proto_type_id: i32, config: &RendererConfig) -> Result<Ty
     Ok(proto::TypePath::from_type(primitive_type_name))
 }
 
+/// Native type of a well-known type, unless it's mapped explicitly in `type_config`.
+fn well_known_type_name<'a>(
+    type_name: &str,
+    config: &'a RendererConfig,
+) -> Result<Option<&'a str>> {
+    if config
+        .type_config
+        .contains_key(proto::normalize_prefix(type_name))
+    {
+        return Ok(None);
+    }
+    well_known::native_type(type_name, config)
+}
+
 fn complex_type_path<'a>(
     type_name: &str,
     config: &'a RendererConfig,
@@ -74,6 +109,7 @@ fn complex_type_path<'a>(
         type_path.set_name_case(Some(config.case_config.message_name));
     }
     type_path.set_package_case(Some(config.case_config.package));
+    type_path.set_keywords(Some(&config.keywords));
     type_path.set_separator(&config.package_separator);
     type_path
 }
@@ -89,6 +125,12 @@ pub fn primitive_type_name(proto_type_id: i32, config: &RendererConfig) -> Resul
     }
 }
 
+/// Proto name of a scalar type id, e.g. `int32`. None for messages, enums and groups.
+pub fn scalar_type_name(proto_type_id: i32) -> Option<&'static str> {
+    let kind = i32_to_proto_type(proto_type_id).ok()?;
+    primitive::from_proto_type(kind).ok()
+}
+
 fn complex_type_name<'a>(type_name: &'a str, config: &'a RendererConfig) -> &'a str {
     let type_name = proto::normalize_prefix(type_name);
     let type_name = config
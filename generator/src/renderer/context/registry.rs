@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use prost_types::{DescriptorProto, EnumDescriptorProto, FileDescriptorSet};
+
+use crate::renderer::context::{EnumContext, MessageContext};
+use crate::renderer::proto::{self, normalize_prefix};
+use crate::renderer::RendererConfig;
+use crate::util;
+
+/// Every message and enum in a descriptor set, including imported files and nested types,
+/// by fully qualified proto name, e.g. `pkg.sub.Outer.Inner`.
+///
+/// Names are the original proto names, not the names after case or type config is applied.
+#[derive(Default, Clone)]
+pub struct TypeRegistry {
+    messages: HashMap<String, MessageContext>,
+    enums: HashMap<String, EnumContext>,
+
+    /// Proto file declaring each type.
+    source_files: HashMap<String, String>,
+}
+
+impl TypeRegistry {
+    pub fn new(descriptor_set: &FileDescriptorSet, config: &RendererConfig) -> Result<Self> {
+        let mut registry = Self::default();
+        for file in &descriptor_set.file {
+            let source_file = util::str_or_error(&file.name, || "File has no 'name'".to_owned())?;
+            let scope = Scope {
+                package: file.package.as_ref(),
+                outer: None,
+                source_file,
+                config,
+            };
+            registry
+                .insert(&scope, &file.message_type, &file.enum_type)
+                .with_context(|| format!("Failed to register types in '{}'", source_file))?;
+        }
+        Ok(registry)
+    }
+
+    /// Accepts names with or without the leading separator, e.g. `.pkg.Message` or `pkg.Message`.
+    pub fn message(&self, full_name: &str) -> Option<&MessageContext> {
+        self.messages.get(normalize_prefix(full_name))
+    }
+
+    pub fn enum_type(&self, full_name: &str) -> Option<&EnumContext> {
+        self.enums.get(normalize_prefix(full_name))
+    }
+
+    pub fn source_file(&self, full_name: &str) -> Option<&str> {
+        self.source_files
+            .get(normalize_prefix(full_name))
+            .map(String::as_str)
+    }
+
+    fn insert(
+        &mut self,
+        scope: &Scope,
+        messages: &[DescriptorProto],
+        enums: &[EnumDescriptorProto],
+    ) -> Result<()> {
+        for proto in enums {
+            let full_name = scope.full_name(&proto.name);
+            self.enums.insert(
+                full_name.clone(),
+                EnumContext::new(proto, scope.prefix().as_ref(), scope.config)?,
+            );
+            self.insert_source_file(scope, full_name);
+        }
+        for proto in messages.iter().filter(|m| !is_map_entry(m)) {
+            let full_name = scope.full_name(&proto.name);
+            let outer = scope.outer.as_deref();
+            self.messages.insert(
+                full_name.clone(),
+                MessageContext::nested(proto, scope.package, outer, scope.config)?,
+            );
+            self.insert_source_file(scope, full_name);
+            let nested = Scope {
+                outer: Some(proto::join_name(outer, util::str_or_unknown(&proto.name))),
+                ..*scope
+            };
+            self.insert(&nested, &proto.nested_type, &proto.enum_type)?;
+        }
+        Ok(())
+    }
+
+    fn insert_source_file(&mut self, scope: &Scope, full_name: String) {
+        self.source_files
+            .insert(full_name, scope.source_file.to_owned());
+    }
+}
+
+/// Where types are being registered from, i.e. a file or an outer message.
+struct Scope<'a> {
+    package: Option<&'a String>,
+    /// Name of the outer message relative to the package, e.g. `Outer` or `Outer.Inner`.
+    outer: Option<String>,
+    source_file: &'a str,
+    config: &'a RendererConfig,
+}
+
+impl Scope<'_> {
+    /// Full name of the package or outer message, if any.
+    fn prefix(&self) -> Option<String> {
+        match &self.outer {
+            None => self.package.cloned(),
+            Some(outer) => Some(proto::join_name(self.package.map(String::as_str), outer)),
+        }
+    }
+
+    fn full_name(&self, name: &Option<String>) -> String {
+        proto::join_name(self.prefix().as_deref(), util::str_or_unknown(name))
+    }
+}
+
+fn is_map_entry(message: &DescriptorProto) -> bool {
+    message
+        .options
+        .as_ref()
+        .and_then(|options| options.map_entry)
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::renderer::context::TypeRegistry;
+    use crate::renderer::tests::{fake_field, fake_file, fake_message};
+    use crate::renderer::RendererConfig;
+    use anyhow::Result;
+    use prost_types::{
+        DescriptorProto, EnumDescriptorProto, EnumValueDescriptorProto, FileDescriptorSet,
+        MessageOptions,
+    };
+
+    fn registry() -> Result<TypeRegistry> {
+        let mut outer = fake_message("Outer", vec![fake_field("inner", ".pkg.Outer.Inner")]);
+        outer
+            .nested_type
+            .push(fake_message("Inner", vec![fake_field("id", ".pkg.Outer")]));
+        outer.nested_type.push(DescriptorProto {
+            name: Some("ValuesEntry".to_owned()),
+            field: vec![
+                fake_field("key", ".pkg.Outer"),
+                fake_field("value", ".pkg.Outer"),
+            ],
+            options: Some(MessageOptions {
+                map_entry: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        outer.enum_type.push(EnumDescriptorProto {
+            name: Some("Kind".to_owned()),
+            value: vec![EnumValueDescriptorProto {
+                name: Some("KIND_A".to_owned()),
+                number: Some(0),
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+        let mut file = fake_file("pkg/a.proto", vec![], vec![outer]);
+        file.package = Some("pkg".to_owned());
+        let other = fake_file("b.proto", vec![], vec![fake_message("NoPackage", vec![])]);
+        TypeRegistry::new(
+            &FileDescriptorSet {
+                file: vec![file, other],
+            },
+            &RendererConfig::default(),
+        )
+    }
+
+    #[test]
+    fn messages() -> Result<()> {
+        let registry = registry()?;
+        assert_eq!(registry.message("pkg.Outer").unwrap().name(), "Outer");
+        assert_eq!(
+            registry.message(".pkg.Outer.Inner").unwrap().name(),
+            "Inner"
+        );
+        assert_eq!(registry.message("NoPackage").unwrap().name(), "NoPackage");
+        assert!(registry.message("pkg.Outer.ValuesEntry").is_none());
+        assert!(registry.message("pkg.Outer.Kind").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn nested_types_are_scoped_by_outer_message() -> Result<()> {
+        let registry = registry()?;
+        let inner = registry.message("pkg.Outer.Inner").unwrap();
+        assert_eq!(inner.full_proto_name(), "pkg.Outer.Inner");
+        assert_eq!(inner.fields()[0].full_proto_name(), "pkg.Outer.Inner.id");
+        let kind = registry.enum_type("pkg.Outer.Kind").unwrap();
+        assert_eq!(kind.full_proto_name(), "pkg.Outer.Kind");
+        assert_eq!(kind.values()[0].full_proto_name(), "pkg.Outer.Kind.KIND_A");
+        Ok(())
+    }
+
+    #[test]
+    fn enums() -> Result<()> {
+        let registry = registry()?;
+        assert_eq!(registry.enum_type("pkg.Outer.Kind").unwrap().name(), "Kind");
+        assert!(registry.enum_type("pkg.Outer").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn source_files() -> Result<()> {
+        let registry = registry()?;
+        assert_eq!(registry.source_file("pkg.Outer.Inner"), Some("pkg/a.proto"));
+        assert_eq!(registry.source_file(".NoPackage"), Some("b.proto"));
+        assert_eq!(registry.source_file("Missing"), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+use anyhow::{Context, Result};
+use log::info;
+
+use crate::dump_config::DumpConfig;
+use crate::in_out_generator::InOutGenerator;
+use crate::render::Render;
+use crate::renderer::dump::renderer::DumpRenderer;
+use crate::{util, Config, DisplayNormalized, InOutConfig};
+use std::path::PathBuf;
+
+mod renderer;
+
+pub(crate) use renderer::{all_value, enum_value, file_value, message_value, type_value};
+
+pub const TEMPLATES_DIR_NAME: &str = "templates";
+pub const JINJA_DIR_NAME: &str = "jinja";
+pub const WASM_DIR_NAME: &str = "wasm";
+pub const PROCESS_DIR_NAME: &str = "process";
+pub const SCRIPTS_DIR_NAME: &str = "scripts";
+
+/// Writes the contexts that would be passed to each script and template set as json or yaml.
+/// When there are no script or template sets, the contexts are built with the default config.
+pub fn generate(config: &Config) -> Result<()> {
+    let dump_config = match &config.dump_context {
+        None => return Ok(()),
+        Some(dump_config) => dump_config,
+    };
+    let mut generator = Generator {
+        config,
+        dump_config,
+        renderer: DumpRenderer::new(dump_config.format),
+    };
+    if !generator.in_out_configs().is_empty() {
+        return generator.generate();
+    }
+    let descriptor_set = util::load_descriptor_set(config)?;
+    info!(
+        "Dumping contexts with default config to output directory '{}'",
+        dump_config.output.display_normalized()
+    );
+    util::create_dir_or_error(&dump_config.output).context("Context dump out dir")?;
+    util::check_dir_is_empty(&dump_config.output).context("Context dump out dir")?;
+    let files = generator
+        .renderer
+        .render(&descriptor_set, &dump_config.output)?;
+    config.record_written_files(&dump_config.output, files);
+    Ok(())
+}
+
+struct Generator<'a> {
+    config: &'a Config,
+    dump_config: &'a DumpConfig,
+    renderer: DumpRenderer,
+}
+impl<'a> InOutGenerator<DumpRenderer> for Generator<'a> {
+    fn name(&self) -> &str {
+        "Context Dump"
+    }
+
+    fn renderer(&mut self) -> &mut DumpRenderer {
+        &mut self.renderer
+    }
+
+    fn app_config(&self) -> &Config {
+        self.config
+    }
+
+    /// Each set is written to its own directory inside the dump output, using the set's config
+    /// and overlays as input.
+    fn in_out_configs(&self) -> Vec<InOutConfig> {
+        let output = &self.dump_config.output;
+        let sets = [
+            (TEMPLATES_DIR_NAME, in_out_sets(&self.config.templates)),
+            (JINJA_DIR_NAME, in_out_sets(&self.config.jinja)),
+            (WASM_DIR_NAME, in_out_sets(&self.config.wasm)),
+            (PROCESS_DIR_NAME, in_out_sets(&self.config.process)),
+            (
+                SCRIPTS_DIR_NAME,
+                self.config
+                    .scripts
+                    .iter()
+                    .map(|x| (x.name.clone(), &x.input, &x.overlays))
+                    .collect(),
+            ),
+        ];
+        let mut configs = Vec::new();
+        for (dir_name, sets) in sets {
+            let names = unique_names(sets.iter().map(|(name, _, _)| name.as_str()));
+            for ((_, input, overlays), name) in sets.into_iter().zip(names) {
+                configs.push(InOutConfig {
+                    input: input.clone(),
+                    output: output.join(dir_name).join(name),
+                    overlays: overlays.clone(),
+                });
+            }
+        }
+        configs
+    }
+}
+
+/// Sets are named after their input directory.
+fn in_out_sets(configs: &[InOutConfig]) -> Vec<(String, &PathBuf, &Vec<PathBuf>)> {
+    configs
+        .iter()
+        .map(|x| {
+            let name = x.input.file_name().unwrap_or_default();
+            (name.to_string_lossy().to_string(), &x.input, &x.overlays)
+        })
+        .collect()
+}
+
+/// Sets with the same name, e.g. `a/templates` and `b/templates`, would overwrite each other's
+/// dumps, so later ones get a numbered suffix: `templates`, `templates-2`, ...
+fn unique_names<'a>(names: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut unique: Vec<String> = Vec::new();
+    for name in names {
+        let mut candidate = name.to_owned();
+        let mut i = 1;
+        while unique.contains(&candidate) {
+            i += 1;
+            candidate = format!("{}-{}", name, i);
+        }
+        unique.push(candidate);
+    }
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::dump_config::{DumpConfig, DumpFormat};
+    use crate::in_out_generator::InOutGenerator;
+    use crate::renderer::dump::renderer::DumpRenderer;
+    use crate::renderer::dump::{unique_names, Generator};
+    use crate::{Config, InOutConfig};
+
+    #[test]
+    fn sets_with_same_input_name_get_separate_dirs() {
+        let mut config = Config::default();
+        config.templates = ["a/templates", "b/templates"]
+            .iter()
+            .map(|input| InOutConfig {
+                input: PathBuf::from(input),
+                output: PathBuf::from("out"),
+                overlays: vec![],
+            })
+            .collect();
+        let dump_config = DumpConfig {
+            format: DumpFormat::Json,
+            output: PathBuf::from("dump"),
+        };
+        let generator = Generator {
+            config: &config,
+            dump_config: &dump_config,
+            renderer: DumpRenderer::new(DumpFormat::Json),
+        };
+        let outputs = generator
+            .in_out_configs()
+            .into_iter()
+            .map(|x| x.output)
+            .collect::<Vec<PathBuf>>();
+        assert_eq!(
+            outputs,
+            vec![
+                PathBuf::from("dump/templates/templates"),
+                PathBuf::from("dump/templates/templates-2"),
+            ]
+        );
+    }
+
+    #[test]
+    fn unique_names_are_unchanged() {
+        assert_eq!(unique_names(["a", "b"].iter().copied()), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn duplicate_names_are_numbered() {
+        assert_eq!(
+            unique_names(
+                ["templates", "templates", "other", "templates"]
+                    .iter()
+                    .copied()
+            ),
+            vec!["templates", "templates-2", "other", "templates-3"]
+        );
+    }
+
+    #[test]
+    fn numbered_names_skip_existing() {
+        assert_eq!(
+            unique_names(["a-2", "a", "a"].iter().copied()),
+            vec!["a-2", "a", "a-3"]
+        );
+    }
+}
@@ -0,0 +1,401 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::dump_config::DumpFormat;
+use crate::renderer::context::overlayed::Overlayed;
+use crate::renderer::context::{
+    AllContext, EnumContext, EnumValueContext, FieldContext, FileContext, MessageContext,
+    MetadataContext,
+};
+use crate::renderer::{find_existing_config_path, Renderer, RendererConfig};
+
+const OVERLAYS_KEY: &str = "overlays";
+const OPTIONS_KEY: &str = "options";
+
+/// Collects the named builtin options which have a value into a json map.
+macro_rules! builtin_options {
+    ($options: ident, $($name: ident),+) => {{
+        let mut map = Map::new();
+        if let Some(options) = $options {
+            $(
+                if let Some(value) = &options.$name {
+                    map.insert(stringify!($name).to_owned(), serde_json::to_value(value)?);
+                }
+            )+
+        }
+        map
+    }};
+}
+
+/// Renders contexts as data rather than through scripts or templates.
+///
+/// Unlike the serialized contexts the template renderer sees, the dump also includes overlays
+/// and the builtin options available to scripts, so it reflects everything either renderer
+/// has access to.
+pub struct DumpRenderer {
+    format: DumpFormat,
+    config: RendererConfig,
+}
+
+impl DumpRenderer {
+    pub fn new(format: DumpFormat) -> Self {
+        Self {
+            format,
+            config: dump_config(RendererConfig::default(), format),
+        }
+    }
+
+    fn write_value<W: Write>(&self, value: &impl Serialize, writer: &mut W) -> Result<()> {
+        match self.format {
+            DumpFormat::Json => {
+                serde_json::to_writer_pretty(&mut *writer, value)?;
+                writer.write_all(b"\n")?;
+            }
+            // serde_yaml writes the `---` document start, so multiple contexts collapsed into
+            // one file (one_file_per_package) is still a valid yaml stream.
+            DumpFormat::Yaml => serde_yaml::to_writer(&mut *writer, value)?,
+        }
+        Ok(())
+    }
+}
+
+impl Renderer for DumpRenderer {
+    fn load(&mut self, input_root: &Path, overlays: &[PathBuf]) -> Result<()> {
+        let config = Self::load_config(&find_existing_config_path(input_root)?, overlays)?;
+        self.config = dump_config(config, self.format);
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.config = dump_config(RendererConfig::default(), self.format);
+    }
+
+    fn config(&self) -> &RendererConfig {
+        &self.config
+    }
+
+    fn has_metadata(&self) -> bool {
+        true
+    }
+
+    fn render_metadata<W: Write>(&self, context: MetadataContext, writer: &mut W) -> Result<()> {
+        self.write_value(&context, writer)
+    }
+
+    fn render_file<W: Write>(&self, context: FileContext, writer: &mut W) -> Result<()> {
+        self.write_value(&file_value(&context)?, writer)
+    }
+}
+
+/// The loaded config decides what goes _into_ the contexts, but the output files themselves are
+/// always plain data.
+fn dump_config(mut config: RendererConfig, format: DumpFormat) -> RendererConfig {
+    config.file_extension = format.as_config();
+    config.generated_header = Some(vec![]);
+    config
+}
+
+/// Serialized `context` with the overlays and builtin options of it and its children, which the
+/// contexts leave out of their own serialization. Also used as the data of jinja templates.
+pub(crate) fn file_value(context: &FileContext) -> Result<Value> {
+    let mut value = serde_json::to_value(context)?;
+    insert_overlays(&mut value, context)?;
+    for (message, value) in context
+        .messages()
+        .iter()
+        .zip(children(&mut value, "messages")?)
+    {
+        *value = message_value(message)?;
+    }
+    for (e, value) in context.enums().iter().zip(children(&mut value, "enums")?) {
+        *value = enum_value(e)?;
+    }
+    Ok(value)
+}
+
+/// Data of per-type output, i.e. a message or enum `value` at the root with its file as `file`.
+pub(crate) fn type_value(mut value: Value, file: &FileContext) -> Result<Value> {
+    object(&mut value)?.insert("file".to_owned(), file_value(file)?);
+    Ok(value)
+}
+
+pub(crate) fn message_value(context: &MessageContext) -> Result<Value> {
+    let mut value = serde_json::to_value(context)?;
+    insert_overlays(&mut value, context)?;
+    let options = context.options().as_ref();
+    insert_options(
+        &mut value,
+        builtin_options!(
+            options,
+            message_set_wire_format,
+            no_standard_descriptor_accessor,
+            deprecated,
+            map_entry
+        ),
+    )?;
+    for (field, value) in context.fields().iter().zip(children(&mut value, "fields")?) {
+        *value = field_value(field)?;
+    }
+    Ok(value)
+}
+
+fn field_value(context: &FieldContext) -> Result<Value> {
+    let mut value = serde_json::to_value(context)?;
+    insert_overlays(&mut value, context)?;
+    let options = context.options();
+    insert_options(
+        &mut value,
+        builtin_options!(options, ctype, jstype, packed, lazy, deprecated, weak),
+    )?;
+    Ok(value)
+}
+
+pub(crate) fn enum_value(context: &EnumContext) -> Result<Value> {
+    let mut value = serde_json::to_value(context)?;
+    insert_overlays(&mut value, context)?;
+    let options = context.options().as_ref();
+    insert_options(
+        &mut value,
+        builtin_options!(options, allow_alias, deprecated),
+    )?;
+    for (enum_value, value) in context.values().iter().zip(children(&mut value, "values")?) {
+        *value = enum_value_value(enum_value)?;
+    }
+    Ok(value)
+}
+
+pub(crate) fn all_value(context: &AllContext) -> Result<Value> {
+    let mut value = serde_json::to_value(context)?;
+    for (file, value) in context.files().iter().zip(children(&mut value, "files")?) {
+        *value = file_value(file)?;
+    }
+    Ok(value)
+}
+
+fn enum_value_value(context: &EnumValueContext) -> Result<Value> {
+    let mut value = serde_json::to_value(context)?;
+    insert_overlays(&mut value, context)?;
+    let options = context.options().as_ref();
+    insert_options(&mut value, builtin_options!(options, deprecated))?;
+    Ok(value)
+}
+
+fn insert_overlays(value: &mut Value, context: &impl Overlayed) -> Result<()> {
+    let overlays = serde_json::to_value(context.overlays())?;
+    object(value)?.insert(OVERLAYS_KEY.to_owned(), overlays);
+    Ok(())
+}
+
+fn insert_options(value: &mut Value, options: Map<String, Value>) -> Result<()> {
+    object(value)?.insert(OPTIONS_KEY.to_owned(), Value::Object(options));
+    Ok(())
+}
+
+fn object(value: &mut Value) -> Result<&mut Map<String, Value>> {
+    value
+        .as_object_mut()
+        .ok_or(anyhow!("Context did not serialize to an object."))
+}
+
+fn children<'a>(value: &'a mut Value, key: &str) -> Result<&'a mut Vec<Value>> {
+    object(value)?
+        .get_mut(key)
+        .and_then(Value::as_array_mut)
+        .ok_or(anyhow!("Context is missing array '{}'.", key))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use anyhow::Result;
+    use prost_types::{
+        DescriptorProto, EnumDescriptorProto, EnumValueDescriptorProto, EnumValueOptions,
+        FieldDescriptorProto, FileDescriptorProto, FileDescriptorSet, FileOptions,
+    };
+    use serde_json::Value;
+    use tempfile::tempdir;
+    use walkdir::WalkDir;
+
+    use crate::dump_config::DumpFormat;
+    use crate::render::Render;
+    use crate::renderer::context::{FileContext, MetadataContext};
+    use crate::renderer::dump::renderer::{file_value, DumpRenderer};
+    use crate::renderer::overlay_config::OverlayConfig;
+    use crate::renderer::{Renderer, RendererConfig};
+
+    #[test]
+    fn includes_overlays() -> Result<()> {
+        let value = file_value(&FileContext::new(&fake_file(), &overlay_config())?)?;
+        assert_eq!(value["overlays"]["key"], "file");
+        assert_eq!(value["messages"][0]["overlays"]["key"], "message");
+        assert_eq!(
+            value["messages"][0]["fields"][0]["overlays"]["key"],
+            "field"
+        );
+        assert_eq!(value["enums"][0]["overlays"]["key"], "enum");
+        assert_eq!(
+            value["enums"][0]["values"][0]["overlays"]["key"],
+            "enum_value"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn includes_options() -> Result<()> {
+        let value = file_value(&FileContext::new(&fake_file(), &RendererConfig::default())?)?;
+        assert_eq!(
+            value["enums"][0]["values"][0]["options"]["deprecated"],
+            Value::Bool(true)
+        );
+        assert!(value["messages"][0]["options"].is_object());
+        Ok(())
+    }
+
+    #[test]
+    fn json_file() -> Result<()> {
+        let renderer = DumpRenderer::new(DumpFormat::Json);
+        let context = FileContext::new(&fake_file(), renderer.config())?;
+        let mut output = Vec::new();
+        renderer.render_file(context, &mut output)?;
+        let value: Value = serde_json::from_slice(&output)?;
+        assert_eq!(value["source_file"], "test.proto");
+        Ok(())
+    }
+
+    #[test]
+    fn yaml_metadata() -> Result<()> {
+        let renderer = DumpRenderer::new(DumpFormat::Yaml);
+        let mut context = MetadataContext::new();
+        context.append_files(&["test.proto"])?;
+        let mut output = Vec::new();
+        renderer.render_metadata(context, &mut output)?;
+        let value: serde_yaml::Value = serde_yaml::from_slice(&output)?;
+        assert_eq!(value["file_names"][0], serde_yaml::Value::from("test"));
+        Ok(())
+    }
+
+    #[test]
+    fn output_ext_matches_format() {
+        assert_eq!(DumpRenderer::new(DumpFormat::Json).output_ext(), "json");
+        assert_eq!(DumpRenderer::new(DumpFormat::Yaml).output_ext(), "yaml");
+    }
+
+    #[test]
+    fn renders_identically_twice() -> Result<()> {
+        assert_renders_identically_twice(DumpRenderer::new(DumpFormat::Json))
+    }
+
+    #[test]
+    fn renders_identically_twice_one_file_per_package() -> Result<()> {
+        let mut renderer = DumpRenderer::new(DumpFormat::Yaml);
+        renderer.config.one_file_per_package = true;
+        assert_renders_identically_twice(renderer)
+    }
+
+    fn assert_renders_identically_twice(renderer: DumpRenderer) -> Result<()> {
+        let descriptor_set = FileDescriptorSet {
+            file: [
+                ("root.proto", "root"),
+                ("a/one.proto", "root.a"),
+                ("a/two.proto", "root.a.inner"),
+                ("b/c/three.proto", "other.b"),
+                ("b/four.proto", "other"),
+                ("d/five.proto", "root.d"),
+                ("e/six.proto", "third.e.f"),
+            ]
+            .iter()
+            .map(|(name, package)| FileDescriptorProto {
+                name: Some(name.to_string()),
+                package: Some(package.to_string()),
+                options: Some(FileOptions {
+                    java_package: Some("java".to_owned()),
+                    go_package: Some("go".to_owned()),
+                    csharp_namespace: Some("csharp".to_owned()),
+                    swift_prefix: Some("swift".to_owned()),
+                    objc_class_prefix: Some("objc".to_owned()),
+                    deprecated: Some(false),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .collect(),
+        };
+        let first = tempdir()?;
+        let second = tempdir()?;
+        renderer.render(&descriptor_set, first.path())?;
+        renderer.render(&descriptor_set, second.path())?;
+        let first = read_all_files(first.path())?;
+        assert!(!first.is_empty());
+        assert_eq!(first, read_all_files(second.path())?);
+        Ok(())
+    }
+
+    fn read_all_files(root: &Path) -> Result<Vec<(PathBuf, Vec<u8>)>> {
+        let mut files = Vec::new();
+        for entry in WalkDir::new(root).sort_by_file_name() {
+            let entry = entry?;
+            if entry.file_type().is_file() {
+                let path = entry.path().strip_prefix(root)?.to_path_buf();
+                files.push((path, fs::read(entry.path())?));
+            }
+        }
+        Ok(files)
+    }
+
+    fn overlay_config() -> RendererConfig {
+        let by_target = [
+            ("test.proto", "file"),
+            ("pkg.Msg", "message"),
+            ("pkg.Msg.field", "field"),
+            ("pkg.Enum", "enum"),
+            ("pkg.Enum.VALUE", "enum_value"),
+        ]
+        .iter()
+        .map(|(target, value)| {
+            let mut overlays = HashMap::new();
+            overlays.insert("key".to_owned(), serde_yaml::Value::from(value.to_string()));
+            (target.to_string(), overlays)
+        })
+        .collect();
+        RendererConfig {
+            overlays: OverlayConfig::new(HashMap::new(), by_target),
+            ..Default::default()
+        }
+    }
+
+    fn fake_file() -> FileDescriptorProto {
+        FileDescriptorProto {
+            name: Some("test.proto".to_owned()),
+            package: Some("pkg".to_owned()),
+            message_type: vec![DescriptorProto {
+                name: Some("Msg".to_owned()),
+                field: vec![FieldDescriptorProto {
+                    name: Some("field".to_owned()),
+                    r#type: Some(5),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            enum_type: vec![EnumDescriptorProto {
+                name: Some("Enum".to_owned()),
+                value: vec![EnumValueDescriptorProto {
+                    name: Some("VALUE".to_owned()),
+                    number: Some(0),
+                    options: Some(EnumValueOptions {
+                        deprecated: Some(true),
+                        ..Default::default()
+                    }),
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+}
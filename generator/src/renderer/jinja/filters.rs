@@ -0,0 +1,54 @@
+use minijinja::{Environment, Error, ErrorKind, Value};
+
+use crate::renderer::case::{Case, CASES};
+use crate::renderer::overlay_config::OverlayConfig;
+use crate::renderer::{text, RendererConfig};
+
+/// Registers all protox filters and functions. MiniJinja's built-ins, e.g. `join`, `replace`,
+/// `indent`, `first` and `default`, are always available too.
+pub fn register(env: &mut Environment, config: &RendererConfig) {
+    env.add_filter("case", change_case);
+    for (name, case) in CASES {
+        let case = *case;
+        env.add_filter(*name, move |value: &str| case.rename(value));
+    }
+    env.add_filter("wrap_comment", wrap_comment);
+    env.add_filter("comment", comment);
+    let overlays = config.overlays.clone();
+    env.add_function("lookup_overlay", move |target: &str, key: &str| {
+        lookup_overlay(&overlays, target, key)
+    });
+}
+
+/// `{{ value | case("UpperCamel") }}` changes the case of `value`, where the case is any name
+/// accepted by case config.
+fn change_case(value: &str, case: &str) -> Result<String, Error> {
+    let case = case
+        .parse::<Case>()
+        .map_err(|err| Error::new(ErrorKind::InvalidOperation, err.to_string()))?;
+    Ok(case.rename(value))
+}
+
+/// `{{ text | wrap_comment(100, "/// ") }}` word wraps `text` as comment lines. The prefix
+/// defaults to `// `.
+fn wrap_comment(text: &str, width: usize, prefix: Option<&str>) -> String {
+    text::wrap_comment(text, width, prefix.unwrap_or("// "))
+}
+
+/// `{{ text | comment("/**", 100) }}` formats `text` as a comment in a style, e.g. `//`, `#` or
+/// `/**`, optionally wrapped to a width.
+fn comment(text: &str, style: &str, width: Option<usize>) -> String {
+    text::comment(text, style, width.unwrap_or(usize::MAX))
+}
+
+/// `{{ lookup_overlay(full_proto_name, "key") }}` is the overlay value of `key` for a target, or
+/// none if there is none.
+fn lookup_overlay(overlays: &OverlayConfig, target: &str, key: &str) -> Value {
+    match overlays
+        .by_target(target)
+        .and_then(|values| values.get(key))
+    {
+        None => Value::from(()),
+        Some(value) => Value::from_serialize(value),
+    }
+}
@@ -0,0 +1,45 @@
+use anyhow::Result;
+
+use crate::in_out_generator::InOutGenerator;
+use crate::renderer::jinja::renderer::JinjaRenderer;
+use crate::{Config, InOutConfig};
+
+mod filters;
+mod renderer;
+
+pub const TEMPLATE_EXT: &str = "jinja";
+pub const METADATA_TEMPLATE_NAME: &str = "metadata.jinja";
+pub const FILE_TEMPLATE_NAME: &str = "file.jinja";
+pub const MESSAGE_TEMPLATE_NAME: &str = "message.jinja";
+pub const ENUM_TEMPLATE_NAME: &str = "enum.jinja";
+pub const ALL_TEMPLATE_NAME: &str = "all.jinja";
+
+pub fn generate(config: &Config) -> Result<()> {
+    Generator {
+        config,
+        renderer: JinjaRenderer::new(),
+    }
+    .generate()
+}
+
+struct Generator<'a> {
+    config: &'a Config,
+    renderer: JinjaRenderer,
+}
+impl<'a> InOutGenerator<JinjaRenderer> for Generator<'a> {
+    fn name(&self) -> &str {
+        "Jinja Templates"
+    }
+
+    fn renderer(&mut self) -> &mut JinjaRenderer {
+        &mut self.renderer
+    }
+
+    fn app_config(&self) -> &Config {
+        self.config
+    }
+
+    fn in_out_configs(&self) -> Vec<InOutConfig> {
+        self.app_config().jinja.clone()
+    }
+}
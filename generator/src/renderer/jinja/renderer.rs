@@ -0,0 +1,649 @@
+use crate::diagnostic::Diagnostic;
+use crate::renderer::context::{
+    AllContext, EnumContext, FileContext, MessageContext, MetadataContext,
+};
+use crate::renderer::jinja::{
+    filters, ALL_TEMPLATE_NAME, ENUM_TEMPLATE_NAME, FILE_TEMPLATE_NAME, MESSAGE_TEMPLATE_NAME,
+    METADATA_TEMPLATE_NAME, TEMPLATE_EXT,
+};
+use crate::renderer::{dump, find_existing_config_path, Renderer, RendererConfig};
+use crate::DisplayNormalized;
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use minijinja::{AutoEscape, Environment, Error};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::error::Error as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Renders final output files with Jinja templates, using the same data and config as the
+/// handlebars [`TemplateRenderer`](crate::renderer::template), but with the control flow, macros
+/// and filters of Jinja.
+pub struct JinjaRenderer {
+    env: Environment<'static>,
+    config: RendererConfig,
+    /// Files that templates were loaded from, by template name.
+    template_paths: HashMap<String, PathBuf>,
+}
+
+impl JinjaRenderer {
+    pub fn new() -> Self {
+        Self::with_config(Default::default())
+    }
+
+    pub fn with_config(config: RendererConfig) -> Self {
+        Self {
+            env: environment(&config),
+            config,
+            template_paths: HashMap::new(),
+        }
+    }
+
+    #[allow(dead_code)]
+    fn load_template_string(&mut self, name: &str, template: impl Into<String>) -> Result<()> {
+        self.env
+            .add_template_owned(name.to_owned(), template.into())
+            .map_err(|err| self.diagnostic(&err))
+            .with_context(|| format!("Failed to load '{}' template from string", name))?;
+        Ok(())
+    }
+
+    /// Loads all templates under `root`, named by their path relative to `root`, e.g.
+    /// `messages/struct.jinja`, which is also how they're included or imported.
+    pub fn load_templates(&mut self, root: &Path) -> Result<()> {
+        for entry in WalkDir::new(root)
+            .follow_links(false)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(|r| r.ok())
+            .filter(|e| e.file_type().is_file())
+        {
+            let file = entry.path();
+            match file.extension() {
+                Some(ext) if ext == TEMPLATE_EXT => {}
+                _ => continue,
+            };
+
+            let template_name = match template_name(root, file) {
+                None => continue,
+                Some(name) => name,
+            };
+
+            self.load_template_file(&template_name, file)?;
+        }
+        Ok(())
+    }
+
+    /// Loads the shared templates in `partials_path`, relative to `root`.
+    fn load_partials(&mut self, root: &Path) -> Result<()> {
+        for dir in self.config.partials_path.clone() {
+            let dir = root.join(dir);
+            if !dir.is_dir() {
+                return Err(anyhow!(
+                    "Partials directory in 'partials_path' does not exist: {}",
+                    dir.display_normalized()
+                ));
+            }
+            self.load_templates(&dir)?;
+        }
+        Ok(())
+    }
+
+    fn load_template_file(&mut self, name: &str, path: &Path) -> Result<()> {
+        self.template_paths
+            .insert(name.to_owned(), path.to_path_buf());
+        let source = fs::read_to_string(path).with_context(|| {
+            format!(
+                "Failed to read '{}' template at path: {}",
+                name,
+                path.display_normalized()
+            )
+        })?;
+        self.env
+            .add_template_owned(name.to_owned(), source)
+            .map_err(|err| self.diagnostic(&err))
+            .with_context(|| {
+                format!(
+                    "Failed to load '{}' template at path: {}",
+                    name,
+                    path.display_normalized()
+                )
+            })?;
+        Ok(())
+    }
+
+    fn has_template(&self, name: &str) -> bool {
+        self.env.get_template(name).is_ok()
+    }
+
+    fn render_to_string<S: Serialize>(&self, template: &str, data: &S) -> Result<String> {
+        let rendered = self
+            .env
+            .get_template(template)
+            .and_then(|template| template.render(data))
+            .map_err(|err| self.diagnostic(&err))
+            .with_context(|| render_error_context(template, data))?;
+        Ok(rendered)
+    }
+
+    fn render_to_write<S: Serialize, W: io::Write>(
+        &self,
+        template: &str,
+        data: &S,
+        writer: &mut W,
+    ) -> Result<()> {
+        let rendered = self.render_to_string(template, data)?;
+        writer.write_all(rendered.as_bytes())?;
+        Ok(())
+    }
+
+    /// Points at the template's file if it was loaded from one, otherwise at its name.
+    fn diagnostic(&self, err: &Error) -> Diagnostic {
+        // Errors in included templates are wrapped by an error pointing at the include.
+        let mut err = err;
+        while let Some(source) = err.source().and_then(|x| x.downcast_ref::<Error>()) {
+            err = source;
+        }
+        let message = match err.detail() {
+            None => err.kind().to_string(),
+            Some(detail) => format!("{}: {}", err.kind(), detail),
+        };
+        let mut diagnostic = Diagnostic::error(message).with_position(err.line(), None);
+        if let Some(name) = err.name() {
+            diagnostic.path = Some(
+                self.template_paths
+                    .get(name)
+                    .cloned()
+                    .unwrap_or_else(|| PathBuf::from(name)),
+            );
+        }
+        diagnostic
+    }
+}
+
+impl Renderer for JinjaRenderer {
+    /// Loads config and templates from the same root path with the following names:
+    /// ```txt
+    ///     root/config.json
+    ///     root/file.jinja
+    ///     root/metadata.jinja (optional)
+    /// ```
+    ///
+    /// Any other `*.jinja` files will also be loaded as templates based on their path, and can
+    /// be used in other templates with e.g. `{% include "messages/struct.jinja" %}`.
+    fn load(&mut self, root: &Path, overlays: &[PathBuf]) -> Result<()> {
+        self.config = Self::load_config(&find_existing_config_path(root)?, overlays)?;
+        // Functions like `lookup_overlay` depend on the config.
+        self.env = environment(&self.config);
+        // Templates in the template directory take precedence over shared partials.
+        self.load_partials(root)?;
+        self.load_templates(root)?;
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.env.clear_templates();
+        self.template_paths.clear();
+    }
+
+    fn config(&self) -> &RendererConfig {
+        &self.config
+    }
+
+    fn has_metadata(&self) -> bool {
+        self.has_template(METADATA_TEMPLATE_NAME)
+    }
+
+    fn render_metadata<W: io::Write>(
+        &self,
+        context: MetadataContext,
+        writer: &mut W,
+    ) -> Result<()> {
+        self.render_to_write(METADATA_TEMPLATE_NAME, &context, writer)
+    }
+
+    fn render_file<W: io::Write>(&self, context: FileContext, writer: &mut W) -> Result<()> {
+        self.render_to_write(FILE_TEMPLATE_NAME, &dump::file_value(&context)?, writer)
+    }
+
+    fn has_file(&self) -> bool {
+        self.has_template(FILE_TEMPLATE_NAME)
+    }
+
+    fn has_message(&self) -> bool {
+        self.has_template(MESSAGE_TEMPLATE_NAME)
+    }
+
+    fn has_enum(&self) -> bool {
+        self.has_template(ENUM_TEMPLATE_NAME)
+    }
+
+    fn has_all(&self) -> bool {
+        self.has_template(ALL_TEMPLATE_NAME)
+    }
+
+    fn render_message<W: io::Write>(
+        &self,
+        context: &MessageContext,
+        file: &FileContext,
+        writer: &mut W,
+    ) -> Result<()> {
+        let data = dump::type_value(dump::message_value(context)?, file)?;
+        self.render_to_write(MESSAGE_TEMPLATE_NAME, &data, writer)
+    }
+
+    fn render_enum<W: io::Write>(
+        &self,
+        context: &EnumContext,
+        file: &FileContext,
+        writer: &mut W,
+    ) -> Result<()> {
+        let data = dump::type_value(dump::enum_value(context)?, file)?;
+        self.render_to_write(ENUM_TEMPLATE_NAME, &data, writer)
+    }
+
+    fn render_all<W: io::Write>(&self, context: AllContext, writer: &mut W) -> Result<()> {
+        self.render_to_write(ALL_TEMPLATE_NAME, &dump::all_value(&context)?, writer)
+    }
+}
+
+/// Output is source code rather than html, so nothing is escaped. Whitespace matters in most
+/// generated code, so block tags don't leave their line behind and the trailing newline of each
+/// template is kept.
+fn environment(config: &RendererConfig) -> Environment<'static> {
+    let mut env = Environment::new();
+    env.set_auto_escape_callback(|_| AutoEscape::None);
+    env.set_trim_blocks(true);
+    env.set_lstrip_blocks(true);
+    env.set_keep_trailing_newline(true);
+    filters::register(&mut env, config);
+    env
+}
+
+/// Path of `file` relative to `root`, always separated by `/`.
+fn template_name(root: &Path, file: &Path) -> Option<String> {
+    let relative = file.strip_prefix(root).ok()?;
+    let components = relative
+        .components()
+        .map(|component| component.as_os_str().to_str())
+        .collect::<Option<Vec<&str>>>()?;
+    Some(components.join("/"))
+}
+
+/// minijinja already reports the failing line and expression, which is what's needed to fix a
+/// template. The data is logged at debug level for when that isn't enough.
+fn render_error_context<S: Serialize>(name: &str, data: &S) -> String {
+    debug!(
+        "Failed to render template '{}' for data: {}",
+        name,
+        serde_json::to_string(data).unwrap_or("(failed to serialize)".to_owned()),
+    );
+    format!("Failed to render template '{}'", name)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::renderer::context::FileContext;
+    use crate::renderer::jinja::renderer::JinjaRenderer;
+    use crate::renderer::jinja::FILE_TEMPLATE_NAME;
+    use crate::renderer::tests::{fake_field, fake_file, fake_message};
+    use crate::renderer::{primitive, Renderer, RendererConfig};
+    use anyhow::Result;
+
+    #[test]
+    fn file_template() -> Result<()> {
+        let mut renderer = JinjaRenderer::new();
+        renderer.load_template_string(
+            FILE_TEMPLATE_NAME,
+            r#"{{ source_file }}
+{% for message in messages %}
+{% include "message_body.jinja" %}
+{% endfor %}"#,
+        )?;
+        renderer.load_template_string(
+            "message_body.jinja",
+            "{{ message.name }}:{% for field in message.fields %} {{ field.field_name }}{% endfor %}\n\n",
+        )?;
+        let messages = vec![
+            fake_message("Msg0", vec![fake_field("a", primitive::FLOAT)]),
+            fake_message("Msg1", vec![]),
+        ];
+        let file = fake_file("file.proto", vec![], messages);
+        let context = FileContext::new(&file, renderer.config())?;
+        let mut output = Vec::new();
+        renderer.render_file(context, &mut output)?;
+        assert_eq!(String::from_utf8(output)?, "file.proto\nMsg0: a\nMsg1:\n");
+        Ok(())
+    }
+
+    #[test]
+    fn missing_template_errors() -> Result<()> {
+        let renderer = JinjaRenderer::with_config(RendererConfig::default());
+        let file = fake_file("file.proto", vec![], vec![]);
+        let context = FileContext::new(&file, renderer.config())?;
+        assert!(!renderer.has_file());
+        assert!(renderer.render_file(context, &mut Vec::new()).is_err());
+        Ok(())
+    }
+
+    mod types {
+        use crate::render::Render;
+        use crate::renderer::jinja::renderer::JinjaRenderer;
+        use crate::renderer::jinja::{FILE_TEMPLATE_NAME, MESSAGE_TEMPLATE_NAME};
+        use crate::renderer::tests::{fake_file, fake_message};
+        use crate::renderer::RendererConfig;
+        use anyhow::Result;
+        use prost_types::FileDescriptorSet;
+        use std::fs;
+        use tempfile::tempdir;
+
+        #[test]
+        fn message_template_per_message() -> Result<()> {
+            let mut config = RendererConfig::default();
+            config.file_extension = "kt".to_owned();
+            config.generated_header = Some(vec![]);
+            config.type_file_name_pattern = Some("{file_name}/{name}.{ext}".to_owned());
+            let mut renderer = JinjaRenderer::with_config(config);
+            renderer.load_template_string(FILE_TEMPLATE_NAME, "file")?;
+            renderer
+                .load_template_string(MESSAGE_TEMPLATE_NAME, "{{ file.source_file }}:{{ name }}")?;
+            let messages = vec![fake_message("Msg0", vec![]), fake_message("Msg1", vec![])];
+            let file = fake_file("dir/file_name.proto", vec![], messages);
+            let output = tempdir()?;
+            Render::render(
+                &renderer,
+                &FileDescriptorSet { file: vec![file] },
+                output.path(),
+            )?;
+            assert_eq!(
+                fs::read_to_string(output.path().join("file_name/Msg0.kt"))?,
+                "dir/file_name.proto:Msg0"
+            );
+            assert!(output.path().join("file_name/Msg1.kt").exists());
+            Ok(())
+        }
+
+        #[test]
+        fn all_template() -> Result<()> {
+            let mut config = RendererConfig::default();
+            config.file_extension = "ts".to_owned();
+            config.all_file_name = "index".to_owned();
+            config.generated_header = Some(vec![]);
+            let mut renderer = JinjaRenderer::with_config(config);
+            renderer.load_template_string(FILE_TEMPLATE_NAME, "")?;
+            renderer.load_template_string(
+                "all.jinja",
+                "{% for file in files %}{{ file.source_file }};{% endfor %}",
+            )?;
+            let descriptor_set = FileDescriptorSet {
+                file: vec![
+                    fake_file("a.proto", vec![], vec![]),
+                    fake_file("b.proto", vec![], vec![]),
+                ],
+            };
+            let output = tempdir()?;
+            Render::render(&renderer, &descriptor_set, output.path())?;
+            assert_eq!(
+                fs::read_to_string(output.path().join("index.ts"))?,
+                "a.proto;b.proto;"
+            );
+            Ok(())
+        }
+    }
+
+    mod load {
+        use crate::dir_init::initialize_jinja_dir;
+        use crate::renderer::context::FileContext;
+        use crate::renderer::jinja::renderer::JinjaRenderer;
+        use crate::renderer::tests::fake_file_empty;
+        use crate::renderer::{Renderer, RendererConfig};
+        use anyhow::Result;
+        use std::fs;
+        use std::path::Path;
+        use tempfile::tempdir;
+
+        #[test]
+        fn templates_named_by_path() -> Result<()> {
+            let dir = tempdir()?;
+            let root = dir.path().join("templates");
+            write(
+                &root,
+                "file.jinja",
+                r#"{% include "messages/struct.jinja" %}"#,
+            )?;
+            write(
+                &root,
+                "messages/struct.jinja",
+                r#"{% import "macros.jinja" as m %}{{ m.field() }}"#,
+            )?;
+            write(
+                &root,
+                "macros.jinja",
+                "{% macro field() %}field{% endmacro %}",
+            )?;
+            assert_eq!(render(&root)?, "field");
+            Ok(())
+        }
+
+        #[test]
+        fn shared_partials() -> Result<()> {
+            let dir = tempdir()?;
+            let root = dir.path().join("templates");
+            write_config(&root, "../shared")?;
+            write(
+                &root,
+                "file.jinja",
+                r#"{% include "common/header.jinja" %} {% include "overridden.jinja" %}"#,
+            )?;
+            write(&root, "overridden.jinja", "local")?;
+            write(&dir.path().join("shared"), "common/header.jinja", "shared")?;
+            write(&dir.path().join("shared"), "overridden.jinja", "shared")?;
+            assert_eq!(render(&root)?, "shared local");
+            Ok(())
+        }
+
+        #[test]
+        fn missing_partials_dir_errors() -> Result<()> {
+            let dir = tempdir()?;
+            write_config(dir.path(), "missing")?;
+            write(dir.path(), "file.jinja", "")?;
+            let mut renderer = JinjaRenderer::new();
+            assert!(renderer.load(dir.path(), &[]).is_err());
+            Ok(())
+        }
+
+        #[test]
+        fn initialized_file_template_renders_empty() -> Result<()> {
+            let dir = tempdir()?;
+            initialize_jinja_dir(dir.path())?;
+            assert!(render(dir.path())?.trim().is_empty());
+            Ok(())
+        }
+
+        fn write(root: &Path, name: &str, contents: &str) -> Result<()> {
+            let path = root.join(name);
+            fs::create_dir_all(path.parent().unwrap())?;
+            fs::write(path, contents)?;
+            Ok(())
+        }
+
+        fn write_config(root: &Path, partials_path: &str) -> Result<()> {
+            let config = RendererConfig {
+                partials_path: vec![partials_path.into()],
+                ..Default::default()
+            };
+            write(root, "config.yaml", &serde_yaml::to_string(&config)?)
+        }
+
+        fn render(root: &Path) -> Result<String> {
+            if !root.join("config.yaml").exists() && !root.join("config.yml").exists() {
+                write(
+                    root,
+                    "config.yaml",
+                    &serde_yaml::to_string(&RendererConfig::default())?,
+                )?;
+            }
+            let mut renderer = JinjaRenderer::new();
+            renderer.load(root, &[])?;
+            let context = FileContext::new(&fake_file_empty("file.proto"), renderer.config())?;
+            let mut output = Vec::new();
+            renderer.render_file(context, &mut output)?;
+            Ok(String::from_utf8(output)?)
+        }
+    }
+
+    mod filters {
+        use crate::renderer::context::FileContext;
+        use crate::renderer::jinja::renderer::JinjaRenderer;
+        use crate::renderer::jinja::FILE_TEMPLATE_NAME;
+        use crate::renderer::overlay_config::OverlayConfig;
+        use crate::renderer::tests::{fake_file, fake_message};
+        use crate::renderer::{Renderer, RendererConfig};
+        use anyhow::Result;
+        use serde_json::json;
+        use std::collections::HashMap;
+
+        #[test]
+        fn case() -> Result<()> {
+            assert_eq!(
+                render(
+                    r#"{{ s | upper_camel }} {{ s | lower_kebab }} {{ s | case("UPPER_SNAKE") }}"#,
+                    json!({"s": "my_name"})
+                )?,
+                "MyName my-name MY_NAME"
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn unknown_case_errors() {
+            assert!(render(r#"{{ s | case("Sponge") }}"#, json!({"s": "a"})).is_err());
+        }
+
+        #[test]
+        fn comments() -> Result<()> {
+            assert_eq!(
+                render(
+                    r#"{{ s | wrap_comment(12, "/// ") }}|{{ s | comment("/*", 10) }}"#,
+                    json!({"s": "one two three"})
+                )?,
+                "/// one two\n/// three|/*\n * one two\n * three\n */"
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn builtins() -> Result<()> {
+            assert_eq!(
+                render(
+                    r#"{{ items | join(", ") }}|{{ "a\nb" | indent(2) }}|{{ missing | default("x") }}"#,
+                    json!({"items": ["a", "b", 1]})
+                )?,
+                "a, b, 1|a\n  b|x"
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn nothing_is_escaped() -> Result<()> {
+            assert_eq!(
+                render("{{ s }}", json!({"s": "Map<K, V> & \"x\""}))?,
+                "Map<K, V> & \"x\""
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn overlays() -> Result<()> {
+            let value = |s: &str| serde_yaml::Value::String(s.to_owned());
+            let config = RendererConfig {
+                overlays: OverlayConfig::new(
+                    HashMap::new(),
+                    HashMap::from([
+                        (
+                            "file.proto".to_owned(),
+                            HashMap::from([("key".to_owned(), value("file"))]),
+                        ),
+                        (
+                            "pkg.Msg".to_owned(),
+                            HashMap::from([("key".to_owned(), value("message"))]),
+                        ),
+                    ]),
+                ),
+                ..Default::default()
+            };
+            let mut renderer = JinjaRenderer::with_config(config);
+            renderer.load_template_string(
+                FILE_TEMPLATE_NAME,
+                r#"{{ overlays.key }} {% for m in messages %}{{ m.overlays.key }}{% endfor %} {{ lookup_overlay("pkg.Msg", "key") }}{% if lookup_overlay("pkg.Msg", "other") is none %}!{% endif %}"#,
+            )?;
+            let mut file = fake_file("file.proto", vec![], vec![fake_message("Msg", vec![])]);
+            file.package = Some("pkg".to_owned());
+            let context = FileContext::new(&file, renderer.config())?;
+            let mut output = Vec::new();
+            renderer.render_file(context, &mut output)?;
+            assert_eq!(String::from_utf8(output)?, "file message message!");
+            Ok(())
+        }
+
+        fn render(template: &str, data: serde_json::Value) -> Result<String> {
+            let mut renderer = JinjaRenderer::new();
+            renderer.load_template_string("test.jinja", template)?;
+            renderer.render_to_string("test.jinja", &data)
+        }
+    }
+
+    mod diagnostics {
+        use crate::diagnostic::Diagnostic;
+        use crate::renderer::context::FileContext;
+        use crate::renderer::jinja::renderer::JinjaRenderer;
+        use crate::renderer::tests::fake_file_empty;
+        use crate::renderer::Renderer;
+        use anyhow::Result;
+        use std::fs;
+        use tempfile::tempdir;
+
+        #[test]
+        fn parse_error_points_at_file() -> Result<()> {
+            let dir = tempdir()?;
+            fs::write(dir.path().join("file.jinja"), "line\n{% if x %}\n")?;
+            let mut renderer = JinjaRenderer::new();
+            let err = renderer.load_templates(dir.path()).err().unwrap();
+            let diagnostic = &Diagnostic::collect(&err)[0];
+            assert_eq!(diagnostic.path, Some(dir.path().join("file.jinja")));
+            assert!(diagnostic.line.is_some());
+            Ok(())
+        }
+
+        #[test]
+        fn render_error_points_at_include() -> Result<()> {
+            let dir = tempdir()?;
+            fs::write(
+                dir.path().join("file.jinja"),
+                r#"{% include "partial.jinja" %}"#,
+            )?;
+            fs::write(
+                dir.path().join("partial.jinja"),
+                "line\n{{ name | not_a_filter }}",
+            )?;
+            let mut renderer = JinjaRenderer::new();
+            renderer.load_templates(dir.path())?;
+            let context = FileContext::new(&fake_file_empty("file"), renderer.config())?;
+            let err = renderer
+                .render_file(context, &mut Vec::new())
+                .err()
+                .unwrap();
+            let diagnostic = &Diagnostic::collect(&err)[0];
+            assert_eq!(diagnostic.path, Some(dir.path().join("partial.jinja")));
+            assert_eq!(diagnostic.line, Some(2));
+            assert_eq!(
+                diagnostic.notes,
+                vec!["Failed to render template 'file.jinja'"]
+            );
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,594 @@
+use serde::{Deserialize, Serialize};
+
+/// Escapes names that are reserved words in the target language. Applied to message, enum,
+/// enum value, field and package names after their case is changed, including in type names.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct KeywordConfig {
+    /// Built-in reserved word lists to use.
+    ///
+    /// Any of: rust, cpp, csharp, java, kotlin, typescript, python, go, swift
+    #[serde(default)]
+    pub presets: Vec<KeywordPreset>,
+
+    /// Additional reserved words.
+    #[serde(default)]
+    pub words: Vec<String>,
+
+    /// How reserved words are escaped.
+    /// default: suffix
+    #[serde(default)]
+    pub escape: EscapeStrategy,
+
+    /// Text added by the escape strategy.
+    /// default: `_` for suffix and prefix, `r#` for raw.
+    #[serde(default)]
+    pub affix: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum KeywordPreset {
+    Rust,
+    Cpp,
+    CSharp,
+    Java,
+    Kotlin,
+    TypeScript,
+    Python,
+    Go,
+    Swift,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum EscapeStrategy {
+    /// e.g. `type_`
+    Suffix,
+    /// e.g. `_type`
+    Prefix,
+    /// A raw identifier, e.g. `r#type`. Words that can't be raw identifiers in rust, like `self`,
+    /// are escaped with a `_` suffix instead.
+    Raw,
+}
+
+impl Default for EscapeStrategy {
+    fn default() -> Self {
+        EscapeStrategy::Suffix
+    }
+}
+
+impl KeywordConfig {
+    pub fn is_reserved(&self, name: &str) -> bool {
+        self.words.iter().any(|word| word == name)
+            || self
+                .presets
+                .iter()
+                .any(|preset| preset.words().contains(&name))
+    }
+
+    /// Returns `name` escaped if it's a reserved word, otherwise unchanged.
+    pub fn escape(&self, name: String) -> String {
+        if !self.is_reserved(&name) {
+            return name;
+        }
+        match self.escape {
+            EscapeStrategy::Suffix => format!("{}{}", name, self.affix_or("_")),
+            EscapeStrategy::Prefix => format!("{}{}", self.affix_or("_"), name),
+            EscapeStrategy::Raw if RUST_NOT_RAW.contains(&name.as_str()) => format!("{}_", name),
+            EscapeStrategy::Raw => format!("{}{}", self.affix_or("r#"), name),
+        }
+    }
+
+    fn affix_or<'a>(&'a self, default: &'a str) -> &'a str {
+        self.affix.as_deref().unwrap_or(default)
+    }
+}
+
+impl KeywordPreset {
+    pub fn words(&self) -> &'static [&'static str] {
+        match self {
+            KeywordPreset::Rust => RUST,
+            KeywordPreset::Cpp => CPP,
+            KeywordPreset::CSharp => CSHARP,
+            KeywordPreset::Java => JAVA,
+            KeywordPreset::Kotlin => KOTLIN,
+            KeywordPreset::TypeScript => TYPESCRIPT,
+            KeywordPreset::Python => PYTHON,
+            KeywordPreset::Go => GO,
+            KeywordPreset::Swift => SWIFT,
+        }
+    }
+}
+
+const RUST_NOT_RAW: &[&str] = &["crate", "self", "Self", "super"];
+
+const RUST: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while", "abstract", "become", "box", "do", "final", "gen", "macro",
+    "override", "priv", "try", "typeof", "unsized", "virtual", "yield",
+];
+
+const CPP: &[&str] = &[
+    "alignas",
+    "alignof",
+    "and",
+    "and_eq",
+    "asm",
+    "auto",
+    "bitand",
+    "bitor",
+    "bool",
+    "break",
+    "case",
+    "catch",
+    "char",
+    "char8_t",
+    "char16_t",
+    "char32_t",
+    "class",
+    "compl",
+    "concept",
+    "const",
+    "consteval",
+    "constexpr",
+    "constinit",
+    "const_cast",
+    "continue",
+    "co_await",
+    "co_return",
+    "co_yield",
+    "decltype",
+    "default",
+    "delete",
+    "do",
+    "double",
+    "dynamic_cast",
+    "else",
+    "enum",
+    "explicit",
+    "export",
+    "extern",
+    "false",
+    "float",
+    "for",
+    "friend",
+    "goto",
+    "if",
+    "inline",
+    "int",
+    "long",
+    "mutable",
+    "namespace",
+    "new",
+    "noexcept",
+    "not",
+    "not_eq",
+    "nullptr",
+    "operator",
+    "or",
+    "or_eq",
+    "private",
+    "protected",
+    "public",
+    "register",
+    "reinterpret_cast",
+    "requires",
+    "return",
+    "short",
+    "signed",
+    "sizeof",
+    "static",
+    "static_assert",
+    "static_cast",
+    "struct",
+    "switch",
+    "template",
+    "this",
+    "thread_local",
+    "throw",
+    "true",
+    "try",
+    "typedef",
+    "typeid",
+    "typename",
+    "union",
+    "unsigned",
+    "using",
+    "virtual",
+    "void",
+    "volatile",
+    "wchar_t",
+    "while",
+    "xor",
+    "xor_eq",
+];
+
+const CSHARP: &[&str] = &[
+    "abstract",
+    "as",
+    "base",
+    "bool",
+    "break",
+    "byte",
+    "case",
+    "catch",
+    "char",
+    "checked",
+    "class",
+    "const",
+    "continue",
+    "decimal",
+    "default",
+    "delegate",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "event",
+    "explicit",
+    "extern",
+    "false",
+    "finally",
+    "fixed",
+    "float",
+    "for",
+    "foreach",
+    "goto",
+    "if",
+    "implicit",
+    "in",
+    "int",
+    "interface",
+    "internal",
+    "is",
+    "lock",
+    "long",
+    "namespace",
+    "new",
+    "null",
+    "object",
+    "operator",
+    "out",
+    "override",
+    "params",
+    "private",
+    "protected",
+    "public",
+    "readonly",
+    "ref",
+    "return",
+    "sbyte",
+    "sealed",
+    "short",
+    "sizeof",
+    "stackalloc",
+    "static",
+    "string",
+    "struct",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "uint",
+    "ulong",
+    "unchecked",
+    "unsafe",
+    "ushort",
+    "using",
+    "virtual",
+    "void",
+    "volatile",
+    "while",
+];
+
+const JAVA: &[&str] = &[
+    "abstract",
+    "assert",
+    "boolean",
+    "break",
+    "byte",
+    "case",
+    "catch",
+    "char",
+    "class",
+    "const",
+    "continue",
+    "default",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "extends",
+    "false",
+    "final",
+    "finally",
+    "float",
+    "for",
+    "goto",
+    "if",
+    "implements",
+    "import",
+    "instanceof",
+    "int",
+    "interface",
+    "long",
+    "native",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "short",
+    "static",
+    "strictfp",
+    "super",
+    "switch",
+    "synchronized",
+    "this",
+    "throw",
+    "throws",
+    "transient",
+    "true",
+    "try",
+    "void",
+    "volatile",
+    "while",
+    "_",
+];
+
+const KOTLIN: &[&str] = &[
+    "as",
+    "break",
+    "class",
+    "continue",
+    "do",
+    "else",
+    "false",
+    "for",
+    "fun",
+    "if",
+    "in",
+    "interface",
+    "is",
+    "null",
+    "object",
+    "package",
+    "return",
+    "super",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typealias",
+    "typeof",
+    "val",
+    "var",
+    "when",
+    "while",
+];
+
+const TYPESCRIPT: &[&str] = &[
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "import",
+    "in",
+    "instanceof",
+    "new",
+    "null",
+    "return",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "implements",
+    "interface",
+    "let",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "static",
+    "yield",
+    "any",
+    "boolean",
+    "number",
+    "string",
+    "symbol",
+    "type",
+    "await",
+];
+
+const PYTHON: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield",
+];
+
+const GO: &[&str] = &[
+    "break",
+    "case",
+    "chan",
+    "const",
+    "continue",
+    "default",
+    "defer",
+    "else",
+    "fallthrough",
+    "for",
+    "func",
+    "go",
+    "goto",
+    "if",
+    "import",
+    "interface",
+    "map",
+    "package",
+    "range",
+    "return",
+    "select",
+    "struct",
+    "switch",
+    "type",
+    "var",
+];
+
+const SWIFT: &[&str] = &[
+    "associatedtype",
+    "class",
+    "deinit",
+    "enum",
+    "extension",
+    "fileprivate",
+    "func",
+    "import",
+    "init",
+    "inout",
+    "internal",
+    "let",
+    "open",
+    "operator",
+    "private",
+    "protocol",
+    "public",
+    "rethrows",
+    "static",
+    "struct",
+    "subscript",
+    "typealias",
+    "var",
+    "break",
+    "case",
+    "continue",
+    "default",
+    "defer",
+    "do",
+    "else",
+    "fallthrough",
+    "for",
+    "guard",
+    "if",
+    "in",
+    "repeat",
+    "return",
+    "switch",
+    "where",
+    "while",
+    "as",
+    "Any",
+    "catch",
+    "false",
+    "is",
+    "nil",
+    "super",
+    "self",
+    "Self",
+    "throw",
+    "throws",
+    "true",
+    "try",
+];
+
+#[cfg(test)]
+mod tests {
+    use crate::renderer::keywords::{EscapeStrategy, KeywordConfig, KeywordPreset};
+
+    #[test]
+    fn no_keywords_by_default() {
+        let config = KeywordConfig::default();
+        assert_eq!(config.escape("type".to_owned()), "type");
+    }
+
+    #[test]
+    fn suffix() {
+        let config = config(EscapeStrategy::Suffix);
+        assert_eq!(config.escape("type".to_owned()), "type_");
+        assert_eq!(config.escape("name".to_owned()), "name");
+    }
+
+    #[test]
+    fn prefix() {
+        let config = config(EscapeStrategy::Prefix);
+        assert_eq!(config.escape("type".to_owned()), "_type");
+    }
+
+    #[test]
+    fn raw() {
+        let config = config(EscapeStrategy::Raw);
+        assert_eq!(config.escape("type".to_owned()), "r#type");
+        assert_eq!(config.escape("self".to_owned()), "self_");
+    }
+
+    #[test]
+    fn custom_affix() {
+        let mut config = config(EscapeStrategy::Prefix);
+        config.affix = Some("@".to_owned());
+        assert_eq!(config.escape("type".to_owned()), "@type");
+    }
+
+    #[test]
+    fn user_words() {
+        let config = KeywordConfig {
+            words: vec!["Message".to_owned()],
+            ..Default::default()
+        };
+        assert_eq!(config.escape("Message".to_owned()), "Message_");
+        // Case sensitive.
+        assert_eq!(config.escape("message".to_owned()), "message");
+    }
+
+    #[test]
+    fn preset_names() -> anyhow::Result<()> {
+        let config: KeywordConfig =
+            serde_yaml::from_str("presets: [rust, cpp, csharp, typescript]\nescape: raw")?;
+        assert_eq!(
+            config.presets,
+            vec![
+                KeywordPreset::Rust,
+                KeywordPreset::Cpp,
+                KeywordPreset::CSharp,
+                KeywordPreset::TypeScript
+            ]
+        );
+        assert_eq!(config.escape, EscapeStrategy::Raw);
+        Ok(())
+    }
+
+    fn config(escape: EscapeStrategy) -> KeywordConfig {
+        KeywordConfig {
+            presets: vec![KeywordPreset::Rust],
+            escape,
+            ..Default::default()
+        }
+    }
+}
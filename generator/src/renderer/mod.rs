@@ -1,4 +1,5 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::{fs, io};
 
@@ -7,21 +8,33 @@ use log::{debug, info};
 use prost_types::{FileDescriptorProto, FileDescriptorSet};
 use serde::de::DeserializeOwned;
 
+pub use named_output::NamedOutput;
 pub use overlay_config::OverlayConfig;
 pub use renderer_config::RendererConfig;
+pub use well_known::WellKnownTypesConfig;
 
 use crate::render::Render;
-use crate::renderer::context::{FileContext, MetadataContext};
-use crate::{util, DisplayNormalized};
+use crate::renderer::context::{
+    AllContext, EnumContext, FileContext, MessageContext, MetadataContext,
+};
+use crate::{parallel, util, DisplayNormalized};
 
 mod case;
 mod context;
+pub mod dump;
+pub mod jinja;
+mod keywords;
+mod named_output;
 mod overlay_config;
 mod primitive;
+pub mod process;
 mod proto;
 mod renderer_config;
 pub mod scripted;
 pub mod template;
+mod text;
+pub mod wasm;
+mod well_known;
 
 pub const CONFIG_FILE_NAMES: &[&'static str] = &["config.yml", "config.json", "config.yaml"];
 pub const DEFAULT_CONFIG_FILE_NAME: &'static str = CONFIG_FILE_NAMES[0];
@@ -41,34 +54,38 @@ impl<R: Renderer> Render for R {
     fn reset(&mut self) {
         Renderer::reset(self)
     }
-    fn render(&self, descriptor_set: &FileDescriptorSet, output_path: &Path) -> Result<()> {
-        if self.config().one_file_per_package {
-            let package_files = self.render_files_collapsed(descriptor_set, output_path)?;
-            self.render_metadata_with_package_files(output_path, package_files)?;
+    fn render(
+        &self,
+        descriptor_set: &FileDescriptorSet,
+        output_path: &Path,
+    ) -> Result<Vec<PathBuf>> {
+        self.load_output_path(output_path)?;
+        self.load_type_registry(descriptor_set)?;
+        // Every output of this render, so that no output silently overwrites another.
+        let written = &mut HashSet::new();
+        // With per-type files, the per-file entry point is optional.
+        let render_files = self.has_file() || self.config().type_file_name_pattern.is_none();
+        if !render_files {
+            self.render_metadata_for_directories(descriptor_set, output_path, written)?;
+        } else if self.config().one_file_per_package {
+            let package_files =
+                self.render_files_collapsed(descriptor_set, output_path, written)?;
+            self.render_metadata_with_package_files(output_path, package_files, written)?;
         } else {
-            self.render_files(descriptor_set, output_path)?;
-            self.render_metadata_for_directories(descriptor_set, output_path)?;
+            self.render_files(descriptor_set, output_path, written)?;
+            self.render_metadata_for_directories(descriptor_set, output_path, written)?;
         }
-        Ok(())
+        self.render_types(descriptor_set, output_path, written)?;
+        self.render_all_to_file(descriptor_set, output_path, written)?;
+        let mut files = written.drain().collect::<Vec<PathBuf>>();
+        files.sort();
+        Ok(files)
     }
 }
 
-pub trait Renderer {
+pub trait Renderer: Sync {
     fn load_config(path: &Path, overlays: &[PathBuf]) -> Result<RendererConfig> {
-        info!("Loading config from: {}", path.display_normalized());
-        let mut config: RendererConfig = deserialize_yaml_file(path).context("RendererConfig")?;
-        Self::load_overlays(&mut config.overlays, overlays)?;
-        config.overlays.initialize();
-        Ok(config)
-    }
-
-    fn load_overlays(base: &mut OverlayConfig, paths: &[PathBuf]) -> Result<()> {
-        for path in paths {
-            info!("Loading overlay config from: {}", path.display_normalized());
-            let overlay = deserialize_yaml_file(path).context("OverlayConfig")?;
-            base.merge(overlay);
-        }
-        Ok(())
+        load_config(path, overlays)
     }
 
     /// Load any necessary files from the `input_root` directory and overlays as specified.
@@ -83,6 +100,67 @@ pub trait Renderer {
         -> Result<()>;
     fn render_file<W: io::Write>(&self, context: FileContext, writer: &mut W) -> Result<()>;
 
+    /// Called before rendering, for renderers that let scripts see where outputs are written.
+    fn load_output_path(&self, _output_path: &Path) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called before rendering, for renderers that let scripts look up types across files.
+    fn load_type_registry(&self, _descriptor_set: &FileDescriptorSet) -> Result<()> {
+        Ok(())
+    }
+
+    fn has_file(&self) -> bool {
+        true
+    }
+    fn has_message(&self) -> bool {
+        false
+    }
+    fn has_enum(&self) -> bool {
+        false
+    }
+    fn has_all(&self) -> bool {
+        false
+    }
+
+    fn render_message<W: io::Write>(
+        &self,
+        _context: &MessageContext,
+        _file: &FileContext,
+        _writer: &mut W,
+    ) -> Result<()> {
+        Err(anyhow!(
+            "Rendering messages is not supported by this renderer."
+        ))
+    }
+
+    fn render_enum<W: io::Write>(
+        &self,
+        _context: &EnumContext,
+        _file: &FileContext,
+        _writer: &mut W,
+    ) -> Result<()> {
+        Err(anyhow!(
+            "Rendering enums is not supported by this renderer."
+        ))
+    }
+
+    fn render_all<W: io::Write>(&self, _context: AllContext, _writer: &mut W) -> Result<()> {
+        Err(anyhow!(
+            "Rendering all files at once is not supported by this renderer."
+        ))
+    }
+
+    /// Like `render_file`, but may also emit any number of extra files, e.g. one per message.
+    fn render_file_outputs<W: io::Write>(
+        &self,
+        context: FileContext,
+        writer: &mut W,
+    ) -> Result<Vec<NamedOutput>> {
+        self.render_file(context, writer)?;
+        Ok(Vec::new())
+    }
+
     fn output_ext(&self) -> &str {
         &self.config().file_extension
     }
@@ -91,19 +169,137 @@ pub trait Renderer {
         &self.config().metadata_file_name
     }
 
-    fn render_files(&self, descriptor_set: &FileDescriptorSet, output_path: &Path) -> Result<()> {
-        for file in &descriptor_set.file {
+    /// When a file only emits named outputs, its own output file is skipped.
+    /// Files are rendered in parallel, then written in order so duplicate outputs are found the
+    /// same way on every run.
+    fn render_files(
+        &self,
+        descriptor_set: &FileDescriptorSet,
+        output_path: &Path,
+        written: &mut HashSet<PathBuf>,
+    ) -> Result<()> {
+        let rendered = parallel::map_ordered(&descriptor_set.file, |file| {
             if self.is_ignored_file(file) {
                 log_ignore_file(&file.name, &self.config().file_extension);
-                continue;
+                return Ok(None);
             }
-            let file_name = &file_name(file, self.output_ext())?;
+            let file_name = file_name(file, self.output_ext())?;
             info!("Rendering file for descriptor '{}'", file_name);
-            let path = &output_path.join(file_name);
-            let mut writer = self.file_writer(&path)?;
             log_render_file(&file.name, &self.config().file_extension);
-            let context = FileContext::new(file, &self.config())?;
-            self.render_file(context, &mut writer)?;
+            let context = FileContext::new(file, self.config())?;
+            let mut content = Vec::new();
+            let named_outputs = self.render_file_outputs(context, &mut content)?;
+            Ok(Some((output_path.join(file_name), content, named_outputs)))
+        })?;
+        for (path, content, named_outputs) in rendered.into_iter().flatten() {
+            if named_outputs.is_empty() || !content.is_empty() {
+                self.file_writer(&path, written)?.write_all(&content)?;
+            }
+            self.write_named_outputs(output_path, named_outputs, written)?;
+        }
+        Ok(())
+    }
+
+    /// Renders each message and enum to its own file when `type_file_name_pattern` is set.
+    fn render_types(
+        &self,
+        descriptor_set: &FileDescriptorSet,
+        output_path: &Path,
+        written: &mut HashSet<PathBuf>,
+    ) -> Result<()> {
+        let pattern = match &self.config().type_file_name_pattern {
+            None => return Ok(()),
+            Some(pattern) => pattern,
+        };
+        let rendered = parallel::map_ordered(&descriptor_set.file, |file| {
+            let mut named_outputs = Vec::new();
+            if self.is_ignored_file(file) {
+                return Ok(named_outputs);
+            }
+            let file_context = FileContext::new(file, self.config())?;
+            if self.has_message() {
+                for message in file_context.messages() {
+                    let mut content = Vec::new();
+                    self.render_message(message, &file_context, &mut content)?;
+                    named_outputs.push(self.type_output(
+                        pattern,
+                        file,
+                        &file_context,
+                        message.name(),
+                        content,
+                    )?);
+                }
+            }
+            if self.has_enum() {
+                for enum_context in file_context.enums() {
+                    let mut content = Vec::new();
+                    self.render_enum(enum_context, &file_context, &mut content)?;
+                    named_outputs.push(self.type_output(
+                        pattern,
+                        file,
+                        &file_context,
+                        enum_context.name(),
+                        content,
+                    )?);
+                }
+            }
+            Ok(named_outputs)
+        })?;
+        let named_outputs = rendered.into_iter().flatten().collect();
+        self.write_named_outputs(output_path, named_outputs, written)
+    }
+
+    fn type_output(
+        &self,
+        pattern: &str,
+        file: &FileDescriptorProto,
+        file_context: &FileContext,
+        type_name: &str,
+        content: Vec<u8>,
+    ) -> Result<NamedOutput> {
+        let file_name = util::str_or_error(&file.name, || "File has no 'name'".to_owned())?;
+        let file_stem = Path::new(file_name)
+            .file_stem()
+            .map(|x| x.to_string_lossy().to_string())
+            .unwrap_or_default();
+        // Built from the proto package, since package_separator and case config are meant for
+        // code, not paths.
+        let package_dir = file
+            .package()
+            .split(proto::PACKAGE_SEPARATOR)
+            .filter(|x| !x.is_empty())
+            .collect::<Vec<&str>>()
+            .join("/");
+        let mut path = pattern.to_owned();
+        if package_dir.is_empty() {
+            // Types without a package go in the output root rather than at an absolute path.
+            path = path.replace("{package_dir}/", "");
+        }
+        let path = path
+            .replace("{name}", type_name)
+            .replace("{package}", file_context.package())
+            .replace("{package_dir}", &package_dir)
+            .replace("{file_name}", &file_stem)
+            .replace("{ext}", self.output_ext());
+        info!("Rendering type '{}' to '{}'", type_name, path);
+        NamedOutput::new(&path, String::from_utf8(content)?)
+            .context("Invalid type_file_name_pattern")
+    }
+
+    fn write_named_outputs(
+        &self,
+        output_path: &Path,
+        named_outputs: Vec<NamedOutput>,
+        written: &mut HashSet<PathBuf>,
+    ) -> Result<()> {
+        for named_output in named_outputs {
+            // Paths chosen by the renderer are used as is, without applying case config.
+            let path = output_path.join(&named_output.path);
+            check_not_written(written, &path)?;
+            info!("Writing named output '{}'", path.display_normalized());
+            let mut writer = io::BufWriter::new(util::create_file_or_error(&path)?);
+            self.write_generated_header(&mut writer)?;
+            writer.write_all(named_output.content.as_bytes())?;
         }
         Ok(())
     }
@@ -112,9 +308,10 @@ pub trait Renderer {
         &self,
         descriptor_set: &FileDescriptorSet,
         output_path: &Path,
-    ) -> Result<HashMap<String, PathBuf>> {
+        written: &mut HashSet<PathBuf>,
+    ) -> Result<BTreeMap<String, PathBuf>> {
         let package_to_files = self.collect_package_to_file_map(descriptor_set);
-        let mut package_files = HashMap::new();
+        let mut package_files = BTreeMap::new();
         for (package, files) in package_to_files {
             let files = files
                 .into_iter()
@@ -124,11 +321,12 @@ pub trait Renderer {
                 continue;
             }
             let path = &self.package_to_file_path(output_path, package);
-            let mut writer = self.file_writer(&path)?;
+            let mut writer = self.file_writer(path, written)?;
             for file in files {
                 log_render_package_file(file, package);
                 let context = FileContext::new(file, &self.config())?;
-                self.render_file(context, &mut writer)?;
+                let named_outputs = self.render_file_outputs(context, &mut writer)?;
+                self.write_named_outputs(output_path, named_outputs, written)?;
             }
             package_files.insert(
                 package.to_owned(),
@@ -142,28 +340,52 @@ pub trait Renderer {
         &self,
         descriptor_set: &FileDescriptorSet,
         output_path: &Path,
+        written: &mut HashSet<PathBuf>,
     ) -> Result<()> {
         if !self.has_metadata() {
             return Ok(());
         }
-        let (dirs, files) = collect_dirs_and_files(descriptor_set)?;
-        let mut contexts = Vec::new();
-        for dir in &dirs {
-            let mut context = MetadataContext::with_relative_dir(dir)?;
-            context.append_subdirectories(dirs.iter())?;
-            context.append_files(&files)?;
-            contexts.push(context);
-        }
-        for context in contexts {
-            self.render_metadata_to_file(output_path, context)?;
+        for context in metadata_contexts(descriptor_set)? {
+            self.render_metadata_to_file(output_path, context, written)?;
         }
         Ok(())
     }
 
-    fn render_metadata_to_file(&self, output_path: &Path, context: MetadataContext) -> Result<()> {
+    /// Renders every file at once to a single `all_file_name` file.
+    fn render_all_to_file(
+        &self,
+        descriptor_set: &FileDescriptorSet,
+        output_path: &Path,
+        written: &mut HashSet<PathBuf>,
+    ) -> Result<()> {
+        if !self.has_all() {
+            return Ok(());
+        }
+        let files = descriptor_set
+            .file
+            .iter()
+            .filter(|file| !self.is_ignored_file(file));
+        let context = AllContext::new(files, self.config())?;
+        let file_path = output_path
+            .join(&self.config().all_file_name)
+            .with_extension(&self.config().file_extension);
+        info!(
+            "Rendering all files to '{}'",
+            file_path.display_normalized()
+        );
+        let mut writer = self.file_writer(&file_path, written)?;
+        self.render_all(context, &mut writer)
+    }
+
+    fn render_metadata_to_file(
+        &self,
+        output_path: &Path,
+        context: MetadataContext,
+        written: &mut HashSet<PathBuf>,
+    ) -> Result<()> {
         let file_path = self.metadata_file_path(output_path, &context);
         log_render_metadata(&file_path);
-        let mut writer = self.file_writer(&file_path)?;
+        let mut writer = self.file_writer(&file_path, written)?;
         self.render_metadata(context, &mut writer)?;
         Ok(())
     }
@@ -171,42 +393,45 @@ pub trait Renderer {
     fn render_metadata_with_package_files(
         &self,
         output_path: &Path,
-        package_files: HashMap<String, PathBuf>,
+        package_files: BTreeMap<String, PathBuf>,
+        written: &mut HashSet<PathBuf>,
     ) -> Result<()> {
         if !self.has_metadata() {
             return Ok(());
         }
         let mut context = MetadataContext::new();
         context.append_package_files(package_files);
-        self.render_metadata_to_file(output_path, context)?;
+        self.render_metadata_to_file(output_path, context, written)?;
         Ok(())
     }
 
-    fn file_writer(&self, path: &Path) -> Result<io::BufWriter<fs::File>> {
-        let path = self.config().case_config.file_name.rename_file_name(path);
+    fn renamed_file_path(&self, path: &Path) -> PathBuf {
+        self.config().case_config.file_name.rename_file_name(path)
+    }
+
+    /// Errors if the (renamed) path was already written during this render.
+    fn file_writer(
+        &self,
+        path: &Path,
+        written: &mut HashSet<PathBuf>,
+    ) -> Result<io::BufWriter<fs::File>> {
+        let path = self.renamed_file_path(path);
+        check_not_written(written, &path)?;
         let mut writer = io::BufWriter::new(util::create_file_or_error(&path)?);
         self.write_generated_header(&mut writer)?;
         Ok(writer)
     }
 
     fn write_generated_header<W: io::Write>(&self, writer: &mut W) -> Result<()> {
-        if let Some(configured_header) = &self.config().generated_header {
-            if !configured_header.is_empty() {
-                let mut header = configured_header.join("\n");
-                header.push('\n');
-                writer.write(header.as_bytes())?;
-            }
-        } else {
-            writer.write(DEFAULT_GENERATED_HEADER.as_bytes())?;
-        }
-        Ok(())
+        write_generated_header(self.config(), writer)
     }
 
+    /// Packages are sorted, and files are in descriptor set order within each package.
     fn collect_package_to_file_map<'a>(
         &'a self,
         descriptor_set: &'a FileDescriptorSet,
-    ) -> HashMap<&'a str, Vec<&'a FileDescriptorProto>> {
-        let mut map = HashMap::new();
+    ) -> BTreeMap<&'a str, Vec<&'a FileDescriptorProto>> {
+        let mut map = BTreeMap::new();
         for file in &descriptor_set.file {
             let package = package(file, &self.config().default_package_file_name);
             let files = map.entry(package).or_insert(Vec::new());
@@ -235,6 +460,17 @@ pub trait Renderer {
     }
 }
 
+/// Guards against renderers emitting the same file twice, which would silently overwrite it.
+fn check_not_written(written: &mut HashSet<PathBuf>, path: &Path) -> Result<()> {
+    if !written.insert(path.to_path_buf()) {
+        return Err(anyhow!(
+            "Output file '{}' was written more than once.",
+            path.display_normalized()
+        ));
+    }
+    Ok(())
+}
+
 fn deserialize_yaml_file<T: DeserializeOwned>(path: &Path) -> Result<T> {
     let file = fs::File::open(path).context("Failed to read file.")?;
     let buf_reader = io::BufReader::new(file);
@@ -254,10 +490,55 @@ pub fn find_existing_config_path(input_root: &Path) -> Result<PathBuf> {
     ))
 }
 
+/// Loads the config at `path`, with each overlay file merged in order.
+fn load_config(path: &Path, overlays: &[PathBuf]) -> Result<RendererConfig> {
+    info!("Loading config from: {}", path.display_normalized());
+    let mut config: RendererConfig = deserialize_yaml_file(path).context("RendererConfig")?;
+    load_overlays(&mut config.overlays, overlays)?;
+    config.overlays.initialize();
+    Ok(config)
+}
+
+fn load_overlays(base: &mut OverlayConfig, paths: &[PathBuf]) -> Result<()> {
+    for path in paths {
+        info!("Loading overlay config from: {}", path.display_normalized());
+        let overlay = deserialize_yaml_file(path).context("OverlayConfig")?;
+        base.merge(overlay);
+    }
+    Ok(())
+}
+
+fn write_generated_header<W: io::Write>(config: &RendererConfig, writer: &mut W) -> Result<()> {
+    if let Some(configured_header) = &config.generated_header {
+        if !configured_header.is_empty() {
+            let mut header = configured_header.join("\n");
+            header.push('\n');
+            writer.write(header.as_bytes())?;
+        }
+    } else {
+        writer.write(DEFAULT_GENERATED_HEADER.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// One metadata context for each directory containing proto files, at any depth.
+fn metadata_contexts(descriptor_set: &FileDescriptorSet) -> Result<Vec<MetadataContext>> {
+    let (dirs, files) = collect_dirs_and_files(descriptor_set)?;
+    let mut contexts = Vec::new();
+    for dir in &dirs {
+        let mut context = MetadataContext::with_relative_dir(dir)?;
+        context.append_subdirectories(dirs.iter())?;
+        context.append_files(&files)?;
+        contexts.push(context);
+    }
+    Ok(contexts)
+}
+
+/// Dirs are sorted, and files are in descriptor set order.
 fn collect_dirs_and_files(
     descriptor_set: &FileDescriptorSet,
-) -> Result<(HashSet<PathBuf>, Vec<PathBuf>)> {
-    let mut dirs = HashSet::new();
+) -> Result<(BTreeSet<PathBuf>, Vec<PathBuf>)> {
+    let mut dirs = BTreeSet::new();
     let mut files = Vec::new();
     for file in &descriptor_set.file {
         let relative_path = file_relative_path(file)?;
@@ -267,7 +548,7 @@ fn collect_dirs_and_files(
     Ok((dirs, files))
 }
 
-fn insert_all_parents(dirs: &mut HashSet<PathBuf>, path: &Path) -> Result<()> {
+fn insert_all_parents(dirs: &mut BTreeSet<PathBuf>, path: &Path) -> Result<()> {
     let parent = util::path_parent_or_error(&path).context("insert_all_parents")?;
     dirs.insert(parent.to_path_buf());
     if !parent.as_os_str().is_empty() {
@@ -342,7 +623,7 @@ mod tests {
         DescriptorProto, EnumDescriptorProto, FieldDescriptorProto, FileDescriptorProto,
     };
 
-    use crate::renderer::context::{FileContext, MetadataContext};
+    use crate::renderer::context::{AllContext, FileContext, MessageContext, MetadataContext};
     use crate::renderer::{Renderer, RendererConfig};
 
     mod load_config {
@@ -454,7 +735,9 @@ mod tests {
 
         use crate::render::Render;
         use crate::renderer::case::Case;
-        use crate::renderer::tests::{fake_file_empty, fake_file_with_package, FakeRenderer};
+        use crate::renderer::tests::{
+            fake_file, fake_file_empty, fake_file_with_package, fake_message, FakeRenderer,
+        };
         use crate::renderer::RendererConfig;
 
         #[test]
@@ -573,6 +856,52 @@ mod tests {
             Ok(())
         }
 
+        #[test]
+        fn type_output_colliding_with_file_output_errors() -> Result<()> {
+            let config = RendererConfig {
+                type_file_name_pattern: Some("{file_name}".to_owned()),
+                ..Default::default()
+            };
+            let mut renderer = FakeRenderer::with_config(config);
+            renderer.has_message = true;
+            let test_dir = tempdir()?;
+            let set = FileDescriptorSet {
+                file: vec![fake_file("msg", vec![], vec![fake_message("msg", vec![])])],
+            };
+            let result = renderer.render(&set, test_dir.path());
+            assert!(result
+                .unwrap_err()
+                .to_string()
+                .contains("was written more than once"));
+            Ok(())
+        }
+
+        #[test]
+        fn all_file_colliding_with_file_output_errors() -> Result<()> {
+            let config = RendererConfig {
+                all_file_name: "file1".to_owned(),
+                ..Default::default()
+            };
+            let mut renderer = FakeRenderer::with_config(config);
+            renderer.has_all = true;
+            let test_dir = tempdir()?;
+            assert!(renderer.render(&test_file_set(), test_dir.path()).is_err());
+            Ok(())
+        }
+
+        #[test]
+        fn metadata_colliding_with_file_output_errors() -> Result<()> {
+            let config = RendererConfig {
+                metadata_file_name: "file1".to_owned(),
+                ..Default::default()
+            };
+            let mut renderer = FakeRenderer::with_config(config);
+            renderer.has_metadata = true;
+            let test_dir = tempdir()?;
+            assert!(renderer.render(&test_file_set(), test_dir.path()).is_err());
+            Ok(())
+        }
+
         fn test_file_set() -> FileDescriptorSet {
             FileDescriptorSet {
                 file: vec![
@@ -865,6 +1194,8 @@ mod tests {
     struct FakeRenderer {
         pub config: RendererConfig,
         pub has_metadata: bool,
+        pub has_message: bool,
+        pub has_all: bool,
     }
 
     impl FakeRenderer {
@@ -902,6 +1233,27 @@ mod tests {
         fn render_file<W: io::Write>(&self, _context: FileContext, _writer: &mut W) -> Result<()> {
             Ok(())
         }
+
+        fn has_message(&self) -> bool {
+            self.has_message
+        }
+
+        fn has_all(&self) -> bool {
+            self.has_all
+        }
+
+        fn render_message<W: io::Write>(
+            &self,
+            _context: &MessageContext,
+            _file: &FileContext,
+            _writer: &mut W,
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        fn render_all<W: io::Write>(&self, _context: AllContext, _writer: &mut W) -> Result<()> {
+            Ok(())
+        }
     }
 
     fn fake_file_with_package(
@@ -0,0 +1,82 @@
+use anyhow::{anyhow, Result};
+use std::path::{Component, Path, PathBuf};
+
+/// An extra file emitted while rendering a proto file, in addition to the file's own output.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NamedOutput {
+    /// Relative to the output directory, and guaranteed to stay inside it.
+    pub path: PathBuf,
+    pub content: String,
+}
+
+impl NamedOutput {
+    pub fn new(path: &str, content: String) -> Result<Self> {
+        Ok(Self {
+            path: relative_output_path(path)?,
+            content,
+        })
+    }
+}
+
+/// Normalizes `path` and checks that it's relative and doesn't leave the output directory,
+/// e.g. `a/../b.txt` is allowed but `../b.txt` or `/b.txt` are not.
+pub fn relative_output_path(path: &str) -> Result<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(name) => normalized.push(name),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return Err(anyhow!(
+                        "Output path '{}' must stay inside the output directory.",
+                        path
+                    ));
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => {
+                return Err(anyhow!(
+                    "Output path '{}' must be relative to the output directory.",
+                    path
+                ));
+            }
+        }
+    }
+    if normalized.as_os_str().is_empty() {
+        return Err(anyhow!("Output path '{}' must name a file.", path));
+    }
+    Ok(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::renderer::named_output::relative_output_path;
+    use anyhow::Result;
+    use std::path::PathBuf;
+
+    #[test]
+    fn normalizes() -> Result<()> {
+        assert_eq!(
+            relative_output_path("./a/b/../c.txt")?,
+            PathBuf::from("a/c.txt")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_escaping_output_dir() {
+        assert!(relative_output_path("../a.txt").is_err());
+        assert!(relative_output_path("a/../../a.txt").is_err());
+    }
+
+    #[test]
+    fn rejects_absolute() {
+        assert!(relative_output_path("/a.txt").is_err());
+    }
+
+    #[test]
+    fn rejects_empty() {
+        assert!(relative_output_path("").is_err());
+        assert!(relative_output_path("a/..").is_err());
+    }
+}
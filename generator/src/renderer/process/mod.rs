@@ -0,0 +1,40 @@
+use anyhow::Result;
+
+use crate::in_out_generator::InOutGenerator;
+use crate::renderer::process::renderer::ProcessRenderer;
+use crate::{Config, InOutConfig};
+
+mod process_config;
+mod renderer;
+
+pub const PROCESS_CONFIG_FILE_NAMES: &[&str] = &["process.yml", "process.json", "process.yaml"];
+
+pub fn generate(config: &Config) -> Result<()> {
+    Generator {
+        config,
+        renderer: ProcessRenderer::new(),
+    }
+    .generate()
+}
+
+struct Generator<'a> {
+    config: &'a Config,
+    renderer: ProcessRenderer,
+}
+impl<'a> InOutGenerator<ProcessRenderer> for Generator<'a> {
+    fn name(&self) -> &str {
+        "External Processes"
+    }
+
+    fn renderer(&mut self) -> &mut ProcessRenderer {
+        &mut self.renderer
+    }
+
+    fn app_config(&self) -> &Config {
+        self.config
+    }
+
+    fn in_out_configs(&self) -> Vec<InOutConfig> {
+        self.app_config().process.clone()
+    }
+}
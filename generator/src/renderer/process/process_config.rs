@@ -0,0 +1,32 @@
+use serde::Deserialize;
+
+/// How to run an external generator, loaded from `process.yml` next to its `config.yml`.
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ProcessConfig {
+    /// The program followed by its arguments. It runs in the input directory, and a program
+    /// path with a directory, e.g. `./gen.py`, is relative to it.
+    /// e.g. ["python3", "gen.py", "--lang", "ts"]
+    pub command: Vec<String>,
+
+    /// How contexts are written to stdin, and outputs read from stdout.
+    /// default: json
+    #[serde(default)]
+    pub format: ProcessFormat,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProcessFormat {
+    /// A single document with every file and metadata context, answered with a single array of
+    /// outputs.
+    Json,
+    /// One context per line, answered with one output per line.
+    Ndjson,
+}
+
+impl Default for ProcessFormat {
+    fn default() -> Self {
+        Self::Json
+    }
+}
@@ -0,0 +1,403 @@
+use crate::render::Render;
+use crate::renderer::context::{AllContext, MetadataContext};
+use crate::renderer::named_output::NamedOutput;
+use crate::renderer::process::process_config::{ProcessConfig, ProcessFormat};
+use crate::renderer::process::PROCESS_CONFIG_FILE_NAMES;
+use crate::renderer::{
+    check_not_written, deserialize_yaml_file, dump, find_existing_config_path, load_config,
+    metadata_contexts, write_generated_header, RendererConfig,
+};
+use crate::{util, DisplayNormalized};
+use anyhow::{anyhow, Context, Result};
+use log::info;
+use prost_types::{FileDescriptorProto, FileDescriptorSet};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::env;
+use std::io::{self, Write};
+use std::iter;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// Renders with a user-supplied command, so existing generators in any language can reuse the
+/// contexts, case config and overlays of protox.
+///
+/// The command is spawned once per run. Every context is written to its stdin, and it answers
+/// on stdout with the files to write, as `{"path": ..., "contents": ...}` objects.
+pub struct ProcessRenderer {
+    config: RendererConfig,
+    process: ProcessConfig,
+    input_root: PathBuf,
+}
+
+/// Contexts written to stdin with [`ProcessFormat::Json`].
+#[derive(Serialize)]
+struct Input {
+    files: Vec<Value>,
+    metadata: Vec<MetadataContext>,
+    all: Value,
+}
+
+/// A line written to stdin with [`ProcessFormat::Ndjson`].
+#[derive(Serialize)]
+#[serde(tag = "kind", content = "context", rename_all = "lowercase")]
+enum InputLine<'a> {
+    File(&'a Value),
+    Metadata(&'a MetadataContext),
+    All(&'a Value),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Output {
+    path: String,
+    contents: String,
+}
+
+impl ProcessRenderer {
+    pub fn new() -> Self {
+        Self {
+            config: Default::default(),
+            process: ProcessConfig {
+                command: vec![],
+                format: ProcessFormat::default(),
+            },
+            input_root: PathBuf::new(),
+        }
+    }
+
+    fn input(&self, descriptor_set: &FileDescriptorSet) -> Result<Vec<u8>> {
+        let context = AllContext::new(
+            descriptor_set
+                .file
+                .iter()
+                .filter(|file| !self.is_ignored_file(file)),
+            &self.config,
+        )?;
+        let files = context
+            .files()
+            .iter()
+            .map(dump::file_value)
+            .collect::<Result<Vec<Value>>>()?;
+        let all = dump::all_value(&context)?;
+        let metadata = metadata_contexts(descriptor_set)?;
+        match self.process.format {
+            ProcessFormat::Json => Ok(serde_json::to_vec(&Input {
+                files,
+                metadata,
+                all,
+            })?),
+            ProcessFormat::Ndjson => {
+                let lines = files
+                    .iter()
+                    .map(InputLine::File)
+                    .chain(metadata.iter().map(InputLine::Metadata))
+                    .chain(iter::once(InputLine::All(&all)));
+                let mut input = Vec::new();
+                for line in lines {
+                    serde_json::to_writer(&mut input, &line)?;
+                    input.push(b'\n');
+                }
+                Ok(input)
+            }
+        }
+    }
+
+    /// Writes `input` from another thread, so neither side blocks on a full pipe.
+    fn run(&self, input: Vec<u8>) -> Result<Vec<u8>> {
+        let (program, args) = self.process.command.split_first().ok_or_else(|| {
+            anyhow!(
+                "'command' is empty in process config in: {}",
+                self.input_root.display_normalized()
+            )
+        })?;
+        info!("Running command: {}", self.process.command.join(" "));
+        let mut child = Command::new(self.program_path(program))
+            .args(args)
+            .current_dir(&self.input_root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to spawn command '{}'", program))?;
+        let mut stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow!("Failed to open stdin of command '{}'", program))?;
+        let writer = thread::spawn(move || stdin.write_all(&input));
+
+        let output = child.wait_with_output()?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() {
+            return Err(anyhow!(
+                "Command '{}' exited with status {}{}",
+                program,
+                output.status,
+                if stderr.trim().is_empty() {
+                    String::new()
+                } else {
+                    format!(":\n{}", stderr.trim_end())
+                }
+            ));
+        }
+        for line in stderr.lines() {
+            info!("[process] {}", line);
+        }
+        match writer.join() {
+            Err(_) => return Err(anyhow!("Failed to write to stdin of '{}'", program)),
+            // Commands that succeed without reading all of their input are fine.
+            Ok(Err(err)) if err.kind() != io::ErrorKind::BrokenPipe => {
+                return Err(err)
+                    .with_context(|| format!("Failed to write to stdin of '{}'", program))
+            }
+            Ok(_) => {}
+        }
+        Ok(output.stdout)
+    }
+
+    fn outputs(&self, stdout: &[u8]) -> Result<Vec<NamedOutput>> {
+        let outputs = match self.process.format {
+            ProcessFormat::Json => serde_json::from_slice::<Vec<Output>>(stdout)
+                .context("Expected a json array of {\"path\", \"contents\"} objects on stdout")?,
+            ProcessFormat::Ndjson => String::from_utf8(stdout.to_vec())?
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| {
+                    serde_json::from_str::<Output>(line).with_context(|| {
+                        format!(
+                            "Expected a {{\"path\", \"contents\"}} json object on each line of stdout, but got: {}",
+                            line
+                        )
+                    })
+                })
+                .collect::<Result<Vec<Output>>>()?,
+        };
+        outputs
+            .into_iter()
+            .map(|output| NamedOutput::new(&output.path, output.contents))
+            .collect()
+    }
+
+    fn write_outputs(&self, output_path: &Path, outputs: Vec<NamedOutput>) -> Result<Vec<PathBuf>> {
+        let mut written = HashSet::new();
+        for output in outputs {
+            let path = output_path.join(&output.path);
+            check_not_written(&mut written, &path)?;
+            info!("Writing process output '{}'", path.display_normalized());
+            let mut writer = io::BufWriter::new(util::create_file_or_error(&path)?);
+            write_generated_header(&self.config, &mut writer)?;
+            writer.write_all(output.content.as_bytes())?;
+        }
+        let mut files = written.into_iter().collect::<Vec<PathBuf>>();
+        files.sort();
+        Ok(files)
+    }
+
+    /// Programs with a directory are relative to the input directory, but bare names like
+    /// `python3` are looked up on the `PATH`.
+    fn program_path(&self, program: &str) -> PathBuf {
+        let path = Path::new(program);
+        if path.is_relative() && path.components().count() > 1 {
+            self.input_root.join(path)
+        } else {
+            path.to_path_buf()
+        }
+    }
+
+    fn is_ignored_file(&self, file: &FileDescriptorProto) -> bool {
+        match file.name.as_ref() {
+            None => true,
+            Some(file) => self.config.ignored_files.contains(file),
+        }
+    }
+}
+
+impl Render for ProcessRenderer {
+    /// Loads config and the command from the same root path with the following names:
+    /// ```txt
+    ///     root/config.json
+    ///     root/process.json
+    /// ```
+    fn load(&mut self, root: &Path, overlays: &[PathBuf]) -> Result<()> {
+        self.config = load_config(&find_existing_config_path(root)?, overlays)?;
+        let path = find_existing_process_config_path(root)?;
+        info!("Loading process config from: {}", path.display_normalized());
+        self.process = deserialize_yaml_file(&path).context("ProcessConfig")?;
+        // Relative programs are resolved against it, which is ambiguous if it's relative too.
+        self.input_root = env::current_dir()?.join(root);
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.process.command.clear();
+    }
+
+    fn render(
+        &self,
+        descriptor_set: &FileDescriptorSet,
+        output_path: &Path,
+    ) -> Result<Vec<PathBuf>> {
+        let input = self.input(descriptor_set)?;
+        let stdout = self.run(input)?;
+        let outputs = self
+            .outputs(&stdout)
+            .with_context(|| format!("Invalid output from '{}'", self.process.command.join(" ")))?;
+        self.write_outputs(output_path, outputs)
+    }
+}
+
+fn find_existing_process_config_path(root: &Path) -> Result<PathBuf> {
+    for name in PROCESS_CONFIG_FILE_NAMES {
+        let path = root.join(name);
+        if path.exists() {
+            return Ok(path);
+        }
+    }
+    Err(anyhow!(
+        "Could not find process config file. Must be named one of: {}",
+        PROCESS_CONFIG_FILE_NAMES.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::render::Render;
+    use crate::renderer::process::renderer::ProcessRenderer;
+    use crate::renderer::tests::{fake_file, fake_message};
+    use crate::renderer::RendererConfig;
+    use anyhow::Result;
+    use prost_types::FileDescriptorSet;
+    use serde_json::Value;
+    use std::fs;
+    use std::path::Path;
+    use tempfile::tempdir;
+
+    #[test]
+    fn json() -> Result<()> {
+        let dir = tempdir()?;
+        let output = render(
+            dir.path(),
+            "json",
+            r#"cat > input.json; printf '[{"path": "a/b.txt", "contents": "hi"}]'"#,
+        )?;
+        assert_eq!(fs::read_to_string(output.join("a/b.txt"))?, "hi");
+        let input: Value =
+            serde_json::from_str(&fs::read_to_string(dir.path().join("input/input.json"))?)?;
+        assert_eq!(input["files"][0]["source_file"], "dir/file.proto");
+        assert_eq!(input["files"][0]["messages"][0]["name"], "Msg");
+        assert_eq!(input["metadata"].as_array().unwrap().len(), 2);
+        assert_eq!(input["all"]["files"][0]["source_file"], "dir/file.proto");
+        assert_eq!(input["all"]["files"][0]["messages"][0]["name"], "Msg");
+        Ok(())
+    }
+
+    #[test]
+    fn ndjson() -> Result<()> {
+        let dir = tempdir()?;
+        let output = render(
+            dir.path(),
+            "ndjson",
+            r#"cat > input.ndjson; printf '{"path": "a.txt", "contents": "1"}\n\n{"path": "b.txt", "contents": "2"}\n'"#,
+        )?;
+        assert_eq!(fs::read_to_string(output.join("a.txt"))?, "1");
+        assert_eq!(fs::read_to_string(output.join("b.txt"))?, "2");
+        let input = fs::read_to_string(dir.path().join("input/input.ndjson"))?;
+        let lines = input
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<Value>, _>>()?;
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0]["kind"], "file");
+        assert_eq!(lines[0]["context"]["source_file"], "dir/file.proto");
+        assert_eq!(lines[1]["kind"], "metadata");
+        assert_eq!(lines[3]["kind"], "all");
+        assert_eq!(
+            lines[3]["context"]["files"][0]["source_file"],
+            "dir/file.proto"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn failing_command_errors_with_stderr() -> Result<()> {
+        let dir = tempdir()?;
+        let err = render(dir.path(), "json", "echo oops >&2; exit 3").unwrap_err();
+        assert!(format!("{:?}", err).contains("oops"));
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_output_errors() -> Result<()> {
+        let dir = tempdir()?;
+        assert!(render(dir.path(), "json", "echo not json").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn output_outside_output_dir_errors() -> Result<()> {
+        let dir = tempdir()?;
+        assert!(render(
+            dir.path(),
+            "json",
+            r#"printf '[{"path": "../a.txt", "contents": ""}]'"#
+        )
+        .is_err());
+        assert!(!dir.path().join("a.txt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn duplicate_output_errors() -> Result<()> {
+        let dir = tempdir()?;
+        assert!(render(
+            dir.path(),
+            "json",
+            r#"printf '[{"path": "a.txt", "contents": ""}, {"path": "./a.txt", "contents": ""}]'"#
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn missing_process_config_errors() -> Result<()> {
+        let dir = tempdir()?;
+        write_config(dir.path())?;
+        assert!(ProcessRenderer::new().load(dir.path(), &[]).is_err());
+        Ok(())
+    }
+
+    /// Runs `script` with `sh` on a single proto file, returning the output directory.
+    fn render(root: &Path, format: &str, script: &str) -> Result<std::path::PathBuf> {
+        let input = root.join("input");
+        fs::create_dir_all(&input)?;
+        write_config(&input)?;
+        fs::write(
+            input.join("process.yml"),
+            format!("command: [sh, gen.sh]\nformat: {}\n", format),
+        )?;
+        fs::write(input.join("gen.sh"), script)?;
+        let mut renderer = ProcessRenderer::new();
+        renderer.load(&input, &[])?;
+        let output = root.join("output");
+        let descriptor_set = FileDescriptorSet {
+            file: vec![fake_file(
+                "dir/file.proto",
+                vec![],
+                vec![fake_message("Msg", vec![])],
+            )],
+        };
+        renderer.render(&descriptor_set, &output)?;
+        Ok(output)
+    }
+
+    fn write_config(root: &Path) -> Result<()> {
+        let config = RendererConfig {
+            generated_header: Some(vec![]),
+            ..Default::default()
+        };
+        fs::write(root.join("config.yml"), serde_yaml::to_string(&config)?)?;
+        Ok(())
+    }
+}
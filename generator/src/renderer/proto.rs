@@ -1,4 +1,5 @@
 use crate::renderer::case::Case;
+use crate::renderer::keywords::KeywordConfig;
 
 pub const PACKAGE_SEPARATOR: char = '.';
 pub const PACKAGE_SEPARATOR_STR: &str = ".";
@@ -9,6 +10,7 @@ pub struct TypePath<'a> {
     separator: Option<&'a str>,
     type_name_case: Option<Case>,
     package_case: Option<Case>,
+    keywords: Option<&'a KeywordConfig>,
 }
 
 impl<'a> TypePath<'a> {
@@ -20,6 +22,7 @@ impl<'a> TypePath<'a> {
             separator: None,
             type_name_case: None,
             package_case: None,
+            keywords: None,
         }
     }
 
@@ -35,6 +38,7 @@ impl<'a> TypePath<'a> {
             separator: None,
             type_name_case: None,
             package_case: None,
+            keywords: None,
         }
     }
 
@@ -48,7 +52,7 @@ impl<'a> TypePath<'a> {
             .as_ref()
             .map(|name| match self.type_name_case {
                 None => name.to_owned(),
-                Some(case) => case.rename(&name),
+                Some(case) => self.escape(case.rename(&name)),
             })
     }
 
@@ -70,6 +74,18 @@ impl<'a> TypePath<'a> {
         self.package_case = case;
     }
 
+    /// Escape reserved words in components and the type name whose case is changed.
+    pub fn set_keywords(&mut self, keywords: Option<&'a KeywordConfig>) {
+        self.keywords = keywords;
+    }
+
+    fn escape(&self, name: String) -> String {
+        match self.keywords {
+            None => name,
+            Some(keywords) => keywords.escape(name),
+        }
+    }
+
     pub fn separator(&self) -> &str {
         self.separator.unwrap_or(PACKAGE_SEPARATOR_STR)
     }
@@ -80,7 +96,7 @@ impl<'a> TypePath<'a> {
             .iter()
             .map(|s| match self.package_case {
                 None => s.to_owned(),
-                Some(case) => case.rename(s),
+                Some(case) => self.escape(case.rename(s)),
             })
             .collect::<Vec<String>>();
         if let Some(type_name) = &self.type_name_with_case() {
@@ -199,6 +215,15 @@ impl<'a> TypePath<'a> {
     }
 }
 
+/// Joins a package or outer type and a name into a fully qualified proto name, e.g.
+/// `pkg.Message`. Without a scope, the name is returned as is.
+pub fn join_name(scope: Option<&str>, name: &str) -> String {
+    match scope {
+        Some(scope) if !scope.is_empty() => format!("{}{}{}", scope, PACKAGE_SEPARATOR, name),
+        _ => name.to_owned(),
+    }
+}
+
 pub fn normalize_prefix(path: &str) -> &str {
     // Normalizes a path by removing the first separator.
     // e.g. ".root.sub.TypeName" to "root.sub.TypeName"
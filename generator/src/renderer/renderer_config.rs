@@ -1,9 +1,11 @@
 use crate::renderer::case::Case;
+use crate::renderer::keywords::KeywordConfig;
 use crate::renderer::overlay_config::OverlayConfig;
-use crate::renderer::template::METADATA_TEMPLATE_NAME;
-use crate::renderer::{primitive, proto};
+use crate::renderer::template::{ALL_TEMPLATE_NAME, METADATA_TEMPLATE_NAME};
+use crate::renderer::{primitive, proto, WellKnownTypesConfig};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct RendererConfig {
@@ -32,6 +34,32 @@ pub struct RendererConfig {
     /// ```
     pub type_config: HashMap<String, String>,
 
+    /// If set, `google.protobuf.*` well-known types like `Timestamp` or `StringValue` are mapped
+    /// to native types. See `WellKnownTypesConfig`.
+    /// ```txt
+    /// e.g.
+    /// {
+    ///     "preset": "typescript",
+    ///     "types": { "Duration": "number" }
+    /// }
+    /// ```
+    #[serde(default)]
+    pub well_known_types: Option<WellKnownTypesConfig>,
+
+    /// Wrap field types to form `full_type` in field contexts. `{}` is replaced with the inner
+    /// type, or `{key}` and `{value}` for maps.
+    /// ```txt
+    /// e.g.
+    /// {
+    ///     "repeated": "Vec<{}>",
+    ///     "map": "HashMap<{key}, {value}>",
+    ///     "optional": "Option<{}>",
+    ///     "message": "Box<{}>"
+    /// }
+    /// ```
+    #[serde(default)]
+    pub type_patterns: TypePatterns,
+
     #[serde(default)]
     pub case_config: CaseConfig,
 
@@ -40,6 +68,12 @@ pub struct RendererConfig {
     #[serde(default = "default_metadata_file_name")]
     pub metadata_file_name: String,
 
+    /// Name of the file rendered from the whole descriptor set with the `render_all` script
+    /// function or `all.hbs` template.
+    /// default: "all"
+    #[serde(default = "default_all_file_name")]
+    pub all_file_name: String,
+
     /// Separator used in qualified type names.
     /// e.g. root.sub.TypeName
     ///          ^   ^
@@ -54,6 +88,23 @@ pub struct RendererConfig {
     #[serde(default)]
     pub one_file_per_package: bool,
 
+    /// If set, each message and enum is also rendered to its own file with the `render_message` and
+    /// `render_enum` script functions, or the `message.hbs` and `enum.hbs` templates. This is the
+    /// path of each file relative to the output directory, where these are replaced:
+    /// ```txt
+    /// {name}        name of the message or enum
+    /// {package}     package of the proto file, e.g. root.sub
+    /// {package_dir} package as a directory, e.g. root/sub, regardless of package_separator
+    /// {file_name}   name of the proto file without extension
+    /// {ext}         file_extension
+    /// ```
+    /// e.g. "{package_dir}/{name}.{ext}"
+    ///
+    /// Unlike per-file output, `case_config.file_name` is not applied to these paths. Types of
+    /// files without a package are written to the output directory itself.
+    #[serde(default)]
+    pub type_file_name_pattern: Option<String>,
+
     /// When `one_file_per_package` is true, use this file name when a package is not specified in the proto file.
     /// default: `unknown`
     #[serde(default = "default_package_file_name")]
@@ -66,6 +117,30 @@ pub struct RendererConfig {
     #[serde(default)]
     pub field_name_override: HashMap<String, String>,
 
+    /// Naming rules for enum values, applied before case config.
+    /// ```txt
+    /// e.g. strip `COLOR_` from `COLOR_RED` in `enum Color`, and drop `COLOR_UNSPECIFIED = 0`
+    /// {
+    ///     "strip_prefix": true,
+    ///     "unspecified": "drop"
+    /// }
+    /// ```
+    #[serde(default)]
+    pub enum_values: EnumValueConfig,
+
+    /// Escape reserved words of the target language in message, enum, enum value, field and
+    /// package names. Applied after case config, and not to fields in `field_name_override`.
+    /// ```txt
+    /// e.g.
+    /// {
+    ///     "presets": ["rust"],
+    ///     "words": ["Message"],
+    ///     "escape": "raw"
+    /// }
+    /// ```
+    #[serde(default)]
+    pub keywords: KeywordConfig,
+
     /// A list of input files that will not be rendered.
     /// e.g. "some/useless/file.proto"
     #[serde(default)]
@@ -94,10 +169,32 @@ pub struct RendererConfig {
     /// Explicitly setting this to an empty array will disable the header entirely.
     pub generated_header: Option<Vec<String>>,
 
+    /// Directories of shared templates to use as partials, e.g. from several template sets.
+    /// Relative paths are relative to the template directory. Like templates in the template
+    /// directory, they're named by their path without the extension, e.g. `{{> messages/struct}}`,
+    /// and templates in the template directory take precedence.
+    /// ```txt
+    /// e.g. ["../shared"]
+    /// ```
+    #[serde(default)]
+    pub partials_path: Vec<PathBuf>,
+
+    /// User-defined values scripts read from `config.params`, so one script set can render
+    /// several variants. Each can be overridden with `--script-param`.
+    /// ```txt
+    /// e.g. { target: unity, nullable_refs: true }
+    /// ```
+    #[serde(default)]
+    pub params: HashMap<String, serde_yaml::Value>,
+
     /// Options for the ScriptedRenderer Output methods related to scope.
     #[serde(default)]
     pub scripted: ScriptedConfig,
 
+    /// Options for WebAssembly plugins.
+    #[serde(default)]
+    pub wasm: WasmConfig,
+
     #[serde(default)]
     pub overlays: OverlayConfig,
 }
@@ -106,6 +203,10 @@ fn default_metadata_file_name() -> String {
     METADATA_TEMPLATE_NAME.to_owned()
 }
 
+fn default_all_file_name() -> String {
+    ALL_TEMPLATE_NAME.to_owned()
+}
+
 fn default_package_separator() -> String {
     proto::PACKAGE_SEPARATOR.to_string()
 }
@@ -125,6 +226,55 @@ pub struct CaseConfig {
     pub field_name: Case,
 }
 
+/// Naming rules for enum values, see `RendererConfig::enum_values`.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct EnumValueConfig {
+    /// Strip the enum name in UPPER_SNAKE_CASE followed by `_` from value names.
+    #[serde(default)]
+    pub strip_prefix: bool,
+
+    /// What to do with a zero value named `UNSPECIFIED` or `<ENUM>_UNSPECIFIED`.
+    /// default: keep
+    #[serde(default)]
+    pub unspecified: UnspecifiedValue,
+
+    /// If set, the unspecified value is given this name, as is.
+    #[serde(default)]
+    pub unspecified_name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum UnspecifiedValue {
+    Keep,
+    Drop,
+}
+
+impl Default for UnspecifiedValue {
+    fn default() -> Self {
+        UnspecifiedValue::Keep
+    }
+}
+
+/// Patterns applied to field types, see `RendererConfig::type_patterns`.
+/// Patterns that aren't set leave the type as is.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct TypePatterns {
+    /// Applied to repeated fields, outermost.
+    #[serde(default)]
+    pub repeated: Option<String>,
+    /// Applied to map fields, with `{key}` and `{value}`.
+    /// default: `map<{key}, {value}>`
+    #[serde(default)]
+    pub map: Option<String>,
+    /// Applied to proto3 `optional` fields and nullable well-known wrapper types.
+    #[serde(default)]
+    pub optional: Option<String>,
+    /// Applied to message types, innermost.
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub enum IndentChar {
     Space,
@@ -138,6 +288,48 @@ pub struct ScriptedConfig {
     pub indent_char: IndentChar,
     /// Config for Output scope.
     pub scope: ScopeConfig,
+    /// Additional directories searched for modules used with `import`, after the script
+    /// directory and before the bundled `protox/` library. Relative paths are evaluated relative
+    /// to the script directory.
+    ///
+    /// ```txt
+    /// e.g.
+    /// module_paths: [ ../shared, /opt/protox/rhai ]
+    /// ```
+    #[serde(default)]
+    pub module_paths: Vec<PathBuf>,
+}
+
+/// Options specific to the WasmRenderer.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct WasmConfig {
+    /// Fuel each call into the plugin may use, roughly one unit per instruction. A plugin that
+    /// runs out fails instead of hanging, e.g. on an infinite loop.
+    /// default: 1000000000
+    #[serde(default = "default_wasm_fuel")]
+    pub fuel: u64,
+
+    /// Maximum size in bytes the plugin's memory may grow to during a call.
+    /// default: 268435456 (256 MiB)
+    #[serde(default = "default_wasm_max_memory")]
+    pub max_memory: usize,
+}
+
+impl Default for WasmConfig {
+    fn default() -> Self {
+        Self {
+            fuel: default_wasm_fuel(),
+            max_memory: default_wasm_max_memory(),
+        }
+    }
+}
+
+fn default_wasm_fuel() -> u64 {
+    1_000_000_000
+}
+
+fn default_wasm_max_memory() -> usize {
+    256 * 1024 * 1024
 }
 
 /// Options for the ScriptedRenderer Output methods related to scope.
@@ -177,17 +369,26 @@ impl Default for RendererConfig {
         Self {
             file_extension: "".to_string(),
             type_config: default_type_config(),
+            well_known_types: None,
+            type_patterns: Default::default(),
             case_config: Default::default(),
             metadata_file_name: default_metadata_file_name(),
+            all_file_name: default_all_file_name(),
             package_separator: default_package_separator(),
             one_file_per_package: false,
+            type_file_name_pattern: None,
             default_package_file_name: default_package_file_name(),
             field_name_override: Default::default(),
+            enum_values: Default::default(),
+            keywords: Default::default(),
             ignored_files: vec![],
             ignored_imports: vec![],
             field_relative_parent_prefix: None,
             generated_header: None,
+            partials_path: vec![],
+            params: Default::default(),
             scripted: Default::default(),
+            wasm: Default::default(),
             overlays: Default::default(),
         }
     }
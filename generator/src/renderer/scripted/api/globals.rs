@@ -0,0 +1,81 @@
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+use rhai::{Engine, Map};
+
+use crate::renderer::RendererConfig;
+use crate::DisplayNormalized;
+
+pub const CONFIG_VAR: &str = "config";
+pub const ENV_VAR: &str = "env";
+
+/// Values pushed as the `config` and `env` constants into the scope of each entry point call.
+/// Updated by the renderer as it's loaded and before each render.
+#[derive(Clone, Default)]
+pub struct Globals {
+    pub config: ConfigGlobal,
+    pub env: EnvGlobal,
+}
+
+pub type SharedGlobals = Arc<RwLock<Globals>>;
+
+/// Read-only view of the config the scripts were loaded with.
+#[derive(Clone, Default)]
+pub struct ConfigGlobal(pub Arc<RendererConfig>);
+
+/// Information about the current run.
+#[derive(Clone, Default)]
+pub struct EnvGlobal {
+    pub input_dir: PathBuf,
+    pub output_dir: PathBuf,
+}
+
+pub fn register(engine: &mut Engine) {
+    engine
+        .register_type_with_name::<ConfigGlobal>("Config")
+        .register_get("file_extension", |config: &mut ConfigGlobal| {
+            config.0.file_extension.clone()
+        })
+        .register_get("package_separator", |config: &mut ConfigGlobal| {
+            config.0.package_separator.clone()
+        })
+        .register_get("type_config", |config: &mut ConfigGlobal| {
+            config
+                .0
+                .type_config
+                .iter()
+                .map(|(proto, native)| (proto.into(), native.clone().into()))
+                .collect::<Map>()
+        })
+        .register_get("params", |config: &mut ConfigGlobal| {
+            config
+                .0
+                .params
+                .iter()
+                .map(|(key, value)| (key.into(), super::yaml_to_dynamic(value)))
+                .collect::<Map>()
+        })
+        .register_fn(
+            "overlay",
+            |config: &mut ConfigGlobal, target: &str, key: &str| {
+                config
+                    .0
+                    .overlays
+                    .by_target(target)
+                    .and_then(|overlays| overlays.get(key))
+                    .cloned()
+                    .unwrap_or(serde_yaml::Value::Null)
+            },
+        );
+    engine
+        .register_type_with_name::<EnvGlobal>("Env")
+        .register_get("input_dir", |env: &mut EnvGlobal| {
+            env.input_dir.display_normalized()
+        })
+        .register_get("output_dir", |env: &mut EnvGlobal| {
+            env.output_dir.display_normalized()
+        })
+        .register_get("protox_version", |_: &mut EnvGlobal| {
+            env!("CARGO_PKG_VERSION").to_owned()
+        });
+}
@@ -1,11 +1,15 @@
 use rhai::exported_module;
 use rhai::plugin::*;
-use std::collections::{BTreeMap, HashMap};
 
+pub mod globals;
 pub mod output;
+pub mod registry;
+pub mod util;
 
-pub fn register(engine: &mut Engine) {
+pub fn register(engine: &mut Engine, type_registry: &registry::SharedTypeRegistry) {
     output::register(engine);
+    registry::register(engine, type_registry);
+    globals::register(engine);
     register_context(engine);
     proto_options::register_script_apis(engine);
 }
@@ -18,20 +22,12 @@ fn get_str_or_new(opt: Option<&String>) -> String {
     opt.map(&String::clone).unwrap_or(String::new())
 }
 
-fn hash_to_btree<K: Ord, V>(map: HashMap<K, V>) -> BTreeMap<K, V> {
-    let mut btree = BTreeMap::<K, V>::new();
-    for (k, v) in map {
-        btree.insert(k, v);
-    }
-    btree
-}
-
 #[export_module]
 mod api {
     use super::get_str_or_new;
+    use crate::renderer::case::Case;
     use crate::renderer::context;
     use crate::renderer::context::overlayed::Overlayed;
-    use crate::renderer::scripted::api::hash_to_btree;
     use crate::util::DisplayNormalized;
     use log::error;
     use std::collections::BTreeMap;
@@ -51,6 +47,14 @@ mod api {
         return result;
     }
 
+    /// Changes the case of `value`, where `case` is any name accepted by case config,
+    /// e.g. `UpperCamel` or `lower_snake_case`.
+    #[rhai_fn(name = "to_case", return_raw)]
+    pub fn to_case(value: &str, case: &str) -> Result<String, Box<rhai::EvalAltResult>> {
+        let case = case.parse::<Case>().map_err(|err| err.to_string())?;
+        Ok(case.rename(value))
+    }
+
     pub type YamlValue = serde_yaml::Value;
 
     ////////////////////////////////////////////////////
@@ -66,6 +70,8 @@ mod api {
     pub type MetadataContext = context::MetadataContext;
     pub type PackageFile = context::PackageFile;
     pub type PackageTreeNode = context::PackageTreeNode;
+    pub type AllContext = context::AllContext;
+    pub type PackageNode = context::PackageNode;
 
     pub type FileOptions = prost_types::FileOptions;
     pub type EnumOptions = prost_types::EnumOptions;
@@ -129,6 +135,16 @@ mod api {
         context.name().to_owned()
     }
 
+    #[rhai_fn(get = "proto_name", pure)]
+    pub fn enum_proto_name(context: &mut EnumContext) -> String {
+        context.proto_name().to_owned()
+    }
+
+    #[rhai_fn(get = "full_proto_name", pure)]
+    pub fn enum_full_proto_name(context: &mut EnumContext) -> String {
+        context.full_proto_name().to_owned()
+    }
+
     #[rhai_fn(get = "values", pure)]
     pub fn enum_values(context: &mut EnumContext) -> rhai::Dynamic {
         context.values().clone().into()
@@ -151,6 +167,21 @@ mod api {
         context.name().to_owned()
     }
 
+    #[rhai_fn(get = "proto_name", pure)]
+    pub fn enum_value_proto_name(context: &mut EnumValueContext) -> String {
+        context.proto_name().to_owned()
+    }
+
+    #[rhai_fn(get = "full_proto_name", pure)]
+    pub fn enum_value_full_proto_name(context: &mut EnumValueContext) -> String {
+        context.full_proto_name().to_owned()
+    }
+
+    #[rhai_fn(get = "is_unspecified", pure)]
+    pub fn enum_value_is_unspecified(context: &mut EnumValueContext) -> bool {
+        context.is_unspecified()
+    }
+
     #[rhai_fn(get = "number", pure)]
     pub fn enum_value_number(context: &mut EnumValueContext) -> rhai::INT {
         context.number().into()
@@ -176,6 +207,16 @@ mod api {
         context.name().to_owned()
     }
 
+    #[rhai_fn(get = "proto_name", pure)]
+    pub fn message_proto_name(context: &mut MessageContext) -> String {
+        context.proto_name().to_owned()
+    }
+
+    #[rhai_fn(get = "full_proto_name", pure)]
+    pub fn message_full_proto_name(context: &mut MessageContext) -> String {
+        context.full_proto_name().to_owned()
+    }
+
     #[rhai_fn(get = "fields", pure)]
     pub fn message_fields(context: &mut MessageContext) -> rhai::Dynamic {
         context.fields().clone().into()
@@ -201,6 +242,16 @@ mod api {
         context.name().to_owned()
     }
 
+    #[rhai_fn(get = "proto_name", pure)]
+    pub fn field_proto_name(context: &mut FieldContext) -> String {
+        context.proto_name().to_owned()
+    }
+
+    #[rhai_fn(get = "full_proto_name", pure)]
+    pub fn field_full_proto_name(context: &mut FieldContext) -> String {
+        context.full_proto_name().to_owned()
+    }
+
     #[rhai_fn(get = "fully_qualified_type", pure)]
     pub fn field_fully_qualified_type(context: &mut FieldContext) -> String {
         get_str_or_new(context.fully_qualified_type())
@@ -226,6 +277,31 @@ mod api {
         context.is_oneof()
     }
 
+    #[rhai_fn(get = "type_kind", pure)]
+    pub fn field_type_kind(context: &mut FieldContext) -> String {
+        context.type_kind().as_str().to_owned()
+    }
+
+    #[rhai_fn(get = "proto_scalar_type", pure)]
+    pub fn field_proto_scalar_type(context: &mut FieldContext) -> String {
+        get_str_or_new(context.proto_scalar_type())
+    }
+
+    #[rhai_fn(get = "is_nullable", pure)]
+    pub fn field_is_nullable(context: &mut FieldContext) -> bool {
+        context.is_nullable()
+    }
+
+    #[rhai_fn(get = "is_optional", pure)]
+    pub fn field_is_optional(context: &mut FieldContext) -> bool {
+        context.is_optional()
+    }
+
+    #[rhai_fn(get = "full_type", pure)]
+    pub fn field_full_type(context: &mut FieldContext) -> String {
+        context.full_type().to_owned()
+    }
+
     #[rhai_fn(get = "fully_qualified_key_type", pure)]
     pub fn field_fully_qualified_key_type(context: &mut FieldContext) -> String {
         get_str_or_new(context.fully_qualified_key_type())
@@ -289,7 +365,7 @@ mod api {
 
     #[rhai_fn(get = "package_file_tree", pure)]
     pub fn metadata_package_file_tree(context: &mut MetadataContext) -> rhai::Dynamic {
-        hash_to_btree(context.package_file_tree().clone()).into()
+        context.package_file_tree().clone().into()
     }
 
     ////////////////////////////////////////////////////
@@ -315,7 +391,38 @@ mod api {
 
     #[rhai_fn(get = "children", pure)]
     pub fn package_tree_node_children(context: &mut PackageTreeNode) -> rhai::Dynamic {
-        hash_to_btree(context.children().clone()).into()
+        context.children().clone().into()
+    }
+
+    ////////////////////////////////////////////////////
+    // AllContext
+
+    #[rhai_fn(get = "files", pure)]
+    pub fn all_files(context: &mut AllContext) -> rhai::Dynamic {
+        context.files().clone().into()
+    }
+
+    #[rhai_fn(get = "package_tree", pure)]
+    pub fn all_package_tree(context: &mut AllContext) -> rhai::Dynamic {
+        context.package_tree().clone().into()
+    }
+
+    ////////////////////////////////////////////////////
+    // PackageNode
+
+    #[rhai_fn(get = "package_", pure)]
+    pub fn package_node_package(context: &mut PackageNode) -> String {
+        context.package().to_owned()
+    }
+
+    #[rhai_fn(get = "files", pure)]
+    pub fn package_node_files(context: &mut PackageNode) -> rhai::Dynamic {
+        context.files().clone().into()
+    }
+
+    #[rhai_fn(get = "children", pure)]
+    pub fn package_node_children(context: &mut PackageNode) -> rhai::Dynamic {
+        context.children().clone().into()
     }
 
     ////////////////////////////////////////////////////
@@ -573,6 +680,89 @@ mod api {
         }
         Ok(map.into())
     }
+
+    /// Value of `key` in a map, or null if it's not a map or doesn't have the key.
+    #[rhai_fn(name = "get", pure)]
+    pub fn yaml_value_get(value: &mut YamlValue, key: &str) -> YamlValue {
+        value.get(key).cloned().unwrap_or(YamlValue::Null)
+    }
+
+    /// Value at a `.` separated path of map keys and array indices, e.g. `"imports.0.path"`,
+    /// or null if there's nothing there.
+    #[rhai_fn(name = "get_path", pure)]
+    pub fn yaml_value_get_path(value: &mut YamlValue, path: &str) -> YamlValue {
+        let mut current = &*value;
+        for part in path.split('.') {
+            let next = match part.parse::<usize>() {
+                Ok(index) if current.is_sequence() => current.get(index),
+                _ => current.get(part),
+            };
+            match next {
+                None => return YamlValue::Null,
+                Some(next) => current = next,
+            }
+        }
+        current.clone()
+    }
+
+    #[rhai_fn(name = "as_str_or", pure)]
+    pub fn yaml_value_as_str_or(value: &mut YamlValue, default: &str) -> String {
+        value.as_str().unwrap_or(default).to_owned()
+    }
+
+    #[rhai_fn(name = "as_int_or", pure)]
+    pub fn yaml_value_as_int_or(value: &mut YamlValue, default: rhai::INT) -> rhai::INT {
+        value.as_i64().unwrap_or(default)
+    }
+
+    #[rhai_fn(name = "as_float_or", pure)]
+    pub fn yaml_value_as_float_or(value: &mut YamlValue, default: rhai::FLOAT) -> rhai::FLOAT {
+        value.as_f64().unwrap_or(default)
+    }
+
+    #[rhai_fn(name = "as_bool_or", pure)]
+    pub fn yaml_value_as_bool_or(value: &mut YamlValue, default: bool) -> bool {
+        value.as_bool().unwrap_or(default)
+    }
+
+    /// Converts the value and everything inside it to plain rhai values, e.g. so an overlay map
+    /// can be iterated or compared directly. Null becomes `()`.
+    #[rhai_fn(name = "to_dynamic", pure)]
+    pub fn yaml_value_to_dynamic(value: &mut YamlValue) -> rhai::Dynamic {
+        super::yaml_to_dynamic(value)
+    }
+}
+
+fn yaml_to_dynamic(value: &serde_yaml::Value) -> Dynamic {
+    match value {
+        serde_yaml::Value::Null => Dynamic::UNIT,
+        serde_yaml::Value::Bool(value) => (*value).into(),
+        serde_yaml::Value::Number(number) => match number.as_i64() {
+            Some(int) => (int as rhai::INT).into(),
+            None => (number.as_f64().unwrap_or_default() as rhai::FLOAT).into(),
+        },
+        serde_yaml::Value::String(value) => value.clone().into(),
+        serde_yaml::Value::Sequence(values) => values
+            .iter()
+            .map(yaml_to_dynamic)
+            .collect::<rhai::Array>()
+            .into(),
+        serde_yaml::Value::Mapping(mapping) => {
+            let mut map = rhai::Map::new();
+            for (key, value) in mapping {
+                let key = match key {
+                    serde_yaml::Value::String(key) => key.clone(),
+                    key => serde_yaml::to_string(key)
+                        .unwrap_or_default()
+                        .trim_start_matches("---")
+                        .trim()
+                        .to_owned(),
+                };
+                map.insert(key.into(), yaml_to_dynamic(value));
+            }
+            map.into()
+        }
+    }
 }
 
 #[cfg(test)]
@@ -759,6 +949,65 @@ mod tests {
             Ok(())
         }
 
+        #[test]
+        fn get() -> Result<()> {
+            let value = serde_yaml::from_str("{ a: 1 }")?;
+            let success = run_test::<bool>(
+                value,
+                r#"value.get("a").as_int() == 1 && value.get("b").is_null()"#,
+            )?;
+            assert!(success);
+            Ok(())
+        }
+
+        #[test]
+        fn get_path() -> Result<()> {
+            let value = serde_yaml::from_str("{ a: { b: [x, y] } }")?;
+            let success = run_test::<bool>(
+                value,
+                r#"
+                value.get_path("a.b.1").as_str() == "y"
+                && value.get_path("a.c.1").is_null()
+                && value.get_path("a.b.2").is_null()
+                "#,
+            )?;
+            assert!(success);
+            Ok(())
+        }
+
+        #[test]
+        fn as_x_or() -> Result<()> {
+            let success = run_test::<bool>(
+                serde_yaml::Value::Null,
+                r#"
+                value.as_str_or("s") == "s"
+                && value.as_int_or(1) == 1
+                && value.as_float_or(1.5) == 1.5
+                && value.as_bool_or(true)
+                "#,
+            )?;
+            assert!(success);
+            assert!(run_test::<bool>(
+                serde_yaml::Value::Number(2.into()),
+                "value.as_int_or(1) == 2"
+            )?);
+            Ok(())
+        }
+
+        #[test]
+        fn to_dynamic() -> Result<()> {
+            let value = serde_yaml::from_str("{ a: [1, 2.5, text, true, ~] }")?;
+            let success = run_test::<bool>(
+                value,
+                r#"
+                let a = value.to_dynamic().a;
+                a[0] == 1 && a[1] == 2.5 && a[2] == "text" && a[3] && a[4] == ()
+                "#,
+            )?;
+            assert!(success);
+            Ok(())
+        }
+
         fn btree_to_mapping(map: BTreeMap<String, serde_yaml::Value>) -> serde_yaml::Value {
             let mut mapping = serde_yaml::Mapping::new();
             for (k, v) in map {
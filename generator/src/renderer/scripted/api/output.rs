@@ -1,4 +1,5 @@
 use crate::renderer::renderer_config::{IndentChar, ScriptedConfig};
+use crate::renderer::NamedOutput;
 use unindent::unindent as unindent_multiline_str;
 
 pub fn register(engine: &mut rhai::Engine) {
@@ -13,7 +14,9 @@ pub fn register(engine: &mut rhai::Engine) {
         .register_fn("indent", Output::indent)
         .register_fn("unindent", Output::unindent)
         .register_fn("push_scope", Output::push_scope)
-        .register_fn("pop_scope", Output::pop_scope);
+        .register_fn("pop_scope", Output::pop_scope)
+        .register_fn("new_output", Output::new_output)
+        .register_fn("add_file", Output::add_file);
 }
 
 /// NOTE: This API is used in rhai, so it follows rhai rules like always using &mut self and rhai::INT.
@@ -22,6 +25,8 @@ pub struct Output {
     config: ScriptedConfig,
     content: String,
     current_indent: rhai::INT,
+    /// Extra files added with `add_file`, written next to this output.
+    files: Vec<NamedOutput>,
 }
 
 impl Output {
@@ -105,9 +110,30 @@ impl Output {
         self.line("}");
     }
 
+    /// Empty output with the same config, to fill and pass to `add_file`.
+    pub fn new_output(&mut self) -> Output {
+        Output::with_config(self.config.clone())
+    }
+
+    /// Adds `output` as a separate file at `path`, relative to the output directory.
+    pub fn add_file(&mut self, path: &str, output: Output) -> Result<(), Box<rhai::EvalAltResult>> {
+        let named_output = NamedOutput::new(path, output.content).map_err(|e| e.to_string())?;
+        if self.files.iter().any(|x| x.path == named_output.path) {
+            return Err(format!("File '{}' was already added.", path).into());
+        }
+        self.files.push(named_output);
+        // Files added to the nested output are kept too.
+        self.files.extend(output.files);
+        Ok(())
+    }
+
     pub fn to_string(self) -> String {
         self.content
     }
+
+    pub fn into_parts(self) -> (String, Vec<NamedOutput>) {
+        (self.content, self.files)
+    }
 }
 
 #[cfg(test)]
@@ -361,6 +387,46 @@ mod tests {
         }
     }
 
+    mod add_file {
+        use crate::renderer::scripted::api::output::Output;
+        use std::path::PathBuf;
+
+        #[test]
+        fn keeps_content_separate() {
+            let mut output = Output::default();
+            output.append("main");
+            let mut file = output.new_output();
+            file.append("extra");
+            output.add_file("dir/extra.txt", file).unwrap();
+            let (content, files) = output.into_parts();
+            assert_eq!(content, "main");
+            assert_eq!(files[0].path, PathBuf::from("dir/extra.txt"));
+            assert_eq!(files[0].content, "extra");
+        }
+
+        #[test]
+        fn keeps_nested_files() {
+            let mut output = Output::default();
+            let mut file = output.new_output();
+            file.add_file("b.txt", Output::default()).unwrap();
+            output.add_file("a.txt", file).unwrap();
+            assert_eq!(output.into_parts().1.len(), 2);
+        }
+
+        #[test]
+        fn rejects_paths_outside_output() {
+            let mut output = Output::default();
+            assert!(output.add_file("../a.txt", Output::default()).is_err());
+        }
+
+        #[test]
+        fn rejects_duplicates() {
+            let mut output = Output::default();
+            output.add_file("a.txt", Output::default()).unwrap();
+            assert!(output.add_file("./a.txt", Output::default()).is_err());
+        }
+    }
+
     fn scope_config() -> ScriptedConfig {
         ScriptedConfig {
             scope: ScopeConfig {
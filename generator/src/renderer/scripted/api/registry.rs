@@ -0,0 +1,60 @@
+use std::sync::{Arc, RwLock};
+
+use rhai::{Dynamic, Engine};
+
+use crate::renderer::context::{FieldContext, TypeRegistry};
+
+/// Replaced by the renderer with the registry of the descriptor set being rendered.
+pub type SharedTypeRegistry = Arc<RwLock<TypeRegistry>>;
+
+pub fn register(engine: &mut Engine, registry: &SharedTypeRegistry) {
+    let shared = registry.clone();
+    engine.register_fn("lookup_message", move |full_name: &str| {
+        lookup_message(&shared, full_name)
+    });
+    let shared = registry.clone();
+    engine.register_fn("lookup_enum", move |full_name: &str| {
+        lookup_enum(&shared, full_name)
+    });
+    let shared = registry.clone();
+    engine.register_fn("lookup_source_file", move |full_name: &str| {
+        read(&shared)
+            .source_file(full_name)
+            .map(|file| Dynamic::from(file.to_owned()))
+            .unwrap_or(Dynamic::UNIT)
+    });
+    let shared = registry.clone();
+    engine.register_get("resolved_type", move |field: &mut FieldContext| match field
+        .proto_type_name()
+    {
+        None => Dynamic::UNIT,
+        Some(full_name) => {
+            let message = lookup_message(&shared, full_name);
+            if message.is_unit() {
+                lookup_enum(&shared, full_name)
+            } else {
+                message
+            }
+        }
+    });
+}
+
+/// Returns `()` when there's no such message, so scripts can check with `type_of`.
+fn lookup_message(registry: &SharedTypeRegistry, full_name: &str) -> Dynamic {
+    read(registry)
+        .message(full_name)
+        .map(|context| Dynamic::from(context.clone()))
+        .unwrap_or(Dynamic::UNIT)
+}
+
+fn lookup_enum(registry: &SharedTypeRegistry, full_name: &str) -> Dynamic {
+    read(registry)
+        .enum_type(full_name)
+        .map(|context| Dynamic::from(context.clone()))
+        .unwrap_or(Dynamic::UNIT)
+}
+
+fn read(registry: &SharedTypeRegistry) -> std::sync::RwLockReadGuard<TypeRegistry> {
+    // A panic while replacing the registry leaves it usable, so ignore poisoning.
+    registry.read().unwrap_or_else(|err| err.into_inner())
+}
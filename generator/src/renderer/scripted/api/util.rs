@@ -0,0 +1,199 @@
+use std::cmp::Ordering;
+
+use rhai::plugin::*;
+use rhai::{Dynamic, FnPtr, NativeCallContext, INT};
+
+/// Name scripts import the utilities with, e.g. `import "protox/util" as util;`.
+pub const MODULE_NAME: &str = "protox/util";
+
+pub fn module() -> Module {
+    exported_module!(util_api)
+}
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+fn call_key(context: &NativeCallContext, key: &FnPtr, item: &Dynamic) -> ScriptResult<Dynamic> {
+    key.call_within_context(context, (item.clone(),))
+}
+
+/// Ints and floats compare by value, anything else by its string.
+fn compare_keys(a: &Dynamic, b: &Dynamic) -> Ordering {
+    if let (Ok(a), Ok(b)) = (a.as_int(), b.as_int()) {
+        return a.cmp(&b);
+    }
+    if let (Ok(a), Ok(b)) = (a.as_float(), b.as_float()) {
+        return a.partial_cmp(&b).unwrap_or(Ordering::Equal);
+    }
+    a.to_string().cmp(&b.to_string())
+}
+
+fn to_width(width: INT) -> usize {
+    width.max(0) as usize
+}
+
+#[export_module]
+mod util_api {
+    use super::{call_key, compare_keys, to_width, ScriptResult};
+    use crate::renderer::text;
+    use rhai::{Array, Dynamic, FnPtr, Map, NativeCallContext, INT};
+    use std::collections::HashSet;
+
+    /// Word wraps `text` to `width`, keeping existing line breaks.
+    #[rhai_fn(name = "wrap")]
+    pub fn wrap(text: &str, width: INT) -> String {
+        text::wrap_comment(text, to_width(width), "")
+    }
+
+    /// Formats `text` as a comment in `style`, e.g. `//`, `///`, `#` or `/**`.
+    #[rhai_fn(name = "comment")]
+    pub fn comment(text: &str, style: &str) -> String {
+        text::comment(text, style, usize::MAX)
+    }
+
+    /// Formats `text` as a comment in `style`, wrapped so lines fit in `width`.
+    #[rhai_fn(name = "comment")]
+    pub fn comment_wrapped(text: &str, style: &str, width: INT) -> String {
+        text::comment(text, style, to_width(width))
+    }
+
+    /// Indents each non-empty line of `text` by `amount` spaces.
+    #[rhai_fn(name = "indent_block")]
+    pub fn indent_block(text: &str, amount: INT) -> String {
+        text::indent_block(text, &" ".repeat(to_width(amount)))
+    }
+
+    /// Indents each non-empty line of `text` with `indent`, e.g. `"\t"`.
+    #[rhai_fn(name = "indent_block")]
+    pub fn indent_block_with(text: &str, indent: &str) -> String {
+        text::indent_block(text, indent)
+    }
+
+    /// The first of each item with the same string.
+    #[rhai_fn(name = "unique")]
+    pub fn unique(items: Array) -> Array {
+        let mut seen = HashSet::new();
+        items
+            .into_iter()
+            .filter(|item| seen.insert(item.to_string()))
+            .collect()
+    }
+
+    /// The first of each item with the same key, e.g. `util::unique(fields, |f| f.full_type)`.
+    #[rhai_fn(name = "unique", return_raw)]
+    pub fn unique_by(context: NativeCallContext, items: Array, key: FnPtr) -> ScriptResult<Array> {
+        let mut seen = HashSet::new();
+        let mut result = Array::new();
+        for item in items {
+            if seen.insert(call_key(&context, &key, &item)?.to_string()) {
+                result.push(item);
+            }
+        }
+        Ok(result)
+    }
+
+    /// Stable sort of `items` by key, e.g. `util::sort_by(fields, |f| f.number)`.
+    #[rhai_fn(name = "sort_by", return_raw)]
+    pub fn sort_by(context: NativeCallContext, items: Array, key: FnPtr) -> ScriptResult<Array> {
+        let mut keyed = Vec::with_capacity(items.len());
+        for item in items {
+            keyed.push((call_key(&context, &key, &item)?, item));
+        }
+        keyed.sort_by(|(a, _), (b, _)| compare_keys(a, b));
+        Ok(keyed.into_iter().map(|(_, item)| item).collect())
+    }
+
+    /// Map of each key to the items with it, in their original order,
+    /// e.g. `util::group_by(fields, |f| f.type_kind)`.
+    #[rhai_fn(name = "group_by", return_raw)]
+    pub fn group_by(context: NativeCallContext, items: Array, key: FnPtr) -> ScriptResult<Map> {
+        let mut groups = Map::new();
+        for item in items {
+            let key = call_key(&context, &key, &item)?.to_string();
+            groups
+                .entry(key.into())
+                .or_insert_with(|| Dynamic::from(Array::new()))
+                .write_lock::<Array>()
+                .ok_or("group is not an array")?
+                .push(item);
+        }
+        Ok(groups)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use rhai::Engine;
+
+    use crate::renderer::scripted::api::util;
+
+    #[test]
+    fn wrap() -> Result<()> {
+        assert_eq!(run(r#"util::wrap("one two three", 8)"#)?, "one two\nthree");
+        Ok(())
+    }
+
+    #[test]
+    fn comment() -> Result<()> {
+        assert_eq!(run(r##"util::comment("one\ntwo", "#")"##)?, "# one\n# two");
+        assert_eq!(
+            run(r#"util::comment("one two", "/*", 8)"#)?,
+            "/*\n * one\n * two\n */"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn indent_block() -> Result<()> {
+        assert_eq!(run(r#"util::indent_block("a\nb", 2)"#)?, "  a\n  b");
+        assert_eq!(run(r#"util::indent_block("a", "\t")"#)?, "\ta");
+        Ok(())
+    }
+
+    #[test]
+    fn unique() -> Result<()> {
+        assert_eq!(run(r#"util::unique([1, 2, 1, 3, 2]).join(",")"#)?, "1,2,3");
+        assert_eq!(
+            run(r#"util::unique(["a", "bb", "c"], |x| x.len()).join(",")"#)?,
+            "a,bb"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn sort_by() -> Result<()> {
+        assert_eq!(
+            run(r#"util::sort_by(["ccc", "a", "bb", "d"], |x| x.len()).join(",")"#)?,
+            "a,d,bb,ccc"
+        );
+        // Ints are compared by value, not as strings.
+        assert_eq!(
+            run(r#"util::sort_by([10, 9, 100], |x| x).join(",")"#)?,
+            "9,10,100"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn group_by() -> Result<()> {
+        assert_eq!(
+            run(r#"
+                let groups = util::group_by(["a", "bb", "c"], |x| x.len());
+                `${groups["1"].join(",")} ${groups["2"].join(",")}`"#)?,
+            "a,c bb"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn key_errors_are_returned() {
+        assert!(run(r#"util::sort_by([1, 2], |x| x.not_a_method())"#).is_err());
+    }
+
+    fn run(script: &str) -> Result<String> {
+        let mut engine = Engine::new();
+        engine.register_fn("join", crate::renderer::scripted::api::api::array_join);
+        engine.register_static_module("util", util::module().into());
+        Ok(engine.eval::<String>(script)?)
+    }
+}
@@ -309,7 +309,7 @@ mod field_context {
 }
 
 mod metadata_context {
-    use std::collections::HashMap;
+    use std::collections::BTreeMap;
     use std::path::PathBuf;
 
     use anyhow::Result;
@@ -390,7 +390,7 @@ mod metadata_context {
     #[test]
     fn package_files_full() -> Result<()> {
         let mut context = MetadataContext::new();
-        let mut package_files = HashMap::<String, PathBuf>::new();
+        let mut package_files = BTreeMap::<String, PathBuf>::new();
         package_files.insert(
             "some.package.0".to_owned(),
             PathBuf::from("some_file_0.ext"),
@@ -415,7 +415,7 @@ mod metadata_context {
     #[test]
     fn package_file_tree() -> Result<()> {
         let mut context = MetadataContext::new();
-        let mut package_files = HashMap::<String, PathBuf>::new();
+        let mut package_files = BTreeMap::<String, PathBuf>::new();
         package_files.insert("0.1.2".to_owned(), PathBuf::from("file0"));
         package_files.insert("0.1".to_owned(), PathBuf::from("file1"));
         package_files.insert("0.3".to_owned(), PathBuf::from("file2"));
@@ -1,11 +1,13 @@
 use anyhow::Result;
 
 use crate::in_out_generator::InOutGenerator;
+use crate::render::Render;
 use crate::renderer::scripted::renderer::ScriptedRenderer;
 use crate::{Config, InOutConfig};
 
 mod api;
 mod renderer;
+mod stdlib;
 
 #[cfg(test)]
 mod integration_tests;
@@ -14,13 +16,14 @@ pub const SCRIPT_EXT: &'static str = "rhai";
 pub const MAIN_SCRIPT_NAME: &'static str = "main";
 pub const RENDER_FILE_FN_NAME: &'static str = "render_file";
 pub const RENDER_METADATA_FN_NAME: &'static str = "render_metadata";
+pub const RENDER_MESSAGE_FN_NAME: &'static str = "render_message";
+pub const RENDER_ENUM_FN_NAME: &'static str = "render_enum";
+pub const RENDER_ALL_FN_NAME: &'static str = "render_all";
 
 pub fn generate(config: &Config) -> Result<()> {
-    Generator {
-        config,
-        renderer: ScriptedRenderer::new(),
-    }
-    .generate()
+    let mut renderer = ScriptedRenderer::new();
+    renderer.set_module_paths(config.script_module_paths.clone());
+    Generator { config, renderer }.generate()
 }
 
 struct Generator<'a> {
@@ -40,6 +43,12 @@ impl<'a> InOutGenerator<ScriptedRenderer> for Generator<'a> {
         &self.config
     }
 
+    fn load_renderer(&mut self, index: usize, config: &InOutConfig) -> Result<()> {
+        let params = self.config.scripts[index].params.clone();
+        self.renderer.set_params(params);
+        self.renderer.load(&config.input, &config.overlays)
+    }
+
     fn in_out_configs(&self) -> Vec<InOutConfig> {
         self.app_config()
             .scripts
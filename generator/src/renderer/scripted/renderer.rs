@@ -1,41 +1,80 @@
+use std::collections::HashMap;
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLockWriteGuard};
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use log::{debug, info};
-use rhai::module_resolvers::FileModuleResolver;
-use rhai::{Dynamic, Engine, Scope, ScriptFnMetadata, AST};
+use prost_types::FileDescriptorSet;
+use rhai::module_resolvers::{FileModuleResolver, ModuleResolversCollection};
+use rhai::{Dynamic, Engine, EvalAltResult, Scope, ScriptFnMetadata, AST};
 
-use crate::renderer::context::{FileContext, MetadataContext};
+use crate::diagnostic::Diagnostic;
+use crate::renderer::context::{
+    AllContext, EnumContext, FileContext, MessageContext, MetadataContext, TypeRegistry,
+};
+use crate::renderer::scripted::api::globals::{
+    ConfigGlobal, Globals, SharedGlobals, CONFIG_VAR, ENV_VAR,
+};
 use crate::renderer::scripted::api::output::Output;
+use crate::renderer::scripted::api::registry::SharedTypeRegistry;
 use crate::renderer::scripted::{
-    api, MAIN_SCRIPT_NAME, RENDER_FILE_FN_NAME, RENDER_METADATA_FN_NAME, SCRIPT_EXT,
+    api, stdlib, MAIN_SCRIPT_NAME, RENDER_ALL_FN_NAME, RENDER_ENUM_FN_NAME, RENDER_FILE_FN_NAME,
+    RENDER_MESSAGE_FN_NAME, RENDER_METADATA_FN_NAME, SCRIPT_EXT,
 };
-use crate::renderer::{find_existing_config_path, Renderer, RendererConfig};
+use crate::renderer::{find_existing_config_path, NamedOutput, Renderer, RendererConfig};
 use crate::DisplayNormalized;
 
 pub struct ScriptedRenderer {
     engine: Engine,
     main_ast: Option<AST>,
     config: RendererConfig,
+    /// Root the main script and modules were loaded from.
+    root: PathBuf,
+    /// Module search paths used for every load, after those in the config.
+    module_paths: Vec<PathBuf>,
+    /// Directories searched for modules in the last load, starting with `root`.
+    module_roots: Vec<PathBuf>,
+    /// Params used for every load, over those in the config.
+    params: HashMap<String, serde_yaml::Value>,
+    type_registry: SharedTypeRegistry,
+    globals: SharedGlobals,
 }
 
 impl ScriptedRenderer {
     pub fn new() -> Self {
+        let type_registry = SharedTypeRegistry::default();
+        let globals = SharedGlobals::default();
         Self {
-            engine: Self::create_engine(),
+            engine: Self::create_engine(&type_registry),
             main_ast: None,
             config: RendererConfig::default(),
+            root: PathBuf::new(),
+            module_paths: vec![],
+            module_roots: vec![],
+            params: HashMap::new(),
+            type_registry,
+            globals,
         }
     }
 
-    fn create_engine() -> Engine {
+    /// Sets the module search paths for following loads, e.g. from `--script-module-path`.
+    pub fn set_module_paths(&mut self, module_paths: Vec<PathBuf>) {
+        self.module_paths = module_paths;
+    }
+
+    /// Sets the params for following loads, e.g. from `--script-param`.
+    pub fn set_params(&mut self, params: HashMap<String, serde_yaml::Value>) {
+        self.params = params;
+    }
+
+    fn create_engine(type_registry: &SharedTypeRegistry) -> Engine {
         let mut engine = Engine::new();
         engine.on_print(|msg| info!("[script] {}", msg));
         engine.on_debug(|msg, _, pos| debug!("[script] {}: {}", pos, msg));
         engine.set_max_expr_depths(128, 64);
         engine.set_max_operations(0);
-        api::register(&mut engine);
+        api::register(&mut engine, type_registry);
         engine
     }
 
@@ -46,21 +85,63 @@ impl ScriptedRenderer {
         }
     }
 
-    fn render<W: Write>(
-        &self,
-        context: rhai::Dynamic,
-        fn_name: &str,
-        writer: &mut W,
-    ) -> Result<()> {
-        let mut scope = Scope::new();
+    /// Calls `fn_name` with `args` followed by a new `Output`, which the function returns.
+    fn render(&self, fn_name: &str, mut args: Vec<Dynamic>) -> Result<Output> {
+        let mut scope = self.scope();
         let ast = self.main_ast_or_error()?;
         let output = Output::with_config(self.config.scripted.clone());
+        args.push(Dynamic::from(output));
         let result: Output = self
             .engine
-            .call_fn(&mut scope, ast, fn_name, (context, output))
-            .with_context(|| format!("Error returned from script function: {}'", fn_name))?;
-        writer.write(result.to_string().as_bytes())?;
-        Ok(())
+            .call_fn(&mut scope, ast, fn_name, args)
+            .map_err(|err| script_diagnostic(*err, &self.root, &self.module_roots))
+            .with_context(|| format!("Error returned from script function '{}'", fn_name))?;
+        Ok(result)
+    }
+
+    fn has_fn(&self, name: &str) -> bool {
+        match &self.main_ast {
+            None => false,
+            Some(ast) => ast
+                .iter_functions()
+                .any(|f: ScriptFnMetadata| f.name == name),
+        }
+    }
+
+    /// The script directory, then the config's module paths relative to it, then `module_paths`.
+    fn module_roots(&self, input_root: &Path) -> Result<Vec<PathBuf>> {
+        let mut roots = vec![input_root.to_path_buf()];
+        roots.extend(
+            self.config
+                .scripted
+                .module_paths
+                .iter()
+                .map(|path| input_root.join(path)),
+        );
+        roots.extend(self.module_paths.iter().cloned());
+        for root in &roots {
+            if !root.is_dir() {
+                bail!(
+                    "Module path '{}' does not exist or is not a directory.",
+                    root.display_normalized()
+                );
+            }
+        }
+        Ok(roots)
+    }
+
+    /// Scope of each entry point, with `config` and `env` as constants that parameters and
+    /// locals can shadow. Other functions don't see it, so entry points pass them on.
+    fn scope(&self) -> Scope<'static> {
+        let globals = self.globals.read().unwrap_or_else(|err| err.into_inner());
+        let mut scope = Scope::new();
+        scope.push_constant(CONFIG_VAR, globals.config.clone());
+        scope.push_constant(ENV_VAR, globals.env.clone());
+        scope
+    }
+
+    fn write_globals(&self) -> RwLockWriteGuard<'_, Globals> {
+        self.globals.write().unwrap_or_else(|err| err.into_inner())
     }
 
     #[cfg(test)]
@@ -77,12 +158,22 @@ impl ScriptedRenderer {
 impl Renderer for ScriptedRenderer {
     fn load(&mut self, input_root: &Path, overlays: &[PathBuf]) -> Result<()> {
         self.config = Self::load_config(&find_existing_config_path(input_root)?, overlays)?;
-        let resolver = FileModuleResolver::new_with_path_and_extension(input_root, SCRIPT_EXT);
-        self.engine.set_module_resolver(resolver);
-        self.main_ast = Some(compile_file(
-            &mut self.engine,
-            &main_script_path(input_root),
-        )?);
+        self.config.params.extend(self.params.clone());
+        self.module_roots = self.module_roots(input_root)?;
+        let mut resolvers = ModuleResolversCollection::new();
+        for root in &self.module_roots {
+            resolvers.push(FileModuleResolver::new_with_path_and_extension(
+                root, SCRIPT_EXT,
+            ));
+        }
+        // Last, so a script set can override a bundled module with its own.
+        resolvers.push(stdlib::resolver(&self.engine)?);
+        self.engine.set_module_resolver(resolvers);
+        self.root = input_root.to_path_buf();
+        self.main_ast = Some(compile_file(&mut self.engine, input_root)?);
+        let mut globals = self.write_globals();
+        globals.config = ConfigGlobal(Arc::new(self.config.clone()));
+        globals.env.input_dir = input_root.to_path_buf();
         Ok(())
     }
 
@@ -94,40 +185,169 @@ impl Renderer for ScriptedRenderer {
         &self.config
     }
 
+    fn load_output_path(&self, output_path: &Path) -> Result<()> {
+        self.write_globals().env.output_dir = output_path.to_path_buf();
+        Ok(())
+    }
+
+    fn load_type_registry(&self, descriptor_set: &FileDescriptorSet) -> Result<()> {
+        let registry = TypeRegistry::new(descriptor_set, &self.config)?;
+        *self
+            .type_registry
+            .write()
+            .unwrap_or_else(|err| err.into_inner()) = registry;
+        Ok(())
+    }
+
     fn has_metadata(&self) -> bool {
-        if let Some(ast) = &self.main_ast {
-            return ast
-                .iter_functions()
-                .find(|f: &ScriptFnMetadata| f.name == RENDER_METADATA_FN_NAME)
-                .is_some();
-        }
-        false
+        self.has_fn(RENDER_METADATA_FN_NAME)
     }
 
     fn render_metadata<W: Write>(&self, context: MetadataContext, writer: &mut W) -> Result<()> {
-        self.render(Dynamic::from(context), RENDER_METADATA_FN_NAME, writer)
+        let output = self.render(RENDER_METADATA_FN_NAME, vec![Dynamic::from(context)])?;
+        write_without_files(output, RENDER_METADATA_FN_NAME, writer)
+    }
+
+    fn has_file(&self) -> bool {
+        self.has_fn(RENDER_FILE_FN_NAME)
+    }
+
+    fn has_message(&self) -> bool {
+        self.has_fn(RENDER_MESSAGE_FN_NAME)
+    }
+
+    fn has_enum(&self) -> bool {
+        self.has_fn(RENDER_ENUM_FN_NAME)
+    }
+
+    fn has_all(&self) -> bool {
+        self.has_fn(RENDER_ALL_FN_NAME)
+    }
+
+    fn render_message<W: Write>(
+        &self,
+        context: &MessageContext,
+        file: &FileContext,
+        writer: &mut W,
+    ) -> Result<()> {
+        let args = vec![Dynamic::from(context.clone()), Dynamic::from(file.clone())];
+        let output = self.render(RENDER_MESSAGE_FN_NAME, args)?;
+        write_without_files(output, RENDER_MESSAGE_FN_NAME, writer)
+    }
+
+    fn render_enum<W: Write>(
+        &self,
+        context: &EnumContext,
+        file: &FileContext,
+        writer: &mut W,
+    ) -> Result<()> {
+        let args = vec![Dynamic::from(context.clone()), Dynamic::from(file.clone())];
+        let output = self.render(RENDER_ENUM_FN_NAME, args)?;
+        write_without_files(output, RENDER_ENUM_FN_NAME, writer)
+    }
+
+    fn render_all<W: Write>(&self, context: AllContext, writer: &mut W) -> Result<()> {
+        let output = self.render(RENDER_ALL_FN_NAME, vec![Dynamic::from(context)])?;
+        write_without_files(output, RENDER_ALL_FN_NAME, writer)
     }
 
     fn render_file<W: Write>(&self, context: FileContext, writer: &mut W) -> Result<()> {
-        self.render(Dynamic::from(context), RENDER_FILE_FN_NAME, writer)
+        if !self.render_file_outputs(context, writer)?.is_empty() {
+            bail!("Files added with `add_file` are only written when rendering to a directory.");
+        }
+        Ok(())
+    }
+
+    fn render_file_outputs<W: Write>(
+        &self,
+        context: FileContext,
+        writer: &mut W,
+    ) -> Result<Vec<NamedOutput>> {
+        let (content, files) = self
+            .render(RENDER_FILE_FN_NAME, vec![Dynamic::from(context)])?
+            .into_parts();
+        writer.write_all(content.as_bytes())?;
+        Ok(files)
     }
 }
 
+fn write_without_files<W: Write>(output: Output, fn_name: &str, writer: &mut W) -> Result<()> {
+    let (content, files) = output.into_parts();
+    if !files.is_empty() {
+        bail!(
+            "`add_file` is only supported in `{}`, not `{}`.",
+            RENDER_FILE_FN_NAME,
+            fn_name
+        );
+    }
+    writer.write_all(content.as_bytes())?;
+    Ok(())
+}
+
 fn main_script_path(root: &Path) -> PathBuf {
     root.join(MAIN_SCRIPT_NAME).with_extension(SCRIPT_EXT)
 }
 
-fn compile_file(engine: &mut rhai::Engine, path: &Path) -> Result<AST> {
-    engine
-        .compile_file(path.to_path_buf())
-        .with_context(|| format!("Error compiling script: {}", path.display_normalized()))
+fn compile_file(engine: &mut rhai::Engine, root: &Path) -> Result<AST> {
+    let path = main_script_path(root);
+    let mut ast = engine
+        .compile_file(path.clone())
+        .map_err(|err| script_diagnostic(*err, root, &[]))
+        .with_context(|| format!("Error compiling script: {}", path.display_normalized()))?;
+    // Errors raised by functions in the main script are then attributed to it by rhai, the
+    // same as for imported modules.
+    ast.set_source(path.display_normalized());
+    Ok(ast)
+}
+
+/// Points at the innermost location of a script error, following function calls and imports
+/// into the module that raised it.
+fn script_diagnostic(mut err: EvalAltResult, root: &Path, module_roots: &[PathBuf]) -> Diagnostic {
+    let mut path = main_script_path(root);
+    loop {
+        err = match err {
+            EvalAltResult::ErrorInFunctionCall(_, source, inner, _) => {
+                if !source.is_empty() {
+                    path = module_path(root, module_roots, &source);
+                }
+                *inner
+            }
+            EvalAltResult::ErrorInModule(name, inner, _) => {
+                path = module_path(root, module_roots, &name);
+                *inner
+            }
+            _ => break,
+        };
+    }
+    let position = err.take_position();
+    Diagnostic::error(err.to_string())
+        .with_path(path)
+        .with_position(position.line(), position.position())
+}
+
+/// Modules are named by their import path, relative to the first of `module_roots` containing
+/// them and without an extension. Bundled modules aren't files, so they fall back to `root`.
+fn module_path(root: &Path, module_roots: &[PathBuf], name: &str) -> PathBuf {
+    let path = |root: &Path| {
+        let path = root.join(name);
+        match path.extension() {
+            None => path.with_extension(SCRIPT_EXT),
+            Some(_) => path,
+        }
+    };
+    module_roots
+        .iter()
+        .map(|module_root| path(module_root))
+        .find(|path| path.exists())
+        .unwrap_or_else(|| path(root))
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::renderer::case::Case;
     use crate::renderer::context::{FileContext, MetadataContext};
     use anyhow::Result;
-    use prost_types::FileDescriptorProto;
+    use prost_types::{DescriptorProto, FileDescriptorProto};
     use std::path::PathBuf;
 
     use crate::renderer::scripted::renderer::ScriptedRenderer;
@@ -173,6 +393,57 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn proto_names_and_case() -> Result<()> {
+        let mut config = RendererConfig::default();
+        config.case_config.message_name = Case::UpperSnake;
+        let file = &FileDescriptorProto {
+            name: Some("file.proto".to_owned()),
+            package: Some("pkg".to_owned()),
+            message_type: vec![DescriptorProto {
+                name: Some("MyMessage".to_owned()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let context = FileContext::new(file, &config)?;
+        let mut renderer = ScriptedRenderer::new();
+        renderer.load_test_script(
+            r#"fn render_file(f, o) {
+                let m = f.messages[0];
+                o.append(`${m.name} ${m.proto_name} ${m.full_proto_name} `);
+                o.append(to_case(m.proto_name, "lower-kebab-case"));
+                o
+            }"#,
+        )?;
+
+        let mut output = Vec::new();
+        renderer.render_file(context, &mut output)?;
+        assert_eq!(
+            String::from_utf8(output)?,
+            "MY_MESSAGE MyMessage pkg.MyMessage my-message"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn to_case_unknown_errors() -> Result<()> {
+        let file = &FileDescriptorProto {
+            name: Some("file.proto".to_owned()),
+            ..Default::default()
+        };
+        let context = FileContext::new(file, &RendererConfig::default())?;
+        let mut renderer = ScriptedRenderer::new();
+        renderer.load_test_script(
+            r#"fn render_file(f, o) {
+                o.append(to_case("name", "Sponge"));
+                o
+            }"#,
+        )?;
+        assert!(renderer.render_file(context, &mut Vec::new()).is_err());
+        Ok(())
+    }
+
     #[test]
     fn has_metadata() -> Result<()> {
         let mut renderer = ScriptedRenderer::new();
@@ -183,4 +454,627 @@ mod tests {
         assert!(renderer.has_metadata());
         Ok(())
     }
+
+    mod named_outputs {
+        use crate::render::Render;
+        use crate::renderer::scripted::renderer::ScriptedRenderer;
+        use anyhow::Result;
+        use prost_types::{DescriptorProto, FileDescriptorProto, FileDescriptorSet};
+        use std::fs;
+        use tempfile::tempdir;
+
+        #[test]
+        fn one_file_per_message() -> Result<()> {
+            let output = render(
+                r#"fn render_file(f, o) {
+                    for m in f.messages {
+                        let file = o.new_output();
+                        file.append(m.name);
+                        o.add_file(`${f.package_}/${m.name}.txt`, file);
+                    }
+                    o
+                }"#,
+            )?;
+            let root = output.path();
+            assert_eq!(
+                fs::read_to_string(root.join("pkg/Msg0.txt"))?.trim_start_matches(|c| c != 'M'),
+                "Msg0"
+            );
+            assert!(root.join("pkg/Msg1.txt").exists());
+            // Nothing was written to the proto's own output.
+            assert!(!root.join("file.txt").exists());
+            Ok(())
+        }
+
+        #[test]
+        fn keeps_main_output_with_content() -> Result<()> {
+            let output = render(
+                r#"fn render_file(f, o) {
+                    o.append("main");
+                    o.add_file("extra.txt", o.new_output());
+                    o
+                }"#,
+            )?;
+            assert!(output.path().join("file.txt").exists());
+            assert!(output.path().join("extra.txt").exists());
+            Ok(())
+        }
+
+        #[test]
+        fn duplicate_across_files_errors() -> Result<()> {
+            let script = r#"fn render_file(f, o) {
+                o.add_file("same.txt", o.new_output());
+                o
+            }"#;
+            let mut renderer = ScriptedRenderer::new();
+            renderer.load_test_script(script)?;
+            let output = tempdir()?;
+            let mut descriptor_set = descriptor_set();
+            descriptor_set.file.push(FileDescriptorProto {
+                name: Some("other.proto".to_owned()),
+                ..Default::default()
+            });
+            assert!(Render::render(&renderer, &descriptor_set, output.path()).is_err());
+            Ok(())
+        }
+
+        fn render(script: &str) -> Result<tempfile::TempDir> {
+            let mut renderer = ScriptedRenderer::new();
+            renderer.config.file_extension = "txt".to_owned();
+            renderer.config.generated_header = Some(vec![]);
+            renderer.load_test_script(script)?;
+            let output = tempdir()?;
+            Render::render(&renderer, &descriptor_set(), output.path())?;
+            Ok(output)
+        }
+
+        fn descriptor_set() -> FileDescriptorSet {
+            let message = |name: &str| DescriptorProto {
+                name: Some(name.to_owned()),
+                ..Default::default()
+            };
+            FileDescriptorSet {
+                file: vec![FileDescriptorProto {
+                    name: Some("file.proto".to_owned()),
+                    package: Some("pkg".to_owned()),
+                    message_type: vec![message("Msg0"), message("Msg1")],
+                    ..Default::default()
+                }],
+            }
+        }
+    }
+
+    mod types {
+        use crate::render::Render;
+        use crate::renderer::scripted::renderer::ScriptedRenderer;
+        use anyhow::Result;
+        use prost_types::{
+            DescriptorProto, EnumDescriptorProto, FileDescriptorProto, FileDescriptorSet,
+        };
+        use std::fs;
+        use tempfile::{tempdir, TempDir};
+
+        #[test]
+        fn one_file_per_type() -> Result<()> {
+            let mut renderer = ScriptedRenderer::new();
+            renderer.config.file_extension = "java".to_owned();
+            renderer.config.generated_header = Some(vec![]);
+            renderer.config.type_file_name_pattern = Some("{package_dir}/{name}.{ext}".to_owned());
+            renderer.load_test_script(
+                r#"
+                fn render_message(m, f, o) {
+                    o.append(`package ${f.package_}; class ${m.name}`);
+                    o
+                }
+                fn render_enum(e, f, o) {
+                    o.append(`enum ${e.name}`);
+                    o
+                }"#,
+            )?;
+            let output = tempdir()?;
+            Render::render(&renderer, &descriptor_set(), output.path())?;
+            let root = output.path();
+            assert_eq!(
+                fs::read_to_string(root.join("root/sub/Msg.java"))?,
+                "package root.sub; class Msg"
+            );
+            assert_eq!(
+                fs::read_to_string(root.join("root/sub/Kind.java"))?,
+                "enum Kind"
+            );
+            // There's no render_file, so no per-file output.
+            assert_eq!(fs::read_dir(root)?.count(), 1);
+            Ok(())
+        }
+
+        #[test]
+        fn no_package_renders_to_output_root() -> Result<()> {
+            let output = render_types(None, ".")?;
+            assert!(output.path().join("Msg.java").exists());
+            assert!(output.path().join("Kind.java").exists());
+            Ok(())
+        }
+
+        #[test]
+        fn package_dir_ignores_package_separator() -> Result<()> {
+            let output = render_types(Some("root.sub"), "::")?;
+            assert!(output.path().join("root/sub/Msg.java").exists());
+            assert!(output.path().join("root/sub/Kind.java").exists());
+            Ok(())
+        }
+
+        fn render_types(package: Option<&str>, package_separator: &str) -> Result<TempDir> {
+            let mut renderer = ScriptedRenderer::new();
+            renderer.config.file_extension = "java".to_owned();
+            renderer.config.package_separator = package_separator.to_owned();
+            renderer.config.type_file_name_pattern = Some("{package_dir}/{name}.{ext}".to_owned());
+            renderer.load_test_script(
+                r#"
+                fn render_message(m, f, o) { o }
+                fn render_enum(e, f, o) { o }"#,
+            )?;
+            let mut descriptor_set = descriptor_set();
+            descriptor_set.file[0].package = package.map(str::to_owned);
+            let output = tempdir()?;
+            Render::render(&renderer, &descriptor_set, output.path())?;
+            Ok(output)
+        }
+
+        #[test]
+        fn disabled_without_pattern() -> Result<()> {
+            let mut renderer = ScriptedRenderer::new();
+            renderer.load_test_script(
+                r#"
+                fn render_file(f, o) { o }
+                fn render_message(m, f, o) { o }"#,
+            )?;
+            let output = tempdir()?;
+            Render::render(&renderer, &descriptor_set(), output.path())?;
+            assert_eq!(fs::read_dir(output.path())?.count(), 1);
+            Ok(())
+        }
+
+        fn descriptor_set() -> FileDescriptorSet {
+            FileDescriptorSet {
+                file: vec![FileDescriptorProto {
+                    name: Some("file.proto".to_owned()),
+                    package: Some("root.sub".to_owned()),
+                    message_type: vec![DescriptorProto {
+                        name: Some("Msg".to_owned()),
+                        ..Default::default()
+                    }],
+                    enum_type: vec![EnumDescriptorProto {
+                        name: Some("Kind".to_owned()),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }],
+            }
+        }
+    }
+
+    mod all {
+        use crate::render::Render;
+        use crate::renderer::scripted::renderer::ScriptedRenderer;
+        use anyhow::Result;
+        use prost_types::{FileDescriptorProto, FileDescriptorSet};
+        use std::fs;
+        use tempfile::tempdir;
+
+        #[test]
+        fn renders_all_files_at_once() -> Result<()> {
+            let mut renderer = ScriptedRenderer::new();
+            renderer.config.file_extension = "txt".to_owned();
+            renderer.config.generated_header = Some(vec![]);
+            renderer.load_test_script(
+                r#"
+                fn render_file(f, o) { o }
+                fn render_all(all, o) {
+                    for f in all.files {
+                        o.line(f.source_file);
+                    }
+                    for key in all.package_tree.keys() {
+                        let node = all.package_tree[key];
+                        o.line(`${node.package_}: ${node.files.len()} ${node.children.len()}`);
+                    }
+                    o
+                }"#,
+            )?;
+            let file = |name: &str, package: &str| FileDescriptorProto {
+                name: Some(name.to_owned()),
+                package: Some(package.to_owned()),
+                ..Default::default()
+            };
+            let descriptor_set = FileDescriptorSet {
+                file: vec![file("a.proto", "root"), file("b.proto", "root.sub")],
+            };
+            let output = tempdir()?;
+            Render::render(&renderer, &descriptor_set, output.path())?;
+            assert_eq!(
+                fs::read_to_string(output.path().join("all.txt"))?,
+                "a.proto\nb.proto\nroot: 1 1\n"
+            );
+            // Per-file outputs are still rendered.
+            assert!(output.path().join("a.txt").exists());
+            Ok(())
+        }
+    }
+
+    mod type_registry {
+        use crate::render::Render;
+        use crate::renderer::scripted::renderer::ScriptedRenderer;
+        use crate::renderer::tests::{fake_field, fake_file, fake_message};
+        use anyhow::Result;
+        use prost_types::{EnumDescriptorProto, FileDescriptorSet};
+        use std::fs;
+        use tempfile::tempdir;
+
+        #[test]
+        fn resolves_types_across_files() -> Result<()> {
+            let mut renderer = ScriptedRenderer::new();
+            renderer.config.file_extension = "txt".to_owned();
+            renderer.config.generated_header = Some(vec![]);
+            renderer.config.ignored_files = vec!["dep.proto".to_owned()];
+            renderer.load_test_script(
+                r#"
+                fn render_file(file, o) {
+                    for field in file.messages[0].fields {
+                        let resolved = field.resolved_type;
+                        o.line(`${field.name}: ${type_of(resolved)}`);
+                    }
+                    let dep = lookup_message(".dep.Dep");
+                    o.line(`${dep.name} ${dep.fields.len()} ${lookup_source_file("dep.Dep")}`);
+                    o.line(`${lookup_enum("dep.Kind").name}`);
+                    o.line(`${type_of(lookup_message("dep.Kind"))}`);
+                    o
+                }"#,
+            )?;
+            let mut dep = fake_file(
+                "dep.proto",
+                vec![EnumDescriptorProto {
+                    name: Some("Kind".to_owned()),
+                    ..Default::default()
+                }],
+                vec![fake_message("Dep", vec![fake_field("a", "int32")])],
+            );
+            dep.package = Some("dep".to_owned());
+            let main = fake_file(
+                "main.proto",
+                vec![],
+                vec![fake_message(
+                    "Main",
+                    vec![
+                        fake_field("dep", ".dep.Dep"),
+                        fake_field("kind", ".dep.Kind"),
+                        fake_field("other", ".missing.Type"),
+                    ],
+                )],
+            );
+            let descriptor_set = FileDescriptorSet {
+                file: vec![dep, main],
+            };
+            let output = tempdir()?;
+            Render::render(&renderer, &descriptor_set, output.path())?;
+            assert_eq!(
+                fs::read_to_string(output.path().join("main.txt"))?,
+                "dep: MessageContext\nkind: EnumContext\nother: ()\nDep 1 dep.proto\nKind\n()\n"
+            );
+            Ok(())
+        }
+    }
+
+    mod diagnostics {
+        use crate::diagnostic::Diagnostic;
+        use crate::renderer::context::FileContext;
+        use crate::renderer::scripted::renderer::ScriptedRenderer;
+        use crate::renderer::{Renderer, RendererConfig};
+        use anyhow::Result;
+        use prost_types::FileDescriptorProto;
+        use std::fs;
+        use std::path::Path;
+        use tempfile::tempdir;
+
+        #[test]
+        fn parse_error() -> Result<()> {
+            let dir = tempdir()?;
+            let err = load(dir.path(), "fn render_file(f, o) {\n    let = 1;\n}")
+                .err()
+                .unwrap();
+            let diagnostic = &Diagnostic::collect(&err)[0];
+            assert_eq!(diagnostic.path, Some(dir.path().join("main.rhai")));
+            assert_eq!(diagnostic.line, Some(2));
+            Ok(())
+        }
+
+        #[test]
+        fn runtime_error_in_module() -> Result<()> {
+            let dir = tempdir()?;
+            fs::write(
+                dir.path().join("util.rhai"),
+                "fn fail() {\n    let x = 1;\n    x.not_a_method()\n}",
+            )?;
+            let renderer = load(
+                dir.path(),
+                "import \"util\" as util;\nfn render_file(f, o) {\n    util::fail()\n}",
+            )?;
+            let file = FileDescriptorProto {
+                name: Some("file.proto".to_owned()),
+                ..Default::default()
+            };
+            let context = FileContext::new(&file, renderer.config())?;
+            let err = renderer
+                .render_file(context, &mut Vec::new())
+                .err()
+                .unwrap();
+            let diagnostic = &Diagnostic::collect(&err)[0];
+            assert_eq!(diagnostic.path, Some(dir.path().join("util.rhai")));
+            assert_eq!(diagnostic.line, Some(3));
+            assert_eq!(diagnostic.column, Some(7));
+            assert!(diagnostic.message.contains("not_a_method"));
+            Ok(())
+        }
+
+        fn load(root: &Path, main: &str) -> Result<ScriptedRenderer> {
+            fs::write(
+                root.join("config.json"),
+                serde_json::to_string(&RendererConfig::default())?,
+            )?;
+            fs::write(root.join("main.rhai"), main)?;
+            let mut renderer = ScriptedRenderer::new();
+            renderer.load(root, &[])?;
+            Ok(renderer)
+        }
+    }
+
+    mod modules {
+        use crate::renderer::context::FileContext;
+        use crate::renderer::scripted::renderer::ScriptedRenderer;
+        use crate::renderer::tests::{fake_field, fake_file, fake_message};
+        use crate::renderer::{Renderer, RendererConfig};
+        use anyhow::Result;
+        use prost_types::field_descriptor_proto::{Label, Type};
+        use std::fs;
+        use std::path::{Path, PathBuf};
+        use tempfile::tempdir;
+
+        const MAIN: &str = r#"
+            import "common" as common;
+            fn render_file(f, o) {
+                o.append(common::name());
+                o
+            }"#;
+
+        #[test]
+        fn config_module_path() -> Result<()> {
+            let dir = tempdir()?;
+            let scripts = dir.path().join("scripts");
+            write_module(&dir.path().join("shared"), "config")?;
+            let mut config = RendererConfig::default();
+            config.scripted.module_paths = vec![PathBuf::from("../shared")];
+            let renderer = load(&scripts, &config, MAIN, vec![])?;
+            assert_eq!(render(&renderer)?, "config");
+            Ok(())
+        }
+
+        #[test]
+        fn renderer_module_path() -> Result<()> {
+            let dir = tempdir()?;
+            let shared = dir.path().join("shared");
+            write_module(&shared, "cli")?;
+            let renderer = load(
+                &dir.path().join("scripts"),
+                &RendererConfig::default(),
+                MAIN,
+                vec![shared],
+            )?;
+            assert_eq!(render(&renderer)?, "cli");
+            Ok(())
+        }
+
+        #[test]
+        fn script_dir_is_searched_first() -> Result<()> {
+            let dir = tempdir()?;
+            let scripts = dir.path().join("scripts");
+            let shared = dir.path().join("shared");
+            write_module(&scripts, "local")?;
+            write_module(&shared, "cli")?;
+            let renderer = load(&scripts, &RendererConfig::default(), MAIN, vec![shared])?;
+            assert_eq!(render(&renderer)?, "local");
+            Ok(())
+        }
+
+        #[test]
+        fn missing_module_path_errors() -> Result<()> {
+            let dir = tempdir()?;
+            let mut config = RendererConfig::default();
+            config.scripted.module_paths = vec![PathBuf::from("missing")];
+            assert!(load(dir.path(), &config, MAIN, vec![]).is_err());
+            Ok(())
+        }
+
+        #[test]
+        fn bundled_modules() -> Result<()> {
+            let dir = tempdir()?;
+            let renderer = load(
+                dir.path(),
+                &RendererConfig::default(),
+                r#"
+                import "protox/fields" as fields;
+                import "protox/text" as text;
+                import "protox/util" as util;
+                fn render_file(f, o) {
+                    let all = f.messages[0].fields;
+                    o.line(fields::names(fields::of_kind(all, "enum")).join(","));
+                    o.line(fields::names(fields::repeated(all)).join(","));
+                    o.line(fields::names(util::sort_by(all, |f| if f.is_array { 0 } else { 1 })).join(","));
+                    o.line(text::quote(`a "b" \c`));
+                    o.append(text::prefix_lines("x\ny", "// "));
+                    o
+                }"#,
+                vec![],
+            )?;
+            let mut repeated = fake_field("list", "string");
+            repeated.label = Some(Label::Repeated as i32);
+            let mut file = fake_file(
+                "file.proto",
+                vec![],
+                vec![fake_message(
+                    "Msg",
+                    vec![fake_field("kind", ".Kind"), repeated],
+                )],
+            );
+            file.message_type[0].field[0].r#type = Some(Type::Enum as i32);
+            let context = FileContext::new(&file, renderer.config())?;
+            let mut output = Vec::new();
+            renderer.render_file(context, &mut output)?;
+            assert_eq!(
+                String::from_utf8(output)?,
+                "kind\nlist\nlist,kind\n\"a \\\"b\\\" \\\\c\"\n// x\n// y"
+            );
+            Ok(())
+        }
+
+        fn write_module(dir: &Path, name: &str) -> Result<()> {
+            fs::create_dir_all(dir)?;
+            fs::write(
+                dir.join("common.rhai"),
+                format!("fn name() {{ \"{}\" }}", name),
+            )?;
+            Ok(())
+        }
+
+        fn load(
+            root: &Path,
+            config: &RendererConfig,
+            main: &str,
+            module_paths: Vec<PathBuf>,
+        ) -> Result<ScriptedRenderer> {
+            fs::create_dir_all(root)?;
+            fs::write(root.join("config.json"), serde_json::to_string(config)?)?;
+            fs::write(root.join("main.rhai"), main)?;
+            let mut renderer = ScriptedRenderer::new();
+            renderer.set_module_paths(module_paths);
+            renderer.load(root, &[])?;
+            Ok(renderer)
+        }
+
+        fn render(renderer: &ScriptedRenderer) -> Result<String> {
+            let file = fake_file("file.proto", vec![], vec![]);
+            let context = FileContext::new(&file, renderer.config())?;
+            let mut output = Vec::new();
+            renderer.render_file(context, &mut output)?;
+            Ok(String::from_utf8(output)?)
+        }
+    }
+
+    mod globals {
+        use crate::render::Render;
+        use crate::renderer::scripted::renderer::ScriptedRenderer;
+        use crate::renderer::tests::fake_file_empty;
+        use crate::renderer::RendererConfig;
+        use crate::DisplayNormalized;
+        use anyhow::Result;
+        use prost_types::FileDescriptorSet;
+        use std::collections::HashMap;
+        use std::fs;
+        use std::path::Path;
+        use tempfile::tempdir;
+
+        #[test]
+        fn config_and_env() -> Result<()> {
+            let dir = tempdir()?;
+            let mut config = RendererConfig::default();
+            config.file_extension = "txt".to_owned();
+            config.generated_header = Some(vec![]);
+            config.params.insert("variant".to_owned(), "full".into());
+            config.params.insert("count".to_owned(), 1.into());
+            let mut params = HashMap::new();
+            params.insert("variant".to_owned(), "lite".into());
+            let output = render(
+                dir.path(),
+                &config,
+                params,
+                r#"
+                fn render_file(f, o) {
+                    o.line(`${config.file_extension} ${config.package_separator}`);
+                    o.line(`${config.type_config["int32"]}`);
+                    o.line(`${config.params.variant} ${config.params.count + 1}`);
+                    o.line(`${env.input_dir} ${env.output_dir}`);
+                    o.append(`${env.protox_version.len() > 0}`);
+                    o
+                }"#,
+            )?;
+            assert_eq!(
+                output,
+                format!(
+                    "txt .\nint32\nlite 2\n{} {}\ntrue",
+                    dir.path().join("input").display_normalized(),
+                    dir.path().join("output").display_normalized(),
+                )
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn locals_take_precedence() -> Result<()> {
+            let dir = tempdir()?;
+            let mut config = RendererConfig::default();
+            config.file_extension = "txt".to_owned();
+            config.generated_header = Some(vec![]);
+            let output = render(
+                dir.path(),
+                &config,
+                HashMap::new(),
+                r#"
+                fn helper(config) { config }
+                fn render_file(f, o) {
+                    let env = "local";
+                    o.append(`${helper("param")} ${env}`);
+                    o
+                }"#,
+            )?;
+            assert_eq!(output, "param local");
+            Ok(())
+        }
+
+        #[test]
+        fn config_is_read_only() -> Result<()> {
+            let dir = tempdir()?;
+            let mut config = RendererConfig::default();
+            config.file_extension = "txt".to_owned();
+            let result = render(
+                dir.path(),
+                &config,
+                HashMap::new(),
+                r#"
+                fn render_file(f, o) {
+                    config = 5;
+                    o
+                }"#,
+            );
+            assert!(result.is_err());
+            Ok(())
+        }
+
+        fn render(
+            root: &Path,
+            config: &RendererConfig,
+            params: HashMap<String, serde_yaml::Value>,
+            main: &str,
+        ) -> Result<String> {
+            let input = root.join("input");
+            let output = root.join("output");
+            fs::create_dir_all(&input)?;
+            fs::write(input.join("config.json"), serde_json::to_string(config)?)?;
+            fs::write(input.join("main.rhai"), main)?;
+            let mut renderer = ScriptedRenderer::new();
+            renderer.set_params(params);
+            Render::load(&mut renderer, &input, &[])?;
+            let descriptor_set = FileDescriptorSet {
+                file: vec![fake_file_empty("file.proto")],
+            };
+            Render::render(&renderer, &descriptor_set, &output)?;
+            Ok(fs::read_to_string(output.join("file.txt"))?)
+        }
+    }
 }
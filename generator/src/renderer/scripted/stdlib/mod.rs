@@ -0,0 +1,34 @@
+use anyhow::{anyhow, Context, Result};
+use rhai::module_resolvers::StaticModuleResolver;
+use rhai::{Engine, Module, Scope};
+
+use crate::renderer::scripted::api::util;
+
+/// Prefix of the import paths of the bundled modules, e.g. `import "protox/fields"`.
+pub const PREFIX: &str = "protox/";
+
+/// Rhai modules bundled with protox, by name without the prefix.
+const MODULES: &[(&str, &str)] = &[
+    ("fields", include_str!("fields.rhai")),
+    ("text", include_str!("text.rhai")),
+];
+
+/// Compiles the bundled modules with `engine`, so they can use the same api as the scripts
+/// importing them.
+pub fn resolver(engine: &Engine) -> Result<StaticModuleResolver> {
+    let mut resolver = StaticModuleResolver::new();
+    resolver.insert(util::MODULE_NAME, util::module());
+    for (name, source) in MODULES {
+        let path = format!("{}{}", PREFIX, name);
+        let module = compile(engine, &path, source)
+            .with_context(|| format!("Error compiling bundled module '{}'", path))?;
+        resolver.insert(path, module);
+    }
+    Ok(resolver)
+}
+
+fn compile(engine: &Engine, path: &str, source: &str) -> Result<Module> {
+    let mut ast = engine.compile(source)?;
+    ast.set_source(path);
+    Module::eval_ast_as_new(Scope::new(), &ast, engine).map_err(|err| anyhow!("{}", err))
+}
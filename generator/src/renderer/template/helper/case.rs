@@ -0,0 +1,44 @@
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, JsonValue, RenderContext, RenderError, ScopedJson,
+};
+
+use crate::renderer::case::Case;
+use crate::renderer::template::helper::params;
+
+/// `{{case value "UpperCamel"}}` changes the case of `value`, where the case is any name accepted
+/// by case config.
+#[derive(Clone, Copy)]
+pub struct ChangeCase;
+
+impl HelperDef for ChangeCase {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'reg, 'rc>, RenderError> {
+        let value = params::str_param(h, 0, "value")?;
+        let case = params::str_param(h, 1, "case")?
+            .parse::<Case>()
+            .map_err(|err| params::error(h, err.to_string()))?;
+        Ok(JsonValue::String(case.rename(value)).into())
+    }
+}
+
+/// A helper for a single case, e.g. `{{upper_camel value}}`.
+#[derive(Clone, Copy)]
+pub struct ToCase(pub Case);
+
+impl HelperDef for ToCase {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'reg, 'rc>, RenderError> {
+        let value = params::str_param(h, 0, "value")?;
+        Ok(JsonValue::String(self.0.rename(value)).into())
+    }
+}
@@ -0,0 +1,48 @@
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, JsonValue, RenderContext, RenderError, ScopedJson,
+};
+
+use crate::renderer::template::helper::params;
+
+/// `{{first array}}` is the first item, or null if the array is empty. Useful as a subexpression,
+/// e.g. `{{#with (first fields)}}`.
+#[derive(Clone, Copy)]
+pub struct First;
+
+/// `{{last array}}` is the last item, or null if the array is empty.
+#[derive(Clone, Copy)]
+pub struct Last;
+
+impl HelperDef for First {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'reg, 'rc>, RenderError> {
+        Ok(item(array(h)?.first()))
+    }
+}
+
+impl HelperDef for Last {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'reg, 'rc>, RenderError> {
+        Ok(item(array(h)?.last()))
+    }
+}
+
+fn array<'a>(h: &'a Helper) -> Result<&'a Vec<JsonValue>, RenderError> {
+    params::param(h, 0, "array")?
+        .as_array()
+        .ok_or_else(|| params::error(h, "param 'array' is not an array"))
+}
+
+fn item<'reg, 'rc>(item: Option<&JsonValue>) -> ScopedJson<'reg, 'rc> {
+    item.cloned().unwrap_or(JsonValue::Null).into()
+}
@@ -1,5 +1,47 @@
+use handlebars::Handlebars;
+
+use crate::renderer::case::CASES;
+use crate::renderer::RendererConfig;
+
+mod case;
+mod collections;
 mod if_equals;
 mod indent;
+mod overlay;
+mod params;
+mod strings;
+mod variables;
+
+pub use variables::clear as clear_variables;
+
+use case::{ChangeCase, ToCase};
+use collections::{First, Last};
+use if_equals::IfEquals;
+use indent::Indent;
+use overlay::LookupOverlay;
+use strings::{Join, Pad, Replace, Trim, WrapComment};
+use variables::{Get, Set};
 
-pub use if_equals::IfEquals;
-pub use indent::Indent;
+/// Registers all protox helpers. Handlebars' built-in helpers, e.g. `eq`, `and` and `len`, are
+/// always available too.
+pub fn register(hbs: &mut Handlebars, config: &RendererConfig) {
+    hbs.register_helper("indent", Box::new(Indent));
+    hbs.register_helper("if_equals", Box::new(IfEquals));
+    hbs.register_helper("case", Box::new(ChangeCase));
+    for (name, case) in CASES {
+        hbs.register_helper(name, Box::new(ToCase(*case)));
+    }
+    hbs.register_helper("join", Box::new(Join));
+    hbs.register_helper("replace", Box::new(Replace));
+    hbs.register_helper("trim", Box::new(Trim));
+    hbs.register_helper("pad", Box::new(Pad));
+    hbs.register_helper("wrap_comment", Box::new(WrapComment));
+    hbs.register_helper("first", Box::new(First));
+    hbs.register_helper("last", Box::new(Last));
+    hbs.register_helper("set", Box::new(Set));
+    hbs.register_helper("get", Box::new(Get));
+    hbs.register_helper(
+        "lookup_overlay",
+        Box::new(LookupOverlay::new(config.overlays.clone())),
+    );
+}
@@ -0,0 +1,43 @@
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, JsonValue, RenderContext, RenderError, ScopedJson,
+};
+
+use crate::renderer::overlay_config::OverlayConfig;
+use crate::renderer::template::helper::params;
+
+/// `{{lookup_overlay full_proto_name "key"}}` is the overlay value of `key` for a target, or
+/// null if there is none. Targets are the same as in overlay config, e.g. `source_file` for files
+/// or `full_proto_name` for messages, fields and enums.
+#[derive(Clone)]
+pub struct LookupOverlay {
+    overlays: OverlayConfig,
+}
+
+impl LookupOverlay {
+    pub fn new(overlays: OverlayConfig) -> Self {
+        Self { overlays }
+    }
+}
+
+impl HelperDef for LookupOverlay {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'reg, 'rc>, RenderError> {
+        let target = params::str_param(h, 0, "target")?;
+        let key = params::str_param(h, 1, "key")?;
+        let value = match self
+            .overlays
+            .by_target(target)
+            .and_then(|values| values.get(key))
+        {
+            None => JsonValue::Null,
+            Some(value) => serde_json::to_value(value)
+                .map_err(|err| params::error(h, format!("invalid value for '{}': {}", key, err)))?,
+        };
+        Ok(value.into())
+    }
+}
@@ -0,0 +1,44 @@
+use handlebars::{Helper, JsonValue, RenderError};
+
+/// Required param `index` of helper `h`, where `name` is only used in errors.
+pub fn param<'a>(h: &'a Helper, index: usize, name: &str) -> Result<&'a JsonValue, RenderError> {
+    h.param(index)
+        .map(|param| param.value())
+        .ok_or_else(|| error(h, format!("param '{}' not found", name)))
+}
+
+pub fn str_param<'a>(h: &'a Helper, index: usize, name: &str) -> Result<&'a str, RenderError> {
+    param(h, index, name)?
+        .as_str()
+        .ok_or_else(|| error(h, format!("param '{}' is not a string", name)))
+}
+
+pub fn u64_param(h: &Helper, index: usize, name: &str) -> Result<u64, RenderError> {
+    param(h, index, name)?
+        .as_u64()
+        .ok_or_else(|| error(h, format!("param '{}' is not a positive integer", name)))
+}
+
+/// Optional string hash value, e.g. `prefix` in `{{helper value prefix="// "}}`.
+pub fn hash_str<'a>(h: &'a Helper, key: &str, default: &'a str) -> Result<&'a str, RenderError> {
+    match h.hash_get(key) {
+        None => Ok(default),
+        Some(value) => value
+            .value()
+            .as_str()
+            .ok_or_else(|| error(h, format!("hash '{}' is not a string", key))),
+    }
+}
+
+pub fn error(h: &Helper, message: impl AsRef<str>) -> RenderError {
+    RenderError::new(format!("Helper '{}': {}", h.name(), message.as_ref()))
+}
+
+/// String representation used when writing values, i.e. strings without quotes.
+pub fn to_plain_string(value: &JsonValue) -> String {
+    match value {
+        JsonValue::String(value) => value.clone(),
+        JsonValue::Null => String::new(),
+        value => value.to_string(),
+    }
+}
@@ -0,0 +1,153 @@
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, JsonValue, RenderContext, RenderError, ScopedJson,
+};
+
+use crate::renderer::template::helper::params;
+use crate::renderer::text::wrap_comment;
+
+/// `{{join array ", "}}` joins the items of an array, writing strings without quotes.
+#[derive(Clone, Copy)]
+pub struct Join;
+
+impl HelperDef for Join {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'reg, 'rc>, RenderError> {
+        let array = params::param(h, 0, "array")?
+            .as_array()
+            .ok_or_else(|| params::error(h, "param 'array' is not an array"))?;
+        let separator = params::str_param(h, 1, "separator")?;
+        let joined = array
+            .iter()
+            .map(params::to_plain_string)
+            .collect::<Vec<String>>()
+            .join(separator);
+        Ok(JsonValue::String(joined).into())
+    }
+}
+
+/// `{{replace value "from" "to"}}` replaces all occurrences of `from`.
+#[derive(Clone, Copy)]
+pub struct Replace;
+
+impl HelperDef for Replace {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'reg, 'rc>, RenderError> {
+        let value = params::str_param(h, 0, "value")?;
+        let from = params::str_param(h, 1, "from")?;
+        let to = params::str_param(h, 2, "to")?;
+        Ok(JsonValue::String(value.replace(from, to)).into())
+    }
+}
+
+/// `{{trim value}}` removes leading and trailing whitespace.
+#[derive(Clone, Copy)]
+pub struct Trim;
+
+impl HelperDef for Trim {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'reg, 'rc>, RenderError> {
+        let value = params::str_param(h, 0, "value")?;
+        Ok(JsonValue::String(value.trim().to_owned()).into())
+    }
+}
+
+/// `{{pad value 12}}` pads `value` to a width, e.g. to align columns.
+///
+/// Hash options: `align="left"` (default) or `align="right"`, and `fill=" "`.
+#[derive(Clone, Copy)]
+pub struct Pad;
+
+impl HelperDef for Pad {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'reg, 'rc>, RenderError> {
+        let value = params::to_plain_string(params::param(h, 0, "value")?);
+        let width = params::u64_param(h, 1, "width")? as usize;
+        let fill = params::hash_str(h, "fill", " ")?;
+        let align_right = match params::hash_str(h, "align", "left")? {
+            "left" => false,
+            "right" => true,
+            align => {
+                return Err(params::error(
+                    h,
+                    format!("unknown align '{}', expected 'left' or 'right'", align),
+                ))
+            }
+        };
+        Ok(JsonValue::String(pad(value, width, fill, align_right)).into())
+    }
+}
+
+/// `{{wrap_comment text 80}}` word wraps `text` so lines including the comment prefix fit in a
+/// width. Line breaks in `text` are kept.
+///
+/// Hash options: `prefix="// "` (default).
+#[derive(Clone, Copy)]
+pub struct WrapComment;
+
+impl HelperDef for WrapComment {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'reg, 'rc>, RenderError> {
+        let text = params::str_param(h, 0, "text")?;
+        let width = params::u64_param(h, 1, "width")? as usize;
+        let prefix = params::hash_str(h, "prefix", "// ")?;
+        Ok(JsonValue::String(wrap_comment(text, width, prefix)).into())
+    }
+}
+
+fn pad(value: String, width: usize, fill: &str, align_right: bool) -> String {
+    let len = value.chars().count();
+    if len >= width || fill.is_empty() {
+        return value;
+    }
+    let padding: String = fill.repeat(width - len).chars().take(width - len).collect();
+    if align_right {
+        format!("{}{}", padding, value)
+    } else {
+        format!("{}{}", value, padding)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::renderer::template::helper::strings::pad;
+
+    #[test]
+    fn pad_left_aligned() {
+        assert_eq!(pad("ab".to_owned(), 4, " ", false), "ab  ");
+    }
+
+    #[test]
+    fn pad_right_aligned() {
+        assert_eq!(pad("ab".to_owned(), 4, "0", true), "00ab");
+    }
+
+    #[test]
+    fn pad_longer_than_width() {
+        assert_eq!(pad("abcdef".to_owned(), 4, " ", false), "abcdef");
+    }
+}
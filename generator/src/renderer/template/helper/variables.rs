@@ -0,0 +1,57 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, HelperResult, JsonValue, Output, RenderContext,
+    RenderError, ScopedJson,
+};
+
+use crate::renderer::template::helper::params;
+
+thread_local! {
+    static VARIABLES: RefCell<HashMap<String, JsonValue>> = RefCell::new(HashMap::new());
+}
+
+/// `{{set "name" value}}` stores a variable for the rest of the output being rendered,
+/// including partials and nested blocks. Writes nothing.
+#[derive(Clone, Copy)]
+pub struct Set;
+
+/// `{{get "name"}}` is the value of a variable stored by `set`, or null if it isn't set.
+#[derive(Clone, Copy)]
+pub struct Get;
+
+/// Forgets all variables. Called before rendering each output.
+pub fn clear() {
+    VARIABLES.with(|variables| variables.borrow_mut().clear());
+}
+
+impl HelperDef for Set {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+        _: &mut dyn Output,
+    ) -> HelperResult {
+        let name = params::str_param(h, 0, "name")?;
+        let value = params::param(h, 1, "value")?.clone();
+        VARIABLES.with(|variables| variables.borrow_mut().insert(name.to_owned(), value));
+        Ok(())
+    }
+}
+
+impl HelperDef for Get {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'reg, 'rc>, RenderError> {
+        let name = params::str_param(h, 0, "name")?;
+        let value = VARIABLES.with(|variables| variables.borrow().get(name).cloned());
+        Ok(value.unwrap_or(JsonValue::Null).into())
+    }
+}
@@ -10,6 +10,9 @@ mod renderer;
 pub const TEMPLATE_EXT: &'static str = "hbs";
 pub const METADATA_TEMPLATE_NAME: &'static str = "metadata";
 pub const FILE_TEMPLATE_NAME: &'static str = "file";
+pub const MESSAGE_TEMPLATE_NAME: &'static str = "message";
+pub const ENUM_TEMPLATE_NAME: &'static str = "enum";
+pub const ALL_TEMPLATE_NAME: &'static str = "all";
 
 pub fn generate(config: &Config) -> Result<()> {
     Generator {
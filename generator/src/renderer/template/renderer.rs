@@ -1,10 +1,18 @@
-use crate::renderer::context::{FileContext, MetadataContext};
-use crate::renderer::template::{helper, FILE_TEMPLATE_NAME, METADATA_TEMPLATE_NAME, TEMPLATE_EXT};
+use crate::diagnostic::Diagnostic;
+use crate::renderer::context::{
+    AllContext, EnumContext, FileContext, MessageContext, MetadataContext,
+};
+use crate::renderer::template::{
+    helper, ALL_TEMPLATE_NAME, ENUM_TEMPLATE_NAME, FILE_TEMPLATE_NAME, MESSAGE_TEMPLATE_NAME,
+    METADATA_TEMPLATE_NAME, TEMPLATE_EXT,
+};
 use crate::renderer::{find_existing_config_path, Renderer, RendererConfig};
 use crate::DisplayNormalized;
-use anyhow::{Context, Result};
-use handlebars::Handlebars;
+use anyhow::{anyhow, Context, Result};
+use handlebars::{Handlebars, RenderError, TemplateError};
+use log::debug;
 use serde::Serialize;
+use std::collections::HashMap;
 use std::io;
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
@@ -16,24 +24,22 @@ use walkdir::WalkDir;
 pub struct TemplateRenderer<'a> {
     hbs: Handlebars<'a>,
     config: RendererConfig,
+    /// Files that templates were loaded from, by template name.
+    template_paths: HashMap<String, PathBuf>,
 }
 
 impl TemplateRenderer<'_> {
     pub fn new() -> Self {
-        let mut hbs = Handlebars::new();
-        hbs.register_helper("indent", Box::new(helper::Indent));
-        hbs.register_helper("if_equals", Box::new(helper::IfEquals));
-        Self {
-            hbs,
-            config: Default::default(),
-        }
+        Self::with_config(Default::default())
     }
 
-    #[allow(dead_code)]
     pub fn with_config(config: RendererConfig) -> Self {
+        let mut hbs = Handlebars::new();
+        helper::register(&mut hbs, &config);
         Self {
-            hbs: Handlebars::new(),
+            hbs,
             config,
+            template_paths: HashMap::new(),
         }
     }
 
@@ -51,14 +57,17 @@ impl TemplateRenderer<'_> {
     fn load_template_string(&mut self, name: &str, template: impl AsRef<str>) -> Result<()> {
         self.hbs
             .register_template_string(name, template)
+            .map_err(|err| self.template_diagnostic(&err))
             .with_context(|| format!("Failed to load '{}' template from string", name))?;
         Ok(())
     }
 
+    /// Loads all templates under `root`, named by their path relative to `root` without the
+    /// extension, e.g. `messages/struct` for `messages/struct.hbs`.
     pub fn load_templates(&mut self, root: &Path) -> Result<()> {
         for entry in WalkDir::new(root)
             .follow_links(false)
-            .max_depth(1)
+            .sort_by_file_name()
             .into_iter()
             .filter_map(|r| r.ok())
             .filter(|e| e.file_type().is_file())
@@ -69,12 +78,9 @@ impl TemplateRenderer<'_> {
                 _ => continue,
             };
 
-            let template_name = match file.file_stem() {
+            let template_name = match template_name(root, file) {
                 None => continue,
-                Some(file_name) => match file_name.to_str() {
-                    None => continue,
-                    Some(name) => name.to_owned(),
-                },
+                Some(name) => name,
             };
 
             self.load_template_file(&template_name, file)?;
@@ -82,9 +88,27 @@ impl TemplateRenderer<'_> {
         Ok(())
     }
 
+    /// Loads the shared templates in `partials_path`, relative to `root`.
+    fn load_partials(&mut self, root: &Path) -> Result<()> {
+        for dir in self.config.partials_path.clone() {
+            let dir = root.join(dir);
+            if !dir.is_dir() {
+                return Err(anyhow!(
+                    "Partials directory in 'partials_path' does not exist: {}",
+                    dir.display_normalized()
+                ));
+            }
+            self.load_templates(&dir)?;
+        }
+        Ok(())
+    }
+
     fn load_template_file(&mut self, name: &str, path: &Path) -> Result<()> {
+        self.template_paths
+            .insert(name.to_owned(), path.to_path_buf());
         self.hbs
             .register_template_file(name, path)
+            .map_err(|err| self.template_diagnostic(&err))
             .with_context(|| {
                 format!(
                     "Failed to load '{}' template at path: {}",
@@ -97,9 +121,11 @@ impl TemplateRenderer<'_> {
 
     #[allow(dead_code)]
     fn render_to_string<S: Serialize>(&self, template: &str, data: &S) -> Result<String> {
+        helper::clear_variables();
         let rendered = self
             .hbs
             .render(template, data)
+            .map_err(|err| self.render_diagnostic(&err))
             .with_context(|| render_error_context(template, data))?;
         Ok(rendered)
     }
@@ -110,11 +136,48 @@ impl TemplateRenderer<'_> {
         data: &S,
         writer: W,
     ) -> Result<()> {
+        helper::clear_variables();
         self.hbs
             .render_to_write(template, data, writer)
+            .map_err(|err| self.render_diagnostic(&err))
             .with_context(|| render_error_context(template, data))?;
         Ok(())
     }
+
+    fn template_diagnostic(&self, err: &TemplateError) -> Diagnostic {
+        #[allow(deprecated)]
+        let message = err.reason.to_string();
+        self.diagnostic(message, &err.template_name, err.line_no, err.column_no)
+    }
+
+    fn render_diagnostic(&self, err: &RenderError) -> Diagnostic {
+        self.diagnostic(
+            err.desc.clone(),
+            &err.template_name,
+            err.line_no,
+            err.column_no,
+        )
+    }
+
+    /// Points at the template's file if it was loaded from one, otherwise at its name.
+    fn diagnostic(
+        &self,
+        message: String,
+        template_name: &Option<String>,
+        line: Option<usize>,
+        column: Option<usize>,
+    ) -> Diagnostic {
+        let mut diagnostic = Diagnostic::error(message).with_position(line, column);
+        if let Some(name) = template_name {
+            diagnostic.path = Some(
+                self.template_paths
+                    .get(name)
+                    .cloned()
+                    .unwrap_or_else(|| PathBuf::from(name)),
+            );
+        }
+        diagnostic
+    }
 }
 
 impl Renderer for TemplateRenderer<'_> {
@@ -128,14 +191,19 @@ impl Renderer for TemplateRenderer<'_> {
     /// Any other `*.hbs` files will also be loaded as templates based on the file name, and can
     /// be used in other templates as partials with the syntax {{> file_name}}.
     /// (See also: https://handlebarsjs.com/guide/partials.html)
-    fn load(&mut self, root: &Path, _: &[PathBuf]) -> Result<()> {
-        self.config = Self::load_config(&find_existing_config_path(root)?, &[])?;
+    fn load(&mut self, root: &Path, overlays: &[PathBuf]) -> Result<()> {
+        self.config = Self::load_config(&find_existing_config_path(root)?, overlays)?;
+        // Helpers like `lookup_overlay` depend on the config.
+        helper::register(&mut self.hbs, &self.config);
+        // Templates in the template directory take precedence over shared partials.
+        self.load_partials(root)?;
         self.load_templates(root)?;
         Ok(())
     }
 
     fn reset(&mut self) {
         self.hbs.clear_templates();
+        self.template_paths.clear();
     }
 
     fn config(&self) -> &RendererConfig {
@@ -157,18 +225,81 @@ impl Renderer for TemplateRenderer<'_> {
     fn render_file<W: io::Write>(&self, context: FileContext, writer: &mut W) -> Result<()> {
         self.render_to_write(FILE_TEMPLATE_NAME, &context, writer)
     }
+
+    fn has_file(&self) -> bool {
+        self.hbs.has_template(FILE_TEMPLATE_NAME)
+    }
+
+    fn has_message(&self) -> bool {
+        self.hbs.has_template(MESSAGE_TEMPLATE_NAME)
+    }
+
+    fn has_enum(&self) -> bool {
+        self.hbs.has_template(ENUM_TEMPLATE_NAME)
+    }
+
+    fn has_all(&self) -> bool {
+        self.hbs.has_template(ALL_TEMPLATE_NAME)
+    }
+
+    fn render_message<W: io::Write>(
+        &self,
+        context: &MessageContext,
+        file: &FileContext,
+        writer: &mut W,
+    ) -> Result<()> {
+        let data = TypeData { context, file };
+        self.render_to_write(MESSAGE_TEMPLATE_NAME, &data, writer)
+    }
+
+    fn render_enum<W: io::Write>(
+        &self,
+        context: &EnumContext,
+        file: &FileContext,
+        writer: &mut W,
+    ) -> Result<()> {
+        let data = TypeData { context, file };
+        self.render_to_write(ENUM_TEMPLATE_NAME, &data, writer)
+    }
+
+    fn render_all<W: io::Write>(&self, context: AllContext, writer: &mut W) -> Result<()> {
+        self.render_to_write(ALL_TEMPLATE_NAME, &context, writer)
+    }
+}
+
+/// Data for `message.hbs` and `enum.hbs` when rendered as their own files. The type's context is
+/// at the root, same as when used as a partial, with its file available as `file`.
+#[derive(Serialize)]
+struct TypeData<'a, T: Serialize> {
+    #[serde(flatten)]
+    context: &'a T,
+    file: &'a FileContext,
+}
+
+/// Path of `file` relative to `root` without the extension, always separated by `/`.
+fn template_name(root: &Path, file: &Path) -> Option<String> {
+    let relative = file.strip_prefix(root).ok()?.with_extension("");
+    let components = relative
+        .components()
+        .map(|component| component.as_os_str().to_str())
+        .collect::<Option<Vec<&str>>>()?;
+    Some(components.join("/"))
 }
 
+/// Handlebars errors point at the template, so the data is left out of the message and only
+/// logged at debug level, where a whole file context is still readable.
 fn render_error_context<S: Serialize>(name: &str, data: &S) -> String {
-    format!(
+    debug!(
         "Failed to render template '{}' for data: {}",
         name,
         serde_json::to_string(data).unwrap_or("(failed to serialize)".to_owned()),
-    )
+    );
+    format!("Failed to render template '{}'", name)
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::renderer::case::Case;
     use crate::renderer::context::{EnumContext, FieldContext, FileContext, MessageContext};
     use crate::renderer::template::renderer::TemplateRenderer;
     use crate::renderer::template::FILE_TEMPLATE_NAME;
@@ -299,6 +430,33 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn field_proto_names_and_case() -> Result<()> {
+        let mut config = RendererConfig::default();
+        config.case_config.field_name = Case::LowerCamel;
+        let mut renderer = TemplateRenderer::with_config(config);
+        load_field_template(
+            &mut renderer,
+            r#"{{field_name}} {{proto_name}} {{full_proto_name}} {{case proto_name "UPPER_SNAKE"}}"#,
+        )?;
+
+        let field = fake_field("user_id", primitive::FLOAT);
+        let package = "pkg".to_owned();
+        let message_name = "User".to_owned();
+        let result = render_field(&renderer, &field, Some(&package), Some(&message_name))?;
+        assert_eq!(result, "userId user_id pkg.User.user_id USER_ID");
+        Ok(())
+    }
+
+    #[test]
+    fn case_helper_unknown_case_errors() -> Result<()> {
+        let mut renderer = TemplateRenderer::with_config(RendererConfig::default());
+        load_field_template(&mut renderer, r#"{{case proto_name "Sponge"}}"#)?;
+        let field = fake_field("user_id", primitive::FLOAT);
+        assert!(render_field(&renderer, &field, None, None).is_err());
+        Ok(())
+    }
+
     #[test]
     fn field_gets_package_from_file() -> Result<()> {
         let config = RendererConfig::default();
@@ -416,6 +574,319 @@ mod tests {
         }
     }
 
+    mod types {
+        use crate::render::Render;
+        use crate::renderer::template::renderer::TemplateRenderer;
+        use crate::renderer::tests::{fake_file, fake_message};
+        use crate::renderer::RendererConfig;
+        use anyhow::Result;
+        use prost_types::FileDescriptorSet;
+        use std::fs;
+        use tempfile::tempdir;
+
+        #[test]
+        fn message_template_per_message() -> Result<()> {
+            let mut config = RendererConfig::default();
+            config.file_extension = "kt".to_owned();
+            config.generated_header = Some(vec![]);
+            config.type_file_name_pattern = Some("{file_name}/{name}.{ext}".to_owned());
+            let mut renderer = TemplateRenderer::with_config(config);
+            renderer.load_file_template_string("file")?;
+            renderer.load_template_string("message", "{{file.source_file}}:{{name}}")?;
+            let messages = vec![fake_message("Msg0", vec![]), fake_message("Msg1", vec![])];
+            let file = fake_file("dir/file_name.proto", vec![], messages);
+            let output = tempdir()?;
+            Render::render(
+                &renderer,
+                &FileDescriptorSet { file: vec![file] },
+                output.path(),
+            )?;
+            assert_eq!(
+                fs::read_to_string(output.path().join("file_name/Msg0.kt"))?,
+                "dir/file_name.proto:Msg0"
+            );
+            assert!(output.path().join("file_name/Msg1.kt").exists());
+            Ok(())
+        }
+    }
+
+    mod all {
+        use crate::render::Render;
+        use crate::renderer::template::renderer::TemplateRenderer;
+        use crate::renderer::tests::fake_file_empty;
+        use crate::renderer::RendererConfig;
+        use anyhow::Result;
+        use prost_types::FileDescriptorSet;
+        use std::fs;
+        use tempfile::tempdir;
+
+        #[test]
+        fn all_template() -> Result<()> {
+            let mut config = RendererConfig::default();
+            config.file_extension = "ts".to_owned();
+            config.all_file_name = "index".to_owned();
+            config.generated_header = Some(vec![]);
+            let mut renderer = TemplateRenderer::with_config(config);
+            renderer.load_file_template_string("")?;
+            renderer.load_template_string("all", "{{#each files}}{{source_file}};{{/each}}")?;
+            let descriptor_set = FileDescriptorSet {
+                file: vec![fake_file_empty("a.proto"), fake_file_empty("b.proto")],
+            };
+            let output = tempdir()?;
+            Render::render(&renderer, &descriptor_set, output.path())?;
+            assert_eq!(
+                fs::read_to_string(output.path().join("index.ts"))?,
+                "a.proto;b.proto;"
+            );
+            Ok(())
+        }
+    }
+
+    mod partials {
+        use crate::renderer::context::FileContext;
+        use crate::renderer::template::renderer::TemplateRenderer;
+        use crate::renderer::tests::fake_file_empty;
+        use crate::renderer::{Renderer, RendererConfig};
+        use anyhow::Result;
+        use std::fs;
+        use std::path::Path;
+        use tempfile::tempdir;
+
+        #[test]
+        fn nested_partials_named_by_path() -> Result<()> {
+            let dir = tempdir()?;
+            let root = dir.path().join("templates");
+            write(&root, "file.hbs", "{{> messages/struct}}")?;
+            write(&root, "messages/struct.hbs", "{{> messages/fields/field}}")?;
+            write(&root, "messages/fields/field.hbs", "field")?;
+            assert_eq!(render(&root)?, "field");
+            Ok(())
+        }
+
+        #[test]
+        fn shared_partials() -> Result<()> {
+            let dir = tempdir()?;
+            let root = dir.path().join("templates");
+            write_config(&root, "../shared")?;
+            write(&root, "file.hbs", "{{> common/header}} {{> overridden}}")?;
+            write(&root, "overridden.hbs", "local")?;
+            write(&dir.path().join("shared"), "common/header.hbs", "shared")?;
+            write(&dir.path().join("shared"), "overridden.hbs", "shared")?;
+            assert_eq!(render(&root)?, "shared local");
+            Ok(())
+        }
+
+        #[test]
+        fn missing_partials_dir_errors() -> Result<()> {
+            let dir = tempdir()?;
+            write_config(dir.path(), "missing")?;
+            write(dir.path(), "file.hbs", "")?;
+            let mut renderer = TemplateRenderer::new();
+            assert!(renderer.load(dir.path(), &[]).is_err());
+            Ok(())
+        }
+
+        fn write(root: &Path, name: &str, contents: &str) -> Result<()> {
+            let path = root.join(name);
+            fs::create_dir_all(path.parent().unwrap())?;
+            fs::write(path, contents)?;
+            Ok(())
+        }
+
+        fn write_config(root: &Path, partials_path: &str) -> Result<()> {
+            let config = RendererConfig {
+                partials_path: vec![partials_path.into()],
+                ..Default::default()
+            };
+            write(root, "config.yaml", &serde_yaml::to_string(&config)?)
+        }
+
+        fn render(root: &Path) -> Result<String> {
+            if !root.join("config.yaml").exists() {
+                write(
+                    root,
+                    "config.yaml",
+                    &serde_yaml::to_string(&RendererConfig::default())?,
+                )?;
+            }
+            let mut renderer = TemplateRenderer::new();
+            renderer.load(root, &[])?;
+            let context = FileContext::new(&fake_file_empty("file.proto"), renderer.config())?;
+            let mut output = Vec::new();
+            renderer.render_file(context, &mut output)?;
+            Ok(String::from_utf8(output)?)
+        }
+    }
+
+    mod helpers {
+        use crate::dir_init::initialize_template_dir;
+        use crate::renderer::context::FileContext;
+        use crate::renderer::overlay_config::OverlayConfig;
+        use crate::renderer::template::renderer::TemplateRenderer;
+        use crate::renderer::tests::fake_file_empty;
+        use crate::renderer::{Renderer, RendererConfig};
+        use anyhow::Result;
+        use serde_json::json;
+        use std::collections::HashMap;
+        use tempfile::tempdir;
+
+        #[test]
+        fn case() -> Result<()> {
+            assert_eq!(
+                render(
+                    "{{upper_camel s}} {{lower_kebab s}}",
+                    json!({"s": "my_name"})
+                )?,
+                "MyName my-name"
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn strings() -> Result<()> {
+            let data = json!({"items": ["a", "b", 1], "s": "  a.b  "});
+            assert_eq!(
+                render(
+                    r#"{{join items ", "}}|{{replace (trim s) "." "::"}}|{{pad "x" 3 fill="-"}}|"#,
+                    data,
+                )?,
+                "a, b, 1|a::b|x--|"
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn wrap_comment() -> Result<()> {
+            assert_eq!(
+                render(
+                    r#"{{wrap_comment s 12 prefix="/// "}}"#,
+                    json!({"s": "one two three"})
+                )?,
+                "/// one two\n/// three"
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn comparison_and_logic() -> Result<()> {
+            let template = r#"{{#if (and (eq a 1) (not (gt a b)))}}yes{{else}}no{{/if}}"#;
+            assert_eq!(render(template, json!({"a": 1, "b": 2}))?, "yes");
+            assert_eq!(render(template, json!({"a": 1, "b": 0}))?, "no");
+            Ok(())
+        }
+
+        #[test]
+        fn first_last() -> Result<()> {
+            let data = json!({"fields": [{"name": "a"}, {"name": "b"}], "empty": []});
+            assert_eq!(
+                render(
+                    "{{#with (first fields)}}{{name}}{{/with}}{{#with (last fields)}}{{name}}{{/with}}{{#if (first empty)}}x{{/if}}",
+                    data,
+                )?,
+                "ab"
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn set_get() -> Result<()> {
+            let template = r#"{{set "sep" ";"}}{{#each items}}{{this}}{{get "sep"}}{{/each}}"#;
+            assert_eq!(render(template, json!({"items": [1, 2]}))?, "1;2;");
+            // Variables don't leak between outputs.
+            assert_eq!(render(r#"{{get "sep"}}"#, json!({}))?, "");
+            Ok(())
+        }
+
+        #[test]
+        fn lookup_overlay() -> Result<()> {
+            let config = RendererConfig {
+                overlays: OverlayConfig::new(
+                    HashMap::new(),
+                    HashMap::from([(
+                        "pkg.Message".to_owned(),
+                        HashMap::from([(
+                            "key".to_owned(),
+                            serde_yaml::Value::String("value".to_owned()),
+                        )]),
+                    )]),
+                ),
+                ..Default::default()
+            };
+            let mut renderer = TemplateRenderer::with_config(config);
+            renderer.load_template_string(
+                "test",
+                r#"{{lookup_overlay name "key"}}{{#if (lookup_overlay name "other")}}x{{/if}}"#,
+            )?;
+            assert_eq!(
+                renderer.render_to_string("test", &json!({"name": "pkg.Message"}))?,
+                "value"
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn initialized_file_template_renders_empty() -> Result<()> {
+            let dir = tempdir()?;
+            initialize_template_dir(dir.path())?;
+            let mut renderer = TemplateRenderer::new();
+            renderer.load(dir.path(), &[])?;
+            let context = FileContext::new(&fake_file_empty("file.proto"), renderer.config())?;
+            let mut output = Vec::new();
+            renderer.render_file(context, &mut output)?;
+            assert!(String::from_utf8(output)?.trim().is_empty());
+            Ok(())
+        }
+
+        fn render(template: &str, data: serde_json::Value) -> Result<String> {
+            let mut renderer = TemplateRenderer::new();
+            renderer.load_template_string("test", template)?;
+            renderer.render_to_string("test", &data)
+        }
+    }
+
+    mod diagnostics {
+        use crate::diagnostic::Diagnostic;
+        use crate::renderer::context::FileContext;
+        use crate::renderer::template::renderer::TemplateRenderer;
+        use crate::renderer::tests::fake_file_empty;
+        use crate::renderer::Renderer;
+        use anyhow::Result;
+        use std::fs;
+        use tempfile::tempdir;
+
+        #[test]
+        fn parse_error_points_at_file() -> Result<()> {
+            let dir = tempdir()?;
+            fs::write(dir.path().join("file.hbs"), "line\n{{#if x}}\n")?;
+            let mut renderer = TemplateRenderer::new();
+            let err = renderer.load_templates(dir.path()).err().unwrap();
+            let diagnostic = &Diagnostic::collect(&err)[0];
+            assert_eq!(diagnostic.path, Some(dir.path().join("file.hbs")));
+            assert!(diagnostic.line.is_some());
+            Ok(())
+        }
+
+        #[test]
+        fn render_error_points_at_partial() -> Result<()> {
+            let dir = tempdir()?;
+            fs::write(dir.path().join("file.hbs"), "{{> partial}}")?;
+            fs::write(dir.path().join("partial.hbs"), "line\n{{not_a_helper 1}}")?;
+            let mut renderer = TemplateRenderer::new();
+            renderer.load_templates(dir.path())?;
+            let file = fake_file_empty("file_name");
+            let context = FileContext::new(&file, renderer.config())?;
+            let err = renderer
+                .render_file(context, &mut Vec::new())
+                .err()
+                .unwrap();
+            let diagnostic = &Diagnostic::collect(&err)[0];
+            assert_eq!(diagnostic.path, Some(dir.path().join("partial.hbs")));
+            assert_eq!(diagnostic.line, Some(2));
+            assert_eq!(diagnostic.notes, vec!["Failed to render template 'file'"]);
+            Ok(())
+        }
+    }
+
     fn fake_enum<N, V>(name: N, values: &[(&V, i32)]) -> EnumDescriptorProto
     where
         N: Into<String>,
@@ -0,0 +1,92 @@
+/// Word wraps `text` so each line including `prefix` fits in `width`, then prefixes each line.
+/// Line breaks in `text` are kept, and words longer than the width get a line of their own.
+pub fn wrap_comment(text: &str, width: usize, prefix: &str) -> String {
+    let max_len = width.saturating_sub(prefix.chars().count());
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > max_len {
+                lines.push(line);
+                line = String::new();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+    lines
+        .iter()
+        .map(|line| [prefix, line].concat().trim_end().to_owned())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Formats `text` as a comment in `style`, wrapped to `width`. The style is either the marker of
+/// a line comment, e.g. `//`, `///`, `#` or `--`, or `/*` or `/**` for a block comment.
+pub fn comment(text: &str, style: &str, width: usize) -> String {
+    match style {
+        "/*" | "/**" => format!("{}\n{}\n */", style, wrap_comment(text, width, " * ")),
+        _ => wrap_comment(text, width, &format!("{} ", style)),
+    }
+}
+
+/// Prefixes each non-empty line of `text` with `indent`.
+pub fn indent_block(text: &str, indent: &str) -> String {
+    text.split('\n')
+        .map(|line| {
+            if line.is_empty() {
+                line.to_owned()
+            } else {
+                format!("{}{}", indent, line)
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::renderer::text::{comment, indent_block, wrap_comment};
+
+    #[test]
+    fn wrap_comment_at_width() {
+        assert_eq!(
+            wrap_comment("one two three four", 13, "// "),
+            "// one two\n// three four"
+        );
+    }
+
+    #[test]
+    fn wrap_comment_keeps_line_breaks() {
+        assert_eq!(wrap_comment("one\n\ntwo", 80, "# "), "# one\n#\n# two");
+    }
+
+    #[test]
+    fn wrap_comment_long_word() {
+        assert_eq!(
+            wrap_comment("a verylongword b", 8, "// "),
+            "// a\n// verylongword\n// b"
+        );
+    }
+
+    #[test]
+    fn line_comment() {
+        assert_eq!(comment("one two", "///", 80), "/// one two");
+    }
+
+    #[test]
+    fn block_comment() {
+        assert_eq!(
+            comment("one two three", "/**", 10),
+            "/**\n * one two\n * three\n */"
+        );
+    }
+
+    #[test]
+    fn indent_block_skips_empty_lines() {
+        assert_eq!(indent_block("a\n\n  b", "  "), "  a\n\n    b");
+    }
+}
@@ -0,0 +1,52 @@
+use anyhow::Result;
+
+use crate::in_out_generator::InOutGenerator;
+use crate::renderer::wasm::renderer::WasmRenderer;
+use crate::{Config, InOutConfig};
+
+mod renderer;
+
+pub const MODULE_FILE_NAME: &str = "plugin.wasm";
+
+/// Module the host functions are imported from. Nothing else can be imported.
+pub const IMPORT_MODULE: &str = "protox";
+pub const LOG_IMPORT: &str = "log";
+pub const ERROR_IMPORT: &str = "error";
+
+pub const MEMORY_EXPORT: &str = "memory";
+pub const ALLOC_EXPORT: &str = "protox_alloc";
+pub const METADATA_EXPORT: &str = "protox_render_metadata";
+pub const FILE_EXPORT: &str = "protox_render_file";
+pub const MESSAGE_EXPORT: &str = "protox_render_message";
+pub const ENUM_EXPORT: &str = "protox_render_enum";
+pub const ALL_EXPORT: &str = "protox_render_all";
+
+pub fn generate(config: &Config) -> Result<()> {
+    Generator {
+        config,
+        renderer: WasmRenderer::new(),
+    }
+    .generate()
+}
+
+struct Generator<'a> {
+    config: &'a Config,
+    renderer: WasmRenderer,
+}
+impl<'a> InOutGenerator<WasmRenderer> for Generator<'a> {
+    fn name(&self) -> &str {
+        "WebAssembly Plugins"
+    }
+
+    fn renderer(&mut self) -> &mut WasmRenderer {
+        &mut self.renderer
+    }
+
+    fn app_config(&self) -> &Config {
+        self.config
+    }
+
+    fn in_out_configs(&self) -> Vec<InOutConfig> {
+        self.app_config().wasm.clone()
+    }
+}
@@ -0,0 +1,557 @@
+use crate::renderer::context::{
+    AllContext, EnumContext, FileContext, MessageContext, MetadataContext,
+};
+use crate::renderer::wasm::{
+    ALLOC_EXPORT, ALL_EXPORT, ENUM_EXPORT, ERROR_IMPORT, FILE_EXPORT, IMPORT_MODULE, LOG_IMPORT,
+    MEMORY_EXPORT, MESSAGE_EXPORT, METADATA_EXPORT, MODULE_FILE_NAME,
+};
+use crate::renderer::{dump, find_existing_config_path, Renderer, RendererConfig};
+use crate::DisplayNormalized;
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info};
+use serde::Serialize;
+use std::convert::TryFrom;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use wasmi::{
+    Caller, Engine, Extern, Linker, Module, Store, StoreLimits, StoreLimitsBuilder, TrapCode,
+};
+
+/// Renders output with a compiled WebAssembly module, so generators can be written in any
+/// language that targets wasm.
+///
+/// Each entry point receives its context as JSON and returns the rendered output. The module runs
+/// in-process, but it can only import the functions in [`IMPORT_MODULE`], so it has no filesystem,
+/// network, clock or WASI access. A fresh instance is created for every call, so plugins don't
+/// need to free memory and can't keep state between files. Each call is limited to
+/// `wasm.fuel` from the config, so a plugin that never returns fails instead of hanging, and its
+/// memory can't grow past `wasm.max_memory`.
+pub struct WasmRenderer {
+    engine: Engine,
+    module: Option<Module>,
+    module_path: PathBuf,
+    config: RendererConfig,
+}
+
+/// State of a single instance, shared with the host functions.
+struct HostState {
+    error: Option<String>,
+    limits: StoreLimits,
+}
+
+impl HostState {
+    fn new(max_memory: usize) -> Self {
+        Self {
+            error: None,
+            limits: StoreLimitsBuilder::new().memory_size(max_memory).build(),
+        }
+    }
+}
+
+impl WasmRenderer {
+    pub fn new() -> Self {
+        Self::with_config(Default::default())
+    }
+
+    pub fn with_config(config: RendererConfig) -> Self {
+        Self {
+            engine: engine(),
+            module: None,
+            module_path: PathBuf::from(MODULE_FILE_NAME),
+            config,
+        }
+    }
+
+    /// Compiles the module in `bytes`, which may also be WAT text.
+    fn load_module_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        let module = Module::new(&self.engine, bytes)
+            .map_err(|err| anyhow!("{}", err))
+            .with_context(|| {
+                format!(
+                    "Failed to compile plugin: {}",
+                    self.module_path.display_normalized()
+                )
+            })?;
+        check_imports(&module)?;
+        for export in [MEMORY_EXPORT, ALLOC_EXPORT] {
+            if module.get_export(export).is_none() {
+                return Err(anyhow!(
+                    "Plugin does not export '{}': {}",
+                    export,
+                    self.module_path.display_normalized()
+                ));
+            }
+        }
+        self.module = Some(module);
+        Ok(())
+    }
+
+    fn has_export(&self, name: &str) -> bool {
+        self.module
+            .as_ref()
+            .map(|module| module.get_export(name).is_some())
+            .unwrap_or(false)
+    }
+
+    fn render_to_write<S: Serialize, W: io::Write>(
+        &self,
+        export: &str,
+        data: &S,
+        writer: &mut W,
+    ) -> Result<()> {
+        let input = serde_json::to_vec(data)?;
+        let output = self
+            .call(export, &input)
+            .with_context(|| render_error_context(export, &self.module_path, &input))?;
+        writer.write_all(&output)?;
+        Ok(())
+    }
+
+    /// Copies `input` into a new instance with `protox_alloc`, calls `export` with its pointer and
+    /// length, and reads the output from the pointer and length packed into the returned `i64`.
+    fn call(&self, export: &str, input: &[u8]) -> Result<Vec<u8>> {
+        let module = self
+            .module
+            .as_ref()
+            .ok_or_else(|| anyhow!("No plugin is loaded"))?;
+        if module.get_export(export).is_none() {
+            return Err(anyhow!("Plugin does not export '{}'", export));
+        }
+
+        let mut store = Store::new(&self.engine, HostState::new(self.config.wasm.max_memory));
+        store.limiter(|state| &mut state.limits);
+        store
+            .set_fuel(self.config.wasm.fuel)
+            .map_err(|err| anyhow!("Failed to set plugin fuel: {}", err))?;
+        let instance = linker(&self.engine)?
+            .instantiate_and_start(&mut store, module)
+            .map_err(|err| anyhow!("Failed to instantiate plugin: {}", err))?;
+        let memory = instance
+            .get_memory(&store, MEMORY_EXPORT)
+            .ok_or_else(|| anyhow!("Plugin does not export '{}'", MEMORY_EXPORT))?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&store, ALLOC_EXPORT)
+            .map_err(|err| anyhow!("Invalid '{}' export: {}", ALLOC_EXPORT, err))?;
+        let render = instance
+            .get_typed_func::<(i32, i32), i64>(&store, export)
+            .map_err(|err| anyhow!("Invalid '{}' export: {}", export, err))?;
+
+        let input_len = i32::try_from(input.len()).context("Plugin input is too large")?;
+        let input_ptr = alloc
+            .call(&mut store, input_len)
+            .map_err(|err| self.call_error(&store, ALLOC_EXPORT, err))?;
+        memory
+            .write(&mut store, input_ptr as u32 as usize, input)
+            .map_err(|err| anyhow!("Failed to write plugin input: {}", err))?;
+
+        let packed = render
+            .call(&mut store, (input_ptr, input_len))
+            .map_err(|err| self.call_error(&store, export, err))?;
+        if let Some(message) = store.data_mut().error.take() {
+            return Err(anyhow!("Plugin error in '{}': {}", export, message));
+        }
+
+        let packed = packed as u64;
+        let output_ptr = (packed >> 32) as usize;
+        let output_len = (packed & u32::MAX as u64) as usize;
+        // The length comes from the plugin, so check it before copying anything.
+        let output =
+            memory_slice(memory.data(&store), output_ptr, output_len).ok_or_else(|| {
+                anyhow!(
+                    "Plugin output of {} bytes at {} is outside its memory",
+                    output_len,
+                    output_ptr
+                )
+            })?;
+        Ok(output.to_vec())
+    }
+
+    /// An error reported with `protox.error` before a trap explains it better than the trap.
+    fn call_error(
+        &self,
+        store: &Store<HostState>,
+        export: &str,
+        err: wasmi::Error,
+    ) -> anyhow::Error {
+        if let Some(message) = &store.data().error {
+            return anyhow!("Plugin error in '{}': {}", export, message);
+        }
+        if err.as_trap_code() == Some(TrapCode::OutOfFuel) {
+            return anyhow!(
+                "Plugin ran out of fuel in '{}' after {} units. It may be stuck in a loop, or \
+                 need a higher `wasm.fuel` in the config.",
+                export,
+                self.config.wasm.fuel
+            );
+        }
+        anyhow!("Plugin failed in '{}': {}", export, err)
+    }
+}
+
+impl Renderer for WasmRenderer {
+    /// Loads config and the compiled module from the same root path with the following names:
+    /// ```txt
+    ///     root/config.json
+    ///     root/plugin.wasm
+    /// ```
+    fn load(&mut self, root: &Path, overlays: &[PathBuf]) -> Result<()> {
+        self.config = Self::load_config(&find_existing_config_path(root)?, overlays)?;
+        self.module_path = root.join(MODULE_FILE_NAME);
+        info!(
+            "Loading plugin from: {}",
+            self.module_path.display_normalized()
+        );
+        let bytes = fs::read(&self.module_path).with_context(|| {
+            format!(
+                "Failed to read plugin at path: {}",
+                self.module_path.display_normalized()
+            )
+        })?;
+        self.load_module_bytes(&bytes)
+    }
+
+    fn reset(&mut self) {
+        self.module = None;
+    }
+
+    fn config(&self) -> &RendererConfig {
+        &self.config
+    }
+
+    fn has_metadata(&self) -> bool {
+        self.has_export(METADATA_EXPORT)
+    }
+
+    fn render_metadata<W: io::Write>(
+        &self,
+        context: MetadataContext,
+        writer: &mut W,
+    ) -> Result<()> {
+        self.render_to_write(METADATA_EXPORT, &context, writer)
+    }
+
+    fn render_file<W: io::Write>(&self, context: FileContext, writer: &mut W) -> Result<()> {
+        self.render_to_write(FILE_EXPORT, &dump::file_value(&context)?, writer)
+    }
+
+    fn has_file(&self) -> bool {
+        self.has_export(FILE_EXPORT)
+    }
+
+    fn has_message(&self) -> bool {
+        self.has_export(MESSAGE_EXPORT)
+    }
+
+    fn has_enum(&self) -> bool {
+        self.has_export(ENUM_EXPORT)
+    }
+
+    fn has_all(&self) -> bool {
+        self.has_export(ALL_EXPORT)
+    }
+
+    fn render_message<W: io::Write>(
+        &self,
+        context: &MessageContext,
+        file: &FileContext,
+        writer: &mut W,
+    ) -> Result<()> {
+        let data = dump::type_value(dump::message_value(context)?, file)?;
+        self.render_to_write(MESSAGE_EXPORT, &data, writer)
+    }
+
+    fn render_enum<W: io::Write>(
+        &self,
+        context: &EnumContext,
+        file: &FileContext,
+        writer: &mut W,
+    ) -> Result<()> {
+        let data = dump::type_value(dump::enum_value(context)?, file)?;
+        self.render_to_write(ENUM_EXPORT, &data, writer)
+    }
+
+    fn render_all<W: io::Write>(&self, context: AllContext, writer: &mut W) -> Result<()> {
+        self.render_to_write(ALL_EXPORT, &dump::all_value(&context)?, writer)
+    }
+}
+
+/// Plugins can only import the host functions, which is what keeps them sandboxed.
+fn check_imports(module: &Module) -> Result<()> {
+    for import in module.imports() {
+        if import.module() != IMPORT_MODULE
+            || (import.name() != LOG_IMPORT && import.name() != ERROR_IMPORT)
+        {
+            return Err(anyhow!(
+                "Plugin imports '{}.{}', but only '{}.{}' and '{}.{}' are available. Plugins have no filesystem, network or WASI access.",
+                import.module(),
+                import.name(),
+                IMPORT_MODULE,
+                LOG_IMPORT,
+                IMPORT_MODULE,
+                ERROR_IMPORT,
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn linker(engine: &Engine) -> Result<Linker<HostState>> {
+    let mut linker = Linker::new(engine);
+    linker
+        .func_wrap(
+            IMPORT_MODULE,
+            LOG_IMPORT,
+            |caller: Caller<'_, HostState>, ptr: i32, len: i32| -> Result<(), wasmi::Error> {
+                info!("[plugin] {}", read_string(&caller, ptr, len)?);
+                Ok(())
+            },
+        )
+        .map_err(|err| anyhow!("{}", err))?;
+    linker
+        .func_wrap(
+            IMPORT_MODULE,
+            ERROR_IMPORT,
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> Result<(), wasmi::Error> {
+                let message = read_string(&caller, ptr, len)?;
+                caller.data_mut().error = Some(message);
+                Ok(())
+            },
+        )
+        .map_err(|err| anyhow!("{}", err))?;
+    Ok(linker)
+}
+
+fn read_string(caller: &Caller<'_, HostState>, ptr: i32, len: i32) -> Result<String, wasmi::Error> {
+    let memory = caller
+        .get_export(MEMORY_EXPORT)
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| wasmi::Error::new(format!("plugin does not export '{}'", MEMORY_EXPORT)))?;
+    let bytes = memory_slice(
+        memory.data(caller),
+        ptr as u32 as usize,
+        len as u32 as usize,
+    )
+    .ok_or_else(|| wasmi::Error::new("string is outside the plugin's memory"))?;
+    Ok(String::from_utf8_lossy(bytes).into_owned())
+}
+
+fn memory_slice(data: &[u8], ptr: usize, len: usize) -> Option<&[u8]> {
+    data.get(ptr..ptr.checked_add(len)?)
+}
+
+/// Fuel metering is always on, so every store needs fuel before it can run anything.
+fn engine() -> Engine {
+    let mut config = wasmi::Config::default();
+    config.consume_fuel(true);
+    Engine::new(&config)
+}
+
+/// Plugins are opaque to protox, so the JSON they were given is logged at debug level, which
+/// allows reproducing a failure by calling the plugin directly.
+fn render_error_context(export: &str, module_path: &Path, input: &[u8]) -> String {
+    debug!(
+        "Failed to render plugin '{}' for input: {}",
+        export,
+        String::from_utf8_lossy(input),
+    );
+    format!(
+        "Failed to render '{}' with plugin: {}",
+        export,
+        module_path.display_normalized()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::renderer::context::FileContext;
+    use crate::renderer::tests::{fake_file, fake_message};
+    use crate::renderer::wasm::renderer::WasmRenderer;
+    use crate::renderer::{Renderer, RendererConfig};
+    use anyhow::Result;
+    use serde_json::Value;
+
+    /// Bump allocator shared by the test modules, with `$body` as the rest of the module.
+    fn module(body: &str) -> String {
+        format!(
+            r#"(module
+  (import "protox" "error" (func $error (param i32 i32)))
+  (import "protox" "log" (func $log (param i32 i32)))
+  (memory (export "memory") 2)
+  (global $next (mut i32) (i32.const 1024))
+  (func (export "protox_alloc") (param $len i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $next))
+    (global.set $next (i32.add (global.get $next) (local.get $len)))
+    (local.get $ptr))
+  {}
+)"#,
+            body
+        )
+    }
+
+    /// `protox_render_file` that returns its input, i.e. the file context as JSON.
+    const ECHO_FILE: &str = r#"(func (export "protox_render_file") (param $ptr i32) (param $len i32) (result i64)
+    (i64.or
+      (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+      (i64.extend_i32_u (local.get $len))))"#;
+
+    const INFINITE_LOOP: &str = r#"(func (export "protox_render_file") (param i32 i32) (result i64)
+    (loop $forever (br $forever))
+    (i64.const 0))"#;
+
+    fn renderer(wat: &str) -> Result<WasmRenderer> {
+        let mut renderer = WasmRenderer::with_config(RendererConfig::default());
+        renderer.load_module_bytes(wat.as_bytes())?;
+        Ok(renderer)
+    }
+
+    fn render_file(renderer: &WasmRenderer) -> Result<String> {
+        let file = fake_file("file.proto", vec![], vec![fake_message("Msg", vec![])]);
+        let context = FileContext::new(&file, renderer.config())?;
+        let mut output = Vec::new();
+        renderer.render_file(context, &mut output)?;
+        Ok(String::from_utf8(output)?)
+    }
+
+    #[test]
+    fn file_context_round_trip() -> Result<()> {
+        let renderer = renderer(&module(ECHO_FILE))?;
+        assert!(renderer.has_file());
+        let output: Value = serde_json::from_str(&render_file(&renderer)?)?;
+        assert_eq!(output["source_file"], "file.proto");
+        assert_eq!(output["messages"][0]["name"], "Msg");
+        Ok(())
+    }
+
+    #[test]
+    fn optional_entry_points() -> Result<()> {
+        let renderer = renderer(&module(ECHO_FILE))?;
+        assert!(!renderer.has_metadata());
+        assert!(!renderer.has_message());
+        assert!(!renderer.has_enum());
+        assert!(!renderer.has_all());
+        Ok(())
+    }
+
+    #[test]
+    fn returns_static_output() -> Result<()> {
+        let renderer = renderer(&module(
+            r#"(data (i32.const 0) "generated")
+  (func (export "protox_render_file") (param i32 i32) (result i64)
+    (i64.const 9))"#,
+        ))?;
+        assert_eq!(render_file(&renderer)?, "generated");
+        Ok(())
+    }
+
+    #[test]
+    fn reported_error_fails_render() -> Result<()> {
+        let renderer = renderer(&module(
+            r#"(data (i32.const 0) "unsupported file")
+  (func (export "protox_render_file") (param i32 i32) (result i64)
+    (call $error (i32.const 0) (i32.const 16))
+    (i64.const 0))"#,
+        ))?;
+        let err = render_file(&renderer).unwrap_err();
+        assert!(format!("{:?}", err).contains("unsupported file"));
+        Ok(())
+    }
+
+    #[test]
+    fn trap_fails_render() -> Result<()> {
+        let renderer = renderer(&module(
+            r#"(func (export "protox_render_file") (param i32 i32) (result i64)
+    unreachable)"#,
+        ))?;
+        assert!(render_file(&renderer).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn infinite_loop_runs_out_of_fuel() -> Result<()> {
+        let mut renderer = renderer(&module(INFINITE_LOOP))?;
+        renderer.config.wasm.fuel = 100_000;
+        let err = render_file(&renderer).unwrap_err();
+        assert!(format!("{:?}", err).contains("ran out of fuel"));
+        Ok(())
+    }
+
+    #[test]
+    fn fuel_is_reset_for_each_call() -> Result<()> {
+        let mut renderer = renderer(&module(ECHO_FILE))?;
+        renderer.config.wasm.fuel = 10_000;
+        render_file(&renderer)?;
+        render_file(&renderer)?;
+        Ok(())
+    }
+
+    #[test]
+    fn output_outside_memory_fails_render() -> Result<()> {
+        let renderer = renderer(&module(
+            r#"(func (export "protox_render_file") (param i32 i32) (result i64)
+    (i64.const 0xffffffff))"#,
+        ))?;
+        let err = render_file(&renderer).unwrap_err();
+        assert!(format!("{:?}", err).contains("outside its memory"));
+        Ok(())
+    }
+
+    #[test]
+    fn memory_growth_is_limited() -> Result<()> {
+        // Grows by 16 pages, trapping if that fails.
+        let wat = module(
+            r#"(func (export "protox_render_file") (param i32 i32) (result i64)
+    (if (i32.eq (memory.grow (i32.const 16)) (i32.const -1)) (then unreachable))
+    (i64.const 0))"#,
+        );
+        let mut renderer = renderer(&wat)?;
+        render_file(&renderer)?;
+        renderer.config.wasm.max_memory = 4 * 65536;
+        assert!(render_file(&renderer).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn missing_export_fails_render() -> Result<()> {
+        let renderer = renderer(&module(""))?;
+        assert!(!renderer.has_file());
+        assert!(render_file(&renderer).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn wasi_imports_are_rejected() {
+        let wat = r#"(module
+  (import "wasi_snapshot_preview1" "fd_write" (func (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (func (export "protox_alloc") (param i32) (result i32) (i32.const 0)))"#;
+        let err = renderer(wat).err().unwrap();
+        assert!(err.to_string().contains("wasi_snapshot_preview1.fd_write"));
+    }
+
+    #[test]
+    fn missing_alloc_is_rejected() {
+        assert!(renderer(r#"(module (memory (export "memory") 1))"#).is_err());
+    }
+
+    mod load {
+        use crate::renderer::wasm::renderer::WasmRenderer;
+        use crate::renderer::{Renderer, RendererConfig};
+        use anyhow::Result;
+        use std::fs;
+        use tempfile::tempdir;
+
+        #[test]
+        fn missing_module_errors() -> Result<()> {
+            let dir = tempdir()?;
+            fs::write(
+                dir.path().join("config.yaml"),
+                serde_yaml::to_string(&RendererConfig::default())?,
+            )?;
+            let mut renderer = WasmRenderer::new();
+            assert!(renderer.load(dir.path(), &[]).is_err());
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::renderer::proto::normalize_prefix;
+use crate::renderer::{primitive, RendererConfig};
+
+pub const PACKAGE_PREFIX: &str = "google.protobuf.";
+
+/// Maps `google.protobuf.*` well-known types to native types.
+///
+/// Explicit entries in `type_config` always take precedence over these.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct WellKnownTypesConfig {
+    /// Native types for the well-known messages of a target language, e.g. `Date` for
+    /// `Timestamp` in typescript. Wrapper types don't depend on the preset.
+    ///
+    /// One of: rust, typescript, go, csharp
+    #[serde(default)]
+    pub preset: Option<WellKnownPreset>,
+
+    /// Native types by well-known type name without the package, overriding the preset.
+    /// ```txt
+    /// e.g.
+    /// {
+    ///     "Timestamp": "MyTime",
+    ///     "Duration": "MyDuration"
+    /// }
+    /// ```
+    #[serde(default)]
+    pub types: HashMap<String, String>,
+
+    /// If true, fields with wrapper types like `StringValue` are marked `is_nullable`, since
+    /// they map to their scalar type.
+    /// default: true
+    #[serde(default = "default_nullable_wrappers")]
+    pub nullable_wrappers: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum WellKnownPreset {
+    Rust,
+    TypeScript,
+    Go,
+    CSharp,
+}
+
+impl Default for WellKnownTypesConfig {
+    fn default() -> Self {
+        Self {
+            preset: None,
+            types: HashMap::new(),
+            nullable_wrappers: default_nullable_wrappers(),
+        }
+    }
+}
+
+fn default_nullable_wrappers() -> bool {
+    true
+}
+
+const WRAPPERS: &[(&str, &str)] = &[
+    ("DoubleValue", primitive::DOUBLE),
+    ("FloatValue", primitive::FLOAT),
+    ("Int64Value", primitive::INT64),
+    ("UInt64Value", primitive::UINT64),
+    ("Int32Value", primitive::INT32),
+    ("UInt32Value", primitive::UINT32),
+    ("BoolValue", primitive::BOOL),
+    ("StringValue", primitive::STRING),
+    ("BytesValue", primitive::BYTES),
+];
+
+const RUST: &[(&str, &str)] = &[
+    ("Timestamp", "::prost_types::Timestamp"),
+    ("Duration", "::prost_types::Duration"),
+    ("Any", "::prost_types::Any"),
+    ("Struct", "::prost_types::Struct"),
+    ("Value", "::prost_types::Value"),
+    ("ListValue", "::prost_types::ListValue"),
+    ("FieldMask", "::prost_types::FieldMask"),
+    ("Empty", "()"),
+];
+
+// Types follow the proto3 JSON mapping.
+const TYPESCRIPT: &[(&str, &str)] = &[
+    ("Timestamp", "Date"),
+    ("Duration", "string"),
+    ("Any", "{ \"@type\": string; [key: string]: unknown }"),
+    ("Struct", "Record<string, unknown>"),
+    ("Value", "unknown"),
+    ("ListValue", "unknown[]"),
+    ("FieldMask", "string"),
+    ("Empty", "Record<string, never>"),
+];
+
+const GO: &[(&str, &str)] = &[
+    ("Timestamp", "time.Time"),
+    ("Duration", "time.Duration"),
+    ("Any", "*anypb.Any"),
+    ("Struct", "map[string]interface{}"),
+    ("Value", "interface{}"),
+    ("ListValue", "[]interface{}"),
+    ("FieldMask", "[]string"),
+    ("Empty", "struct{}"),
+];
+
+const CSHARP: &[(&str, &str)] = &[
+    ("Timestamp", "DateTime"),
+    ("Duration", "TimeSpan"),
+    ("Any", "Google.Protobuf.WellKnownTypes.Any"),
+    ("Struct", "Google.Protobuf.WellKnownTypes.Struct"),
+    ("Value", "Google.Protobuf.WellKnownTypes.Value"),
+    ("ListValue", "Google.Protobuf.WellKnownTypes.ListValue"),
+    ("FieldMask", "Google.Protobuf.WellKnownTypes.FieldMask"),
+    ("Empty", "Google.Protobuf.WellKnownTypes.Empty"),
+];
+
+impl WellKnownPreset {
+    fn types(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            WellKnownPreset::Rust => RUST,
+            WellKnownPreset::TypeScript => TYPESCRIPT,
+            WellKnownPreset::Go => GO,
+            WellKnownPreset::CSharp => CSHARP,
+        }
+    }
+}
+
+/// Name of a `google.protobuf.*` type without the package, e.g. `Timestamp`.
+pub fn well_known_name(type_name: &str) -> Option<&str> {
+    normalize_prefix(type_name).strip_prefix(PACKAGE_PREFIX)
+}
+
+/// Native type of a well-known type, if `well_known_types` is configured and has one.
+///
+/// Wrapper types map to the `type_config` of their scalar, e.g. `StringValue` to `string`.
+pub fn native_type<'a>(type_name: &str, config: &'a RendererConfig) -> Result<Option<&'a str>> {
+    let (well_known_config, name) = match (&config.well_known_types, well_known_name(type_name)) {
+        (Some(well_known_config), Some(name)) => (well_known_config, name),
+        _ => return Ok(None),
+    };
+    if let Some(native_type) = well_known_config.types.get(name) {
+        return Ok(Some(native_type));
+    }
+    if let Some(scalar) = wrapped_scalar(name) {
+        return match config.type_config.get(scalar) {
+            None => Err(anyhow!(
+                "No native type is configured for proto primitive '{}', needed by '{}'",
+                scalar,
+                type_name
+            )),
+            Some(native_type) => Ok(Some(native_type)),
+        };
+    }
+    Ok(well_known_config
+        .preset
+        .and_then(|preset| find(preset.types(), name)))
+}
+
+/// True if `type_name` is a wrapper type mapped to its scalar and `nullable_wrappers` is set.
+pub fn is_nullable(type_name: &str, config: &RendererConfig) -> bool {
+    let well_known_config = match &config.well_known_types {
+        None => return false,
+        Some(well_known_config) => well_known_config,
+    };
+    well_known_config.nullable_wrappers
+        && !config.type_config.contains_key(normalize_prefix(type_name))
+        && well_known_name(type_name)
+            .filter(|name| !well_known_config.types.contains_key(*name))
+            .and_then(wrapped_scalar)
+            .is_some()
+}
+
+fn wrapped_scalar(name: &str) -> Option<&'static str> {
+    find(WRAPPERS, name)
+}
+
+fn find(types: &'static [(&'static str, &'static str)], name: &str) -> Option<&'static str> {
+    types
+        .iter()
+        .find(|(well_known, _)| *well_known == name)
+        .map(|(_, native_type)| *native_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::renderer::well_known::{
+        is_nullable, native_type, WellKnownPreset, WellKnownTypesConfig,
+    };
+    use crate::renderer::RendererConfig;
+
+    #[test]
+    fn not_configured() -> Result<()> {
+        let config = RendererConfig::default();
+        assert_eq!(native_type(".google.protobuf.StringValue", &config)?, None);
+        assert!(!is_nullable(".google.protobuf.StringValue", &config));
+        Ok(())
+    }
+
+    #[test]
+    fn wrapper_uses_scalar_type_config() -> Result<()> {
+        let mut config = config(None);
+        config
+            .type_config
+            .insert("int64".to_owned(), "i64".to_owned());
+        assert_eq!(
+            native_type(".google.protobuf.Int64Value", &config)?,
+            Some("i64")
+        );
+        assert!(is_nullable(".google.protobuf.Int64Value", &config));
+        Ok(())
+    }
+
+    #[test]
+    fn nullable_wrappers_disabled() {
+        let mut config = config(None);
+        config.well_known_types.as_mut().unwrap().nullable_wrappers = false;
+        assert!(!is_nullable(".google.protobuf.Int64Value", &config));
+    }
+
+    #[test]
+    fn preset() -> Result<()> {
+        let config = config(Some(WellKnownPreset::TypeScript));
+        assert_eq!(
+            native_type(".google.protobuf.Timestamp", &config)?,
+            Some("Date")
+        );
+        assert!(!is_nullable(".google.protobuf.Timestamp", &config));
+        Ok(())
+    }
+
+    #[test]
+    fn no_preset() -> Result<()> {
+        let config = config(None);
+        assert_eq!(native_type(".google.protobuf.Timestamp", &config)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn override_preset() -> Result<()> {
+        let mut config = config(Some(WellKnownPreset::TypeScript));
+        config
+            .well_known_types
+            .as_mut()
+            .unwrap()
+            .types
+            .insert("Timestamp".to_owned(), "MyTime".to_owned());
+        assert_eq!(
+            native_type("google.protobuf.Timestamp", &config)?,
+            Some("MyTime")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn ignores_other_packages() -> Result<()> {
+        let config = config(Some(WellKnownPreset::TypeScript));
+        assert_eq!(native_type(".my.Timestamp", &config)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn preset_names() -> Result<()> {
+        let config: WellKnownTypesConfig = serde_yaml::from_str("preset: typescript")?;
+        assert_eq!(config.preset, Some(WellKnownPreset::TypeScript));
+        assert!(config.nullable_wrappers);
+        Ok(())
+    }
+
+    fn config(preset: Option<WellKnownPreset>) -> RendererConfig {
+        RendererConfig {
+            well_known_types: Some(WellKnownTypesConfig {
+                preset,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+}
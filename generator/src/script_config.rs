@@ -1,5 +1,6 @@
 use crate::{util, InOutConfig};
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 #[derive(Clone)]
@@ -8,6 +9,8 @@ pub struct ScriptConfig {
     pub input: PathBuf,
     pub output: PathBuf,
     pub overlays: Vec<PathBuf>,
+    /// Merged over the `params` in the script set's config.
+    pub params: HashMap<String, serde_yaml::Value>,
 }
 
 impl ScriptConfig {
@@ -27,8 +30,38 @@ impl ScriptConfig {
                 .iter()
                 .filter_map(|x| util::path_as_absolute(x, script_root).ok())
                 .collect::<Vec<PathBuf>>(),
+            params: HashMap::new(),
         })
     }
+
+    /// Adds each `KEY=VALUE` param, where VALUE is parsed as yaml so e.g. `true` and `5` keep
+    /// their types. Later params override earlier ones with the same key.
+    pub fn with_params(mut self, params: &[&str]) -> Result<Self> {
+        for param in params {
+            self.set_param(param)?;
+        }
+        Ok(self)
+    }
+
+    /// Adds a single `KEY=VALUE` param, overriding any param with the same key.
+    pub fn set_param(&mut self, param: &str) -> Result<()> {
+        let (key, value) = parse_param(param)?;
+        self.params.insert(key, value);
+        Ok(())
+    }
+}
+
+fn parse_param(param: &str) -> Result<(String, serde_yaml::Value)> {
+    let (key, value) = param
+        .split_once('=')
+        .filter(|(key, _)| !key.is_empty())
+        .ok_or_else(|| anyhow!("Script param '{}' must be in the form KEY=VALUE.", param))?;
+    let value = match value {
+        "" => serde_yaml::Value::String(String::new()),
+        value => serde_yaml::from_str(value)
+            .unwrap_or_else(|_| serde_yaml::Value::String(value.to_owned())),
+    };
+    Ok((key.to_owned(), value))
 }
 
 impl From<ScriptConfig> for InOutConfig {
@@ -58,6 +91,39 @@ mod tests {
     use std::env;
     use std::path::PathBuf;
 
+    #[test]
+    fn params() -> Result<()> {
+        let config = ScriptConfig::from_config(
+            "name",
+            None,
+            None,
+            Some(&PathBuf::new()),
+            Some(&PathBuf::new()),
+            &[],
+        )?
+        .with_params(&["a=true", "b=5", "c=some text", "d=", "b=6"])?;
+        assert_eq!(config.params["a"], serde_yaml::Value::Bool(true));
+        assert_eq!(config.params["b"], serde_yaml::Value::Number(6.into()));
+        assert_eq!(config.params["c"], serde_yaml::Value::from("some text"));
+        assert_eq!(config.params["d"], serde_yaml::Value::from(""));
+        Ok(())
+    }
+
+    #[test]
+    fn params_without_key_error() -> Result<()> {
+        let config = ScriptConfig::from_config(
+            "name",
+            None,
+            None,
+            Some(&PathBuf::new()),
+            Some(&PathBuf::new()),
+            &[],
+        )?;
+        assert!(config.clone().with_params(&["novalue"]).is_err());
+        assert!(config.with_params(&["=value"]).is_err());
+        Ok(())
+    }
+
     #[test]
     fn name() -> Result<()> {
         let config = ScriptConfig::from_config(
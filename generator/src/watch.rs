@@ -0,0 +1,596 @@
+use crate::util::DisplayNormalized;
+use crate::{diagnostic, protoc, run_generators, util, Config, Generator};
+use anyhow::{anyhow, Context, Result};
+use log::{error, info};
+use notify::{watcher, DebouncedEvent, RecursiveMode, Watcher};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+const DEBOUNCE_DURATION: Duration = Duration::from_millis(300);
+
+/// Runs generation once, then watches all inputs and reruns the generators affected by each set
+/// of changes until the process is stopped. Errors from generation are printed instead of
+/// returned so that a broken script or template can be fixed without restarting.
+pub fn watch(config: &Config) -> Result<()> {
+    // Outputs are cleared before every regeneration, so make sure we're only ever clearing
+    // directories that we filled ourselves.
+    check_outputs_are_empty(config)?;
+    regenerate_or_log(config, &Affected::all());
+
+    let mut reloaded: Option<Config> = None;
+    loop {
+        let current = reloaded.as_ref().unwrap_or(config);
+        watch_until_manifest_changes(current)?;
+        info!("Manifest changed, reloading configuration.");
+        match reload(current) {
+            Ok(config) => reloaded = Some(config),
+            // Keep watching with the previous config until the manifest is fixed.
+            Err(err) => diagnostic::emit(&err, current.message_format),
+        }
+    }
+}
+
+fn watch_until_manifest_changes(config: &Config) -> Result<()> {
+    let (tx, rx) = channel();
+    let mut watcher = watcher(tx, DEBOUNCE_DURATION).context("Failed to create file watcher.")?;
+    for path in watched_paths(config)? {
+        watcher
+            .watch(&path, RecursiveMode::Recursive)
+            .with_context(|| format!("Failed to watch path '{}'", path.display_normalized()))?;
+    }
+    loop {
+        let changed = wait_for_changes(&rx)?;
+        if is_manifest_changed(config, &changed) {
+            return Ok(());
+        }
+        let affected = Affected::from_paths(config, &changed);
+        if affected.is_empty() {
+            continue;
+        }
+        regenerate_or_log(config, &affected);
+    }
+}
+
+/// Outputs of the previous config were filled by us, so they're cleared before switching. Any new
+/// outputs must be empty, same as when starting.
+fn reload(previous: &Config) -> Result<Config> {
+    let config = previous.reload()?;
+    clear_outputs(cleared_outputs(previous))?;
+    check_outputs_are_empty(&config)?;
+    regenerate_or_log(&config, &Affected::all());
+    Ok(config)
+}
+
+/// The set of generators that need to be rerun for a set of changed paths.
+#[derive(Default, Debug, PartialEq)]
+struct Affected {
+    protos: bool,
+    templates: bool,
+    jinja: bool,
+    wasm: bool,
+    process: bool,
+    scripts: bool,
+    encode: bool,
+}
+
+impl Affected {
+    fn all() -> Self {
+        Self {
+            protos: true,
+            templates: true,
+            jinja: true,
+            wasm: true,
+            process: true,
+            scripts: true,
+            encode: true,
+        }
+    }
+
+    fn from_paths(config: &Config, paths: &[PathBuf]) -> Self {
+        let mut affected = Self::default();
+        for path in paths {
+            let path = &canonical(path);
+            if is_output_path(config, path) {
+                continue;
+            }
+            if is_proto_path(config, path) {
+                // Everything downstream is built from the descriptor set.
+                return Self::all();
+            }
+            affected.templates |= config
+                .templates
+                .iter()
+                .any(|x| is_in_out_path(path, &x.input, &x.overlays));
+            affected.jinja |= config
+                .jinja
+                .iter()
+                .any(|x| is_in_out_path(path, &x.input, &x.overlays));
+            affected.wasm |= config
+                .wasm
+                .iter()
+                .any(|x| is_in_out_path(path, &x.input, &x.overlays));
+            affected.process |= config
+                .process
+                .iter()
+                .any(|x| is_in_out_path(path, &x.input, &x.overlays));
+            affected.scripts |= config
+                .scripts
+                .iter()
+                .any(|x| is_in_out_path(path, &x.input, &x.overlays))
+                || config
+                    .script_module_paths
+                    .iter()
+                    .any(|x| path.starts_with(canonical(x)));
+            affected.encode |= config.encode.iter().any(|x| *path == canonical(&x.target));
+        }
+        affected
+    }
+
+    fn generators(&self) -> Vec<Generator> {
+        let renderers = self.templates || self.jinja || self.wasm || self.process || self.scripts;
+        Generator::ALL
+            .iter()
+            .copied()
+            .filter(|generator| match generator {
+                Generator::Template => self.templates,
+                Generator::Jinja => self.jinja,
+                Generator::Wasm => self.wasm,
+                Generator::Process => self.process,
+                Generator::Script => self.scripts,
+                // Dumps are built using the script and template configs.
+                Generator::Dump => renderers,
+                Generator::Encode => self.encode,
+            })
+            .collect()
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+fn regenerate_or_log(config: &Config, affected: &Affected) {
+    info!("Regenerating: {:?}", affected);
+    match regenerate(config, affected) {
+        Ok(_) => info!("Regeneration complete. Watching for changes..."),
+        Err(err) => diagnostic::emit(&err, config.message_format),
+    }
+}
+
+fn regenerate(config: &Config, affected: &Affected) -> Result<()> {
+    if affected.protos {
+        // The rust generator refuses to write into a non-empty directory.
+        clear_outputs(config.protos.iter().map(|x| &x.output))?;
+        protoc::generate(config)?;
+    }
+    let generators = affected.generators();
+    for generator in &generators {
+        clear_outputs(generator_outputs(config, *generator).into_iter())?;
+    }
+    run_generators(config, &generators)
+}
+
+/// Blocks until at least one change arrives, then keeps collecting until things settle down,
+/// so that e.g. saving several files at once only triggers a single regeneration.
+fn wait_for_changes(rx: &Receiver<DebouncedEvent>) -> Result<Vec<PathBuf>> {
+    let mut changed = Vec::new();
+    let first = rx
+        .recv()
+        .map_err(|_| anyhow!("File watcher stopped unexpectedly."))?;
+    collect_event_paths(first, &mut changed);
+    loop {
+        match rx.recv_timeout(DEBOUNCE_DURATION) {
+            Ok(event) => collect_event_paths(event, &mut changed),
+            Err(RecvTimeoutError::Timeout) => return Ok(changed),
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(anyhow!("File watcher stopped unexpectedly."))
+            }
+        }
+    }
+}
+
+fn collect_event_paths(event: DebouncedEvent, paths: &mut Vec<PathBuf>) {
+    match event {
+        DebouncedEvent::Create(path)
+        | DebouncedEvent::Write(path)
+        | DebouncedEvent::Remove(path)
+        | DebouncedEvent::Chmod(path) => paths.push(path),
+        DebouncedEvent::Rename(from, to) => {
+            paths.push(from);
+            paths.push(to);
+        }
+        DebouncedEvent::Error(err, path) => error!("File watcher error: {} ({:?})", err, path),
+        // Notices are always followed by the real event.
+        DebouncedEvent::NoticeWrite(_)
+        | DebouncedEvent::NoticeRemove(_)
+        | DebouncedEvent::Rescan => {}
+    }
+}
+
+fn watched_paths(config: &Config) -> Result<Vec<PathBuf>> {
+    let mut paths = vec![config.input.clone()];
+    paths.extend(config.manifest_path.iter().cloned());
+    for include in &config.includes {
+        paths.push(absolute(&PathBuf::from(include))?);
+    }
+    for template in &config.templates {
+        paths.push(template.input.clone());
+        paths.extend(template.overlays.iter().cloned());
+    }
+    for jinja in &config.jinja {
+        paths.push(jinja.input.clone());
+        paths.extend(jinja.overlays.iter().cloned());
+    }
+    for wasm in &config.wasm {
+        paths.push(wasm.input.clone());
+        paths.extend(wasm.overlays.iter().cloned());
+    }
+    for process in &config.process {
+        paths.push(process.input.clone());
+        paths.extend(process.overlays.iter().cloned());
+    }
+    for script in &config.scripts {
+        paths.push(script.input.clone());
+        paths.extend(script.overlays.iter().cloned());
+    }
+    paths.extend(config.script_module_paths.iter().cloned());
+    for encode in &config.encode {
+        paths.push(absolute(&encode.target)?);
+    }
+    let mut paths = paths.iter().map(|x| canonical(x)).collect::<Vec<PathBuf>>();
+    // Overlays commonly live inside the input directories, which are already watched.
+    paths.sort();
+    paths.dedup();
+    let mut result: Vec<PathBuf> = Vec::new();
+    for path in paths {
+        if !result.iter().any(|parent| path.starts_with(parent)) {
+            result.push(path);
+        }
+    }
+    Ok(result)
+}
+
+fn is_manifest_changed(config: &Config, paths: &[PathBuf]) -> bool {
+    match &config.manifest_path {
+        None => false,
+        Some(manifest) => {
+            let manifest = canonical(manifest);
+            paths.iter().any(|path| canonical(path) == manifest)
+        }
+    }
+}
+
+fn is_proto_path(config: &Config, path: &Path) -> bool {
+    path.starts_with(canonical(&config.input))
+        || config
+            .includes
+            .iter()
+            .any(|include| path.starts_with(canonical(Path::new(include))))
+}
+
+fn is_in_out_path(path: &Path, input: &Path, overlays: &[PathBuf]) -> bool {
+    path.starts_with(canonical(input)) || overlays.iter().any(|x| *path == canonical(x))
+}
+
+/// Output directories can live inside watched directories, e.g. generating into a subfolder of
+/// `--input`. Those changes are caused by us and must not trigger another regeneration.
+fn is_output_path(config: &Config, path: &Path) -> bool {
+    let outputs = config
+        .encode
+        .iter()
+        .map(|x| &x.output)
+        .chain(cleared_outputs(config));
+    for output in outputs {
+        if path.starts_with(canonical(output)) {
+            return true;
+        }
+    }
+    false
+}
+
+/// Outputs that are cleared before being regenerated.
+fn cleared_outputs(config: &Config) -> impl Iterator<Item = &PathBuf> {
+    let proto_outputs = config.protos.iter().map(|x| &x.output);
+    let generator_outputs = Generator::ALL
+        .iter()
+        .flat_map(move |generator| generator_outputs(config, *generator));
+    proto_outputs.chain(generator_outputs)
+}
+
+/// Outputs cleared before rerunning a generator. Encode outputs are single files that are
+/// simply overwritten.
+fn generator_outputs(config: &Config, generator: Generator) -> Vec<&PathBuf> {
+    match generator {
+        Generator::Template => config.templates.iter().map(|x| &x.output).collect(),
+        Generator::Jinja => config.jinja.iter().map(|x| &x.output).collect(),
+        Generator::Wasm => config.wasm.iter().map(|x| &x.output).collect(),
+        Generator::Process => config.process.iter().map(|x| &x.output).collect(),
+        Generator::Script => config.scripts.iter().map(|x| &x.output).collect(),
+        Generator::Dump => config.dump_context.iter().map(|x| &x.output).collect(),
+        Generator::Encode => vec![],
+    }
+}
+
+fn check_outputs_are_empty(config: &Config) -> Result<()> {
+    for output in cleared_outputs(config) {
+        util::check_dir_is_empty(output).context("--watch requires empty output directories")?;
+    }
+    Ok(())
+}
+
+fn clear_outputs<'a>(outputs: impl Iterator<Item = &'a PathBuf>) -> Result<()> {
+    for output in outputs {
+        if output.exists() {
+            fs::remove_dir_all(output).with_context(|| {
+                format!(
+                    "Failed to clear output directory '{}'",
+                    output.display_normalized()
+                )
+            })?;
+        }
+    }
+    Ok(())
+}
+
+fn absolute(path: &Path) -> Result<PathBuf> {
+    Ok(env::current_dir()?.join(path))
+}
+
+/// Event paths are absolute with symlinks resolved, while configured paths may be relative or go
+/// through symlinks, so both are made canonical before comparing. Removed files can't be
+/// canonicalized, so their closest existing parent is used instead.
+fn canonical(path: &Path) -> PathBuf {
+    let path = absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    if let Ok(canonical) = fs::canonicalize(&path) {
+        return canonical;
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(file_name)) => canonical(parent).join(file_name),
+        _ => path,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::script_config::ScriptConfig;
+    use crate::watch::{
+        check_outputs_are_empty, clear_outputs, cleared_outputs, is_manifest_changed,
+        watched_paths, Affected,
+    };
+    use crate::{Config, Generator, InOutConfig, LangConfig};
+    use anyhow::Result;
+    use std::env::current_dir;
+    use std::fs;
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    #[test]
+    fn proto_change_affects_all() {
+        let config = test_config();
+        let affected = Affected::from_paths(&config, &[PathBuf::from("/in/some.proto")]);
+        assert_eq!(affected, Affected::all());
+    }
+
+    #[test]
+    fn template_change_affects_templates() {
+        let config = test_config();
+        let affected = Affected::from_paths(&config, &[PathBuf::from("/templates/a/file.hbs")]);
+        assert_eq!(
+            affected,
+            Affected {
+                templates: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn jinja_change_affects_jinja() {
+        let mut config = test_config();
+        config.jinja = vec![InOutConfig {
+            input: PathBuf::from("/templates/j"),
+            output: PathBuf::from("/out/j"),
+            overlays: vec![],
+        }];
+        let affected = Affected::from_paths(&config, &[PathBuf::from("/templates/j/file.jinja")]);
+        assert_eq!(
+            affected,
+            Affected {
+                jinja: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn wasm_change_affects_wasm() {
+        let mut config = test_config();
+        config.wasm = vec![InOutConfig {
+            input: PathBuf::from("/plugins/w"),
+            output: PathBuf::from("/out/w"),
+            overlays: vec![],
+        }];
+        let affected = Affected::from_paths(&config, &[PathBuf::from("/plugins/w/plugin.wasm")]);
+        assert_eq!(
+            affected,
+            Affected {
+                wasm: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn script_overlay_change_affects_scripts() {
+        let config = test_config();
+        let affected = Affected::from_paths(&config, &[PathBuf::from("/overlays/b.yml")]);
+        assert_eq!(
+            affected,
+            Affected {
+                scripts: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn script_module_path_change_affects_scripts() {
+        let mut config = test_config();
+        config.script_module_paths = vec![PathBuf::from("/shared")];
+        let affected = Affected::from_paths(&config, &[PathBuf::from("/shared/util.rhai")]);
+        assert_eq!(
+            affected,
+            Affected {
+                scripts: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn relative_input_matches_absolute_event_path() -> Result<()> {
+        let mut config = test_config();
+        config.input = PathBuf::from("relative/in");
+        let event_path = current_dir()?.join("relative/in/some.proto");
+        let affected = Affected::from_paths(&config, &[event_path]);
+        assert_eq!(affected, Affected::all());
+        Ok(())
+    }
+
+    #[test]
+    fn relative_script_module_path_affects_scripts() -> Result<()> {
+        let mut config = test_config();
+        config.script_module_paths = vec![PathBuf::from("relative/shared")];
+        let event_path = current_dir()?.join("relative/shared/util.rhai");
+        let affected = Affected::from_paths(&config, &[event_path]);
+        assert_eq!(
+            affected,
+            Affected {
+                scripts: true,
+                ..Default::default()
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn symlinked_input_matches_event_path() -> Result<()> {
+        let dir = tempdir()?;
+        let real = dir.path().join("real");
+        fs::create_dir(&real)?;
+        std::os::unix::fs::symlink(&real, dir.path().join("link"))?;
+        let mut config = test_config();
+        config.input = dir.path().join("link");
+        let affected = Affected::from_paths(&config, &[fs::canonicalize(&real)?.join("a.proto")]);
+        assert_eq!(affected, Affected::all());
+        Ok(())
+    }
+
+    #[test]
+    fn manifest_change_is_detected() {
+        let mut config = test_config();
+        config.manifest_path = Some(PathBuf::from("/project/protox.yml"));
+        assert!(is_manifest_changed(
+            &config,
+            &[PathBuf::from("/project/protox.yml")]
+        ));
+        assert!(!is_manifest_changed(
+            &config,
+            &[PathBuf::from("/project/other.yml")]
+        ));
+        assert!(!is_manifest_changed(
+            &test_config(),
+            &[PathBuf::from("/project/protox.yml")]
+        ));
+    }
+
+    #[test]
+    fn watched_paths_include_manifest_and_module_paths() -> Result<()> {
+        let mut config = test_config();
+        config.manifest_path = Some(PathBuf::from("/project/protox.yml"));
+        config.script_module_paths = vec![PathBuf::from("/shared")];
+        let paths = watched_paths(&config)?;
+        assert!(paths.contains(&PathBuf::from("/project/protox.yml")));
+        assert!(paths.contains(&PathBuf::from("/shared")));
+        Ok(())
+    }
+
+    #[test]
+    fn output_change_is_ignored() {
+        let mut config = test_config();
+        config.templates[0].output = PathBuf::from("/in/generated");
+        let affected = Affected::from_paths(&config, &[PathBuf::from("/in/generated/file.txt")]);
+        assert!(affected.is_empty());
+    }
+
+    #[test]
+    fn watched_paths_skips_nested() -> Result<()> {
+        let mut config = test_config();
+        config.templates[0].overlays = vec![PathBuf::from("/templates/a/overlay.yml")];
+        let paths = watched_paths(&config)?;
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("/in"),
+                PathBuf::from("/overlays/b.yml"),
+                PathBuf::from("/scripts/b"),
+                PathBuf::from("/templates/a"),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn proto_outputs_are_checked_and_cleared() -> Result<()> {
+        let dir = tempdir()?;
+        let output = dir.path().join("rust");
+        fs::create_dir(&output)?;
+        fs::write(output.join("mod.rs"), "")?;
+        let mut config = Config::default();
+        config.protos = vec![LangConfig::from_config(
+            "rust",
+            "rust",
+            Some(&dir.path().to_path_buf()),
+        )?];
+        assert!(check_outputs_are_empty(&config).is_err());
+        clear_outputs(cleared_outputs(&config))?;
+        assert!(!output.exists());
+        check_outputs_are_empty(&config)?;
+        Ok(())
+    }
+
+    #[test]
+    fn renderer_change_also_reruns_dump() {
+        let affected = Affected {
+            templates: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            affected.generators(),
+            vec![Generator::Template, Generator::Dump]
+        );
+        assert_eq!(Affected::all().generators(), Generator::ALL.to_vec());
+    }
+
+    fn test_config() -> Config {
+        let mut config = Config::default();
+        config.input = PathBuf::from("/in");
+        config.templates = vec![InOutConfig {
+            input: PathBuf::from("/templates/a"),
+            output: PathBuf::from("/out/a"),
+            overlays: vec![],
+        }];
+        config.scripts = vec![ScriptConfig {
+            name: "b".to_owned(),
+            input: PathBuf::from("/scripts/b"),
+            output: PathBuf::from("/out/b"),
+            overlays: vec![PathBuf::from("/overlays/b.yml")],
+            params: Default::default(),
+        }];
+        config
+    }
+}